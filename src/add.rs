@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    config::repo_name, edit::ConfigDocument, restore, Config, Reporter, RestoreOption,
+    WorkspacesError,
+};
+
+/// Adds a git project to the workspace at `ws_path` in the config file and
+/// clones it with the settings it inherits. Unless `force` is set, refuses to
+/// when that would drop comments from the config file.
+pub fn add_project(
+    config: &Config,
+    ws_path: PathBuf,
+    repo: String,
+    name: Option<String>,
+    force: bool,
+    reporter: &dyn Reporter,
+) -> Result<PathBuf, WorkspacesError> {
+    let keys = config.workspace_keys(&ws_path)?.to_vec();
+    let name = match name {
+        Some(name) => name,
+        None => repo_name(&repo)?,
+    };
+    let proj_path = config.resolve_path(&ws_path).join(&name);
+    if config.lookup_project(&proj_path).is_ok() || config.lookup_workspace(&proj_path).is_ok() {
+        return Err(anyhow!("{} is already managed", proj_path.to_string_lossy()).into());
+    }
+
+    let mut git = Mapping::new();
+    git.insert("repo".into(), repo.into());
+    let mut project = Mapping::new();
+    project.insert("git".into(), Value::Mapping(git));
+
+    let mut doc = ConfigDocument::load(force)?;
+    doc.insert_project(&keys, &name, Value::Mapping(project))?;
+    let config = doc.save()?;
+
+    restore(
+        &config,
+        RestoreOption::Project {
+            proj_path: proj_path.clone(),
+        },
+        1,
+        reporter,
+    )
+    .with_context(|| {
+        format!(
+            "Added {name} to the config but could not clone it, retry with `workspaces restore project`"
+        )
+    })?;
+
+    Ok(proj_path)
+}
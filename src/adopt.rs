@@ -0,0 +1,271 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    edit::ConfigDocument,
+    expand_home,
+    git::{GitCloneProtocol, GitCloneStrategy, GitHost},
+    path_buf_to_string, scan, Config, GitConfig, WorkspacesError,
+};
+
+pub struct AdoptOptions {
+    /// How many directory levels below the scanned path to look for clones
+    pub depth: usize,
+    /// Report what would be adopted without writing the config
+    pub dry_run: bool,
+    /// Write the config even if that drops its comments
+    pub force: bool,
+}
+
+pub enum AdoptStatus {
+    /// Added with the repo its origin remote points at
+    Adopted(String),
+    /// Added as a plain directory since no git settings could be inferred
+    AdoptedWithoutGit(String),
+    Skipped(String),
+}
+
+pub struct AdoptEntry {
+    pub path: PathBuf,
+    pub status: AdoptStatus,
+}
+
+pub struct AdoptReport {
+    pub config_path: PathBuf,
+    pub dry_run: bool,
+    pub entries: Vec<AdoptEntry>,
+    /// Clones found that the config already has
+    pub managed: usize,
+}
+
+impl AdoptReport {
+    pub fn adopted(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| !matches!(e.status, AdoptStatus::Skipped(_)))
+            .count()
+    }
+
+    pub fn print(&self) {
+        for e in self.entries.iter() {
+            let path = e.path.to_string_lossy();
+            match &e.status {
+                AdoptStatus::Adopted(repo) => println!("adopted\t{path}\t{repo}"),
+                AdoptStatus::AdoptedWithoutGit(reason) => {
+                    println!("adopted\t{path}\twithout git settings, {reason}")
+                }
+                AdoptStatus::Skipped(reason) => println!("skipped\t{path}\t{reason}"),
+            }
+        }
+        if !self.entries.is_empty() {
+            println!();
+        }
+
+        if self.managed > 0 {
+            println!("{} clones found are already in the config", self.managed);
+        }
+        let config_path = self.config_path.to_string_lossy();
+        if self.dry_run {
+            println!(
+                "{} projects would be added to {config_path}, run without --dry-run to write them",
+                self.adopted()
+            );
+        } else {
+            println!("Added {} projects to {config_path}", self.adopted());
+        }
+    }
+}
+
+/// Shortens paths under the home directory to `~/...` for the config file.
+fn display_root(root: &Path) -> Result<String> {
+    match home::home_dir().and_then(|h| root.strip_prefix(h).ok().map(Path::to_path_buf)) {
+        Some(rel) => path_buf_to_string(Path::new("~").join(rel)),
+        None => path_buf_to_string(root.to_path_buf()),
+    }
+}
+
+/// Keys of the deepest configured workspace containing `rel_dir` and the git
+/// settings projects in it inherit, plus the directories below it that still
+/// need a workspace.
+pub(crate) fn nearest_workspace(
+    config: Option<&Config>,
+    root: &Path,
+    rel_dir: &Path,
+) -> (Vec<String>, GitConfig, Vec<String>) {
+    let components = rel_dir
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>();
+    let Some(config) = config else {
+        return (vec![], GitConfig::default(), components);
+    };
+
+    for depth in (1..=components.len()).rev() {
+        let ws_path = root.join(components[..depth].join("/"));
+        if let Ok(keys) = config.workspace_keys(&ws_path) {
+            let inherited = config
+                .lookup_workspace(&ws_path)
+                .ok()
+                .and_then(|ws| ws.git.clone())
+                .unwrap_or_else(|| config.git.clone());
+            return (keys.to_vec(), inherited, components[depth..].to_vec());
+        }
+    }
+
+    (vec![], config.git.clone(), components)
+}
+
+/// Project entry for a clone, only spelling out settings that differ from
+/// what the project would inherit.
+pub(crate) fn git_project(
+    host: GitHost,
+    protocol: GitCloneProtocol,
+    repo: &str,
+    worktree: bool,
+    inherited: &GitConfig,
+) -> Result<Value> {
+    let strategy = if worktree {
+        GitCloneStrategy::Worktree
+    } else {
+        GitCloneStrategy::Branch
+    };
+
+    let mut git = Mapping::new();
+    git.insert("repo".into(), repo.into());
+    if inherited.host.clone().unwrap_or(GitHost::GitHub) != host {
+        git.insert("host".into(), serde_yaml::to_value(host)?);
+    }
+    if inherited
+        .protocol
+        .clone()
+        .unwrap_or(GitCloneProtocol::Https)
+        != protocol
+    {
+        git.insert("protocol".into(), serde_yaml::to_value(protocol)?);
+    }
+    if inherited
+        .clone_strategy
+        .clone()
+        .unwrap_or(GitCloneStrategy::Branch)
+        != strategy
+    {
+        git.insert("clone_strategy".into(), serde_yaml::to_value(strategy)?);
+    }
+
+    let mut project = Mapping::new();
+    project.insert("git".into(), Value::Mapping(git));
+    Ok(Value::Mapping(project))
+}
+
+/// Scans `path` (the root by default) for clones that are not in the config
+/// yet and adds them, creating the config when there is none. The directory
+/// layout below the root becomes the workspace layout.
+pub fn adopt(path: Option<PathBuf>, opts: &AdoptOptions) -> Result<AdoptReport, WorkspacesError> {
+    let config = if Config::file_path()?.exists() {
+        Some(Config::from_config_file()?)
+    } else {
+        None
+    };
+
+    let (root, scan_dir, mut doc) = match &config {
+        Some(c) => {
+            let dir = match path {
+                Some(p) => c.resolve_path(&expand_home(p)),
+                None => c.root.clone(),
+            };
+            (c.root.clone(), dir, ConfigDocument::load(opts.force)?)
+        }
+        None => {
+            let dir = path.ok_or_else(|| {
+                anyhow!("No config found, pass the directory to adopt and it becomes the root")
+            })?;
+            let dir = std::path::absolute(expand_home(dir))
+                .context("Tried resolving directory to adopt")?;
+            let dir = dir.components().collect::<PathBuf>();
+            let doc = ConfigDocument::new(&display_root(&dir)?)?;
+            (dir.clone(), dir, doc)
+        }
+    };
+    if !scan_dir.starts_with(&root) {
+        return Err(anyhow!(
+            "{} is outside the root {}",
+            scan_dir.to_string_lossy(),
+            root.to_string_lossy()
+        )
+        .into());
+    }
+    if !scan_dir.is_dir() {
+        return Err(anyhow!("{} is not a directory", scan_dir.to_string_lossy()).into());
+    }
+
+    let mut entries = vec![];
+    let mut managed = 0;
+    for found in scan::scan(&scan_dir, opts.depth)? {
+        let path = found.path;
+        let skip = |reason: &str| AdoptEntry {
+            path: path.clone(),
+            status: AdoptStatus::Skipped(reason.to_string()),
+        };
+
+        if let Some(c) = config.as_ref() {
+            if c.lookup_project(&path).is_ok() || c.lookup_workspace(&path).is_ok() {
+                managed += 1;
+                continue;
+            }
+            if path
+                .ancestors()
+                .skip(1)
+                .any(|a| c.lookup_project(a).is_ok())
+            {
+                entries.push(skip("inside another project"));
+                continue;
+            }
+        }
+
+        let rel_path = path.strip_prefix(&root).unwrap();
+        let (Some(name), Some(rel_dir)) = (rel_path.file_name(), rel_path.parent()) else {
+            continue;
+        };
+        if rel_dir.as_os_str().is_empty() {
+            entries.push(skip("directly under the root, move it into a workspace"));
+            continue;
+        }
+
+        let (mut keys, inherited, missing) = nearest_workspace(config.as_ref(), &root, rel_dir);
+        keys.extend(missing);
+
+        let (project, status) = match found.origin {
+            Some(origin) => match GitHost::parse_url(&origin) {
+                Some((host, protocol, repo)) => (
+                    git_project(host, protocol, &repo, found.worktree, &inherited)?,
+                    AdoptStatus::Adopted(repo),
+                ),
+                None => (
+                    Value::Null,
+                    AdoptStatus::AdoptedWithoutGit(format!("unsupported origin {origin}")),
+                ),
+            },
+            None => (
+                Value::Null,
+                AdoptStatus::AdoptedWithoutGit("no origin remote".to_string()),
+            ),
+        };
+
+        doc.insert_project(&keys, &name.to_string_lossy(), project)?;
+        entries.push(AdoptEntry { path, status });
+    }
+
+    let report = AdoptReport {
+        config_path: Config::file_path()?,
+        dry_run: opts.dry_run,
+        entries,
+        managed,
+    };
+    if !opts.dry_run && report.adopted() > 0 {
+        doc.save()?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,206 @@
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Context};
+use flate2::{write::GzEncoder, Compression};
+use tracing::instrument;
+
+use crate::{
+    data_dir, expand_home,
+    git::Git,
+    lock::Lock,
+    state::{self, now},
+    Config, Reporter, WorkspacesError,
+};
+
+/// Packs the project `query` names, see `Config::find_project`, into a
+/// `.tar.gz` in `archive_dir` and removes it, returning the archive. Unless
+/// `force` is set, refuses to when the clone holds work that is not pushed.
+#[instrument(level = "debug", skip(config, reporter))]
+pub fn archive_project(
+    config: &Config,
+    query: &str,
+    force: bool,
+    reporter: &dyn Reporter,
+) -> Result<PathBuf, WorkspacesError> {
+    let proj_path = config.find_project(query)?;
+    if !proj_path.exists() {
+        return Err(anyhow!("{} does not exist", proj_path.to_string_lossy()).into());
+    }
+    let _lock = Lock::acquire(&config.lock_dir(), reporter)?;
+
+    let project = config.lookup_project(&proj_path)?;
+    if let (Some(proj_git), false) = (&project.git, force) {
+        let work = Git::new(proj_path.clone(), proj_git.clone())
+            .unpushed_work()
+            .context("Tried checking for unpushed work")?;
+        if !work.is_empty() {
+            return Err(anyhow!(
+                "{} holds work that is not pushed, push it or pass --force:\n\t{}",
+                proj_path.to_string_lossy(),
+                work.join("\n\t")
+            )
+            .into());
+        }
+    }
+
+    let dir = match &config.archive_dir {
+        Some(dir) => expand_home(dir),
+        None => data_dir().join("archives"),
+    };
+    fs::create_dir_all(&dir).map_err(|source| WorkspacesError::Io {
+        path: dir.clone(),
+        source,
+    })?;
+    let relative = config.relative_path(&proj_path);
+    let name = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("-");
+    let archive = dir.join(format!("{name}-{}.tar.gz", now()));
+
+    let file = File::create(&archive)
+        .with_context(|| format!("Tried creating {}", archive.to_string_lossy()))?;
+    let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    tar.follow_symlinks(false);
+    let written = tar
+        .append_dir_all(&name, &proj_path)
+        .and_then(|_| tar.into_inner())
+        .and_then(|gz| gz.finish());
+    if let Err(e) = written {
+        // A partial archive would pass for a backup of the project
+        let _ = fs::remove_file(&archive);
+        return Err(anyhow::Error::new(e)
+            .context(format!("Tried writing {}", archive.to_string_lossy()))
+            .into());
+    }
+
+    // Marked first, so a removal that stops halfway leaves a project that is
+    // known to be archived rather than one restore takes as complete
+    state::record_archive(config, &proj_path, &archive)
+        .context("Tried marking the project archived")?;
+    fs::remove_dir_all(&proj_path).with_context(|| {
+        format!(
+            "Tried removing {}, which is archived in {}, remove what is left of it by hand",
+            proj_path.to_string_lossy(),
+            archive.to_string_lossy()
+        )
+    })?;
+    Ok(archive)
+}
+
+#[cfg(test)]
+mod should {
+    use std::{fs, path::Path};
+
+    use rstest::*;
+
+    use super::archive_project;
+    use crate::{state, Config, SilentReporter};
+
+    /// A config with the project `w0/p0`, archived into `<root>/.archives`,
+    /// and that project as a clone whose commit origin has.
+    fn config_with_clone(root: &Path) -> (Config, git2::Repository) {
+        let contents = format!(
+            "root: {0}\narchive_dir: {0}/.archives\nworkspaces:\n  w0:\n    projects:\n      p0:\n        git: {{ repo: czifro/p0 }}\n",
+            root.to_string_lossy()
+        );
+        let mut config = Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+
+        let repo = git2::Repository::init(root.join("w0/p0")).unwrap();
+        fs::write(root.join("w0/p0/a"), "a").unwrap();
+        let oid = commit(&repo, "base");
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+        repo.reference(&format!("refs/remotes/origin/{branch}"), oid, true, "")
+            .unwrap();
+        (config, repo)
+    }
+
+    /// Commits the whole working tree onto HEAD.
+    fn commit(repo: &git2::Repository, message: &str) -> git2::Oid {
+        let sig = git2::Signature::now("workspaces", "workspaces@example.com").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents = parent.iter().collect::<Vec<_>>();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+            .unwrap()
+    }
+
+    #[rstest]
+    #[case::dirty("dirty")]
+    #[case::unpushed("unpushed")]
+    fn refuse_unpushed_work_without_force(#[case] work: &str) {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-archive-{work}-{}", std::process::id()));
+        let (config, repo) = config_with_clone(&root);
+        fs::write(root.join("w0/p0/a"), "changed").unwrap();
+        if work == "unpushed" {
+            commit(&repo, "local");
+        }
+
+        let refused = archive_project(&config, "p0", false, &SilentReporter);
+        let kept = root.join("w0/p0/a").exists();
+        let forced = archive_project(&config, "p0", true, &SilentReporter);
+        let removed = !root.join("w0/p0").exists();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(refused.is_err_and(|e| e.to_string().contains("--force")));
+        assert!(kept);
+        assert!(forced.is_ok());
+        assert!(removed);
+    }
+
+    #[rstest]
+    fn archive_the_tree_and_mark_it_archived() {
+        let root = std::env::temp_dir().join(format!("workspaces-archive-{}", std::process::id()));
+        let (config, _) = config_with_clone(&root);
+
+        let archive = archive_project(&config, "p0", false, &SilentReporter).unwrap();
+        let gz = flate2::read::GzDecoder::new(fs::File::open(&archive).unwrap());
+        let mut entries = tar::Archive::new(gz)
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_path_buf())
+            .collect::<Vec<_>>();
+        entries.sort();
+        let removed = !root.join("w0/p0").exists();
+        let archived = state::archived_projects(&config);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(archive.starts_with(root.join(".archives")));
+        assert!(entries.contains(&Path::new("w0-p0/a").to_path_buf()));
+        assert!(entries.contains(&Path::new("w0-p0/.git/HEAD").to_path_buf()));
+        assert!(removed);
+        assert_eq!(archived.get(&root.join("w0/p0")), Some(&archive));
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn keep_the_project_when_the_archive_fails() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-archive-fail-{}", std::process::id()));
+        let (config, _) = config_with_clone(&root);
+        // tar cannot hold a socket
+        let _socket = std::os::unix::net::UnixListener::bind(root.join("w0/p0/socket")).unwrap();
+
+        let archived = archive_project(&config, "p0", true, &SilentReporter);
+        let kept = root.join("w0/p0/a").exists();
+        let archives = fs::read_dir(root.join(".archives")).unwrap().count();
+        let marked = state::archived_projects(&config);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(archived.is_err());
+        assert!(kept);
+        assert_eq!(archives, 0);
+        assert!(marked.is_empty());
+    }
+}
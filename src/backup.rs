@@ -0,0 +1,102 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::{expand_home, git::Git, Config, WorkspacesError};
+
+pub enum BackupStatus {
+    Created,
+    Updated(Vec<String>),
+    Unchanged,
+    Failed(String),
+}
+
+pub struct BackupEntry {
+    pub path: PathBuf,
+    pub mirror: PathBuf,
+    pub status: BackupStatus,
+}
+
+pub struct BackupReport {
+    pub entries: Vec<BackupEntry>,
+}
+
+impl BackupReport {
+    pub fn failed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, BackupStatus::Failed(_)))
+            .count()
+    }
+
+    pub fn print(&self) {
+        let (mut created, mut updated, mut unchanged) = (0, 0, 0);
+
+        for e in self.entries.iter() {
+            let path = e.path.to_string_lossy();
+            match e.status {
+                BackupStatus::Created => {
+                    created += 1;
+                    println!("created\t{path}");
+                }
+                BackupStatus::Updated(ref refs) => {
+                    updated += 1;
+                    println!("updated\t{path}");
+                    for r in refs.iter() {
+                        println!("\t\t{r}");
+                    }
+                }
+                BackupStatus::Unchanged => unchanged += 1,
+                BackupStatus::Failed(ref reason) => {
+                    println!("FAILED\t{path}");
+                    println!("\t\t{reason}");
+                }
+            }
+        }
+        println!();
+
+        println!(
+            "Backed up {} repositories: {created} created, {updated} updated, {unchanged} unchanged, {} failed",
+            self.entries.len(),
+            self.failed()
+        );
+    }
+}
+
+pub fn backup(config: &Config, to: PathBuf) -> Result<BackupReport, WorkspacesError> {
+    let to = expand_home(to);
+    fs::create_dir_all(&to).context("Tried creating backup location")?;
+
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(path, _)| path.exists())
+        .filter_map(|(path, p)| p.git.clone().map(|g| (path, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let entries = projects
+        .into_iter()
+        .map(|(path, proj_git)| {
+            let mut mirror = to.join(config.relative_path(&path)).into_os_string();
+            mirror.push(".git");
+            let mirror = PathBuf::from(mirror);
+            let existed = mirror.exists();
+
+            let status = match Git::new(path.clone(), proj_git).mirror_to(&mirror) {
+                Err(e) => BackupStatus::Failed(format!("{e:#}")),
+                Ok(_) if !existed => BackupStatus::Created,
+                Ok(changed) if changed.is_empty() => BackupStatus::Unchanged,
+                Ok(changed) => BackupStatus::Updated(changed),
+            };
+
+            BackupEntry {
+                path,
+                mirror,
+                status,
+            }
+        })
+        .collect();
+
+    Ok(BackupReport { entries })
+}
@@ -1,7 +1,19 @@
-use std::path::PathBuf;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process::ExitCode,
+    sync::Mutex,
+    time::Duration,
+};
 
 use anyhow::{Context, Result};
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use crossterm::{
+    event::{self, KeyCode, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use tracing::level_filters::LevelFilter;
 
 use dev_workspaces::*;
 
@@ -13,36 +25,806 @@ use dev_workspaces::*;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Only print results and errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also print the URLs cloned from and the credentials offered
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// When to color output
+    #[arg(long, value_enum, default_value_t = Color::Auto, global = true)]
+    color: Color,
+    /// Log what the library does at this level and below, defaults to off or to
+    /// info with --log-file
+    #[arg(long, value_enum, global = true)]
+    log_level: Option<LogLevel>,
+    /// Append the log to a file instead of printing it
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+    /// Config file to use instead of $WORKSPACES_CONFIG or
+    /// $XDG_CONFIG_HOME/workspaces/workspaces.yaml
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Only see the workspaces and projects of this profile in the config,
+    /// overrides $WORKSPACES_PROFILE
+    #[arg(long, global = true)]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Create a starter config file
+    Init {
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+
     /// List out managed paths
-    #[command(subcommand)]
-    List(ListCommand),
+    List {
+        #[command(subcommand)]
+        cmd: ListCommand,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+        format: Format,
+        /// Only list what is tagged with one of these tags
+        #[arg(long = "tag", global = true)]
+        tags: Vec<String>,
+    },
 
     /// Show doctor diagnosis on managed workspaces and projects
-    Doctor,
+    Doctor {
+        /// Ask the git hosts whether repos were renamed or transferred upstream
+        #[arg(long)]
+        upstream: bool,
+        /// Create missing workspaces and restore missing projects
+        #[arg(long)]
+        fix: bool,
+        /// Point origin and the other remotes at the URLs built from the
+        /// config, for clones made before the config changed
+        #[arg(long, conflicts_with = "fix")]
+        repair_remotes: bool,
+        /// Exit with a status telling what is wrong: 4 for missing
+        /// workspaces, 8 for missing projects, 16 for other problems, added up
+        #[arg(long, conflicts_with_all = ["fix", "repair_remotes"])]
+        check: bool,
+        /// Number of projects to clone concurrently with --fix, defaults to
+        /// the config's `jobs` or 1
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// Only check what is tagged with one of these tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+
+    /// Show branch and working tree state of every project
+    Status {
+        /// Fetch from origin first, so the commits ahead of and behind the
+        /// upstream are current
+        #[arg(long)]
+        fetch: bool,
+        /// Number of projects to fetch concurrently, defaults to the
+        /// config's `jobs` or 1
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+    },
+
+    #[command(long_about = Some(r#"
+Fetch every cloned project and update its checkouts from origin
+
+Each checkout is fast-forwarded to origin/<branch>. Checkouts with commits of
+their own are skipped unless --rebase rebases them onto origin, and so are
+checkouts with uncommitted changes unless --autostash stashes them around the
+update. A rebase that conflicts is aborted, leaving the checkout as it was.
+
+Examples:
+   workspaces pull
+   workspaces pull --rebase --autostash --tag work
+"#))]
+    /// Update every project from origin
+    Pull {
+        /// Rebase local commits onto origin instead of skipping diverged
+        /// branches
+        #[arg(long)]
+        rebase: bool,
+        /// Stash uncommitted changes before updating and apply them after
+        #[arg(long)]
+        autostash: bool,
+        /// Number of projects to pull concurrently, defaults to the config's
+        /// `jobs` or 1
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Only pull projects tagged with one of these tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+
+    #[command(long_about = Some(r#"
+Push the branch of every project that is ahead of origin
+
+The checked out branch of each checkout is pushed when it has commits that
+origin/<branch> lacks. Nothing is ever force pushed: branches behind origin are
+skipped, and so are branches origin does not have yet.
+
+Examples:
+   workspaces push --dry-run
+   workspaces push --tag work
+"#))]
+    /// Push every project that is ahead of origin
+    Push {
+        /// Only report what would be pushed
+        #[arg(long)]
+        dry_run: bool,
+        /// Number of projects to push concurrently, defaults to the config's
+        /// `jobs` or 1
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Only push projects tagged with one of these tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+
+    #[command(long_about = Some(r#"
+Delete directories under the root that the config does not declare
+
+Lists every directory below the root that is not a workspace, a project or on
+the way to a link, with the git clones found in it, then asks before deleting
+them. Hidden directories and symlinks are left alone. Profiles are ignored, so
+projects outside the active profile are never pruned.
+
+Examples:
+   workspaces prune --dry-run
+   workspaces prune --yes
+"#))]
+    /// Delete directories under the root that are not in the config
+    Prune {
+        /// Only list what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+        /// Delete without asking
+        #[arg(short, long, conflicts_with = "dry_run")]
+        yes: bool,
+    },
+
+    #[command(long_about = Some(r#"
+Pack a project into an archive and remove it to reclaim disk space
+
+The project directory is written to a .tar.gz in `archive_dir` from the config,
+or else the data directory, after checking that every change, branch and stash
+in it is pushed. Archived projects are left out when restoring workspaces and
+reported by doctor, until restored by name with `restore project`.
+
+Examples:
+   workspaces archive src/old_project
+   workspaces archive old_project --force
+"#))]
+    /// Archive a project and remove its working copy
+    Archive {
+        /// Project path or name
+        project: String,
+        /// Archive even when some work is not pushed
+        #[arg(long)]
+        force: bool,
+    },
+
+    #[command(long_about = Some(r#"
+Show when projects were restored and synced
+
+Lists the latest restores and syncs with their outcome, newest first. Given a
+project, also shows when it was last restored and synced, how long its clone
+took and the commit it was left at.
+
+Examples:
+   workspaces history
+   workspaces history project_a --limit 5
+"#))]
+    /// Show the history of restores and syncs
+    History {
+        /// Project path or name
+        project: Option<String>,
+        /// Number of operations to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+
+    #[command(long_about = Some(r#"
+Keep checking that the file system matches the config
+
+Runs doctor every --interval seconds and whenever the config or a file it
+includes changes, printing the diagnosis when it differs from the previous
+one. With --restore, workspaces and projects added to the config are restored
+as soon as the change is noticed.
+
+Examples:
+   workspaces watch --restore
+   workspaces watch --interval 60 --format json
+"#))]
+    /// Run doctor periodically and on config changes
+    Watch {
+        /// Seconds between doctor runs
+        #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Restore workspaces and projects added to the config
+        #[arg(long)]
+        restore: bool,
+        /// Number of projects to clone concurrently with --restore, defaults
+        /// to the config's `jobs` or 1
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Output format of the diagnosis
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+
+    #[command(long_about = Some(r#"
+Open a project in the editor, restoring it first if it is missing
+
+The project is given by path, or by name or the end of its path as long as
+that matches a single project. The editor is `editor` in the config, where
+{path} stands for the project directory, or else $VISUAL or $EDITOR.
+
+Examples:
+   workspaces open src/nested/project_a
+   workspaces open project_a
+"#))]
+    /// Open a project in the editor
+    Open {
+        /// Project path or name
+        project: String,
+    },
+
+    #[command(long_about = Some(r#"
+Open a tmux session for a workspace with a window per project
+
+The session is created unless it exists, then attached to, or switched to
+when already inside tmux. The panes of each window and their layout come from
+`tmux` in the workspace or at the top of the config.
+
+Examples:
+   workspaces tmux src
+   workspaces tmux src/nested --detach
+"#))]
+    /// Open a tmux session for a workspace
+    Tmux {
+        /// Workspace path
+        workspace: String,
+        /// Only create the session
+        #[arg(long)]
+        detach: bool,
+    },
+
+    #[command(long_about = Some(r#"
+Browse the workspace tree in the terminal
+
+Shows every workspace and project with whether it exists and the branch and
+working tree state of each project. Keys:
+   up/down, j/k   move
+   enter, space   fold or unfold a workspace
+   r              restore the workspace or project
+   s              fast-forward the projects to origin
+   o              open the project in the editor
+   F5             refresh
+   q, esc         quit
+"#))]
+    /// Browse, restore and open workspaces and projects interactively
+    Ui,
+
+    #[command(long_about = Some(r#"
+Print the path of a project picked by fuzzy matching its path
+
+Without a query, or with --interactive, the matching projects are listed on
+stderr to narrow down by typing, move through with the arrow keys and pick
+with Enter. Meant to be wrapped by a shell function, see `shell-init`.
+
+Examples:
+   cd "$(workspaces jump)"
+   cd "$(workspaces jump nested/proj)"
+"#))]
+    /// Print the path of a project picked by fuzzy matching
+    Jump {
+        /// Text to fuzzy match project paths against
+        query: Option<String>,
+        /// Pick from the matching projects even with a query
+        #[arg(short, long)]
+        interactive: bool,
+    },
+
+    /// Find workspaces and projects whose path fuzzily matches a pattern
+    Search {
+        /// Text to match paths relative to the root against
+        pattern: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+
+    #[command(long_about = Some(r#"
+Run a command in the directory of every project
+
+A single argument is run by the shell, several are run as a program and its
+arguments. Projects missing on disk are skipped. With --jobs above 1 the output
+of each project is collected and printed once it is done, otherwise it goes
+straight to the terminal. Exits non-zero if the command fails in any project.
+
+Examples:
+   workspaces exec -- git status --short
+   workspaces exec --workspace work --tag rust -- 'cargo update && cargo test'
+   workspaces exec --jobs 4 -- git fetch
+"#))]
+    /// Run a command in every project
+    Exec {
+        /// Only run in the projects of this workspace and nested ones
+        #[arg(long)]
+        workspace: Option<String>,
+        /// Only run in projects tagged with one of these tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Number of projects to run in concurrently
+        #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: u16,
+        /// Command to run, after `--`
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+
+    #[command(long_about = Some(r#"
+Run a command template in the directory of every project
+
+Like exec, but the command is run by the shell after replacing {path}, {name},
+{workspace} and {repo} with the project directory, its name, the path of its
+workspace relative to the root and its git repo. The values are quoted, so
+they are left out of quotes in the template.
+
+Examples:
+   workspaces foreach 'code {path}' --tag active
+   workspaces foreach 'echo {workspace}/{name}: {repo}'
+   workspaces foreach 'gh repo view {repo} --web' --workspace work
+"#))]
+    /// Run a command template in every project
+    Foreach {
+        /// Command with {path}, {name}, {workspace} or {repo} in it
+        command: String,
+        /// Only run in the projects of this workspace and nested ones
+        #[arg(long)]
+        workspace: Option<String>,
+        /// Only run in projects tagged with one of these tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// Number of projects to run in concurrently
+        #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: u16,
+    },
+
+    #[command(long_about = Some(r#"
+Print a shell function that changes to a project picked by `workspaces jump`
+
+Examples:
+   eval "$(workspaces shell-init bash)"    # in ~/.bashrc
+   eval "$(workspaces shell-init zsh)"     # in ~/.zshrc
+   workspaces shell-init fish | source     # in ~/.config/fish/config.fish
+"#))]
+    /// Print a shell function for jumping to projects
+    ShellInit {
+        #[arg(value_enum)]
+        shell: Shell,
+        /// Name of the function
+        #[arg(long, default_value = "wj")]
+        name: String,
+    },
+
+    /// Add entries to the config
+    #[command(subcommand)]
+    Add(AddCommand),
+
+    /// Add the repositories of a GitHub organization or GitLab group to the
+    /// config
+    #[command(subcommand)]
+    Import(ImportCommand),
+
+    #[command(long_about = Some(r#"
+Start a new project in a workspace and add it to the config
+
+The files of the template are copied into a fresh repository with a single
+commit. With --repo the project is registered with that repository as origin,
+and --create-remote also creates it on the host and pushes the initial commit.
+
+Examples:
+   workspaces new src/tool
+   workspaces new src/tool --template owner/rust-template --repo owner/tool
+   workspaces new src/tool --template owner/rust-template --repo owner/tool --create-remote
+"#))]
+    /// Start a new project, optionally from a template repository
+    New {
+        /// Workspace path and project name, e.g. src/tool
+        path: String,
+        /// Repository to copy the files of, e.g. owner/template
+        #[arg(long)]
+        template: Option<String>,
+        /// Repository the project is pushed to, e.g. owner/tool
+        #[arg(long)]
+        repo: Option<String>,
+        /// Create --repo on the host and push the initial commit
+        #[arg(long, requires = "repo")]
+        create_remote: bool,
+        /// Make the created repository public instead of private
+        #[arg(long, requires = "create_remote")]
+        public: bool,
+        /// Rewrite the config file even if that drops its comments
+        #[arg(long)]
+        force: bool,
+    },
+
+    #[command(long_about = Some(r#"
+Scan a directory for existing clones and add them to the config
+
+The directory layout below the root becomes the workspace layout and the git
+settings of each project are inferred from its origin remote. Without a config
+the scanned directory becomes the root of a new one.
+
+Examples:
+   workspaces adopt
+   workspaces adopt path/of/workspace --dry-run
+   workspaces adopt ~/dev
+"#))]
+    /// Add existing clones to the config
+    Adopt {
+        /// Directory to scan, defaults to the root
+        path: Option<String>,
+        /// Directory levels to search below the scanned directory
+        #[arg(long, default_value_t = 3)]
+        depth: usize,
+        /// Show what would be added without changing the config
+        #[arg(long)]
+        dry_run: bool,
+        /// Rewrite the config file even if that drops its comments
+        #[arg(long)]
+        force: bool,
+    },
 
     /// Restore workspaces and projects
     #[command(subcommand)]
     Restore(RestoreCommand),
 
+    /// Check the integrity of cloned project repositories
+    Verify {
+        /// Validate every object in each repository's object database
+        #[arg(long)]
+        fsck: bool,
+    },
+
+    /// Re-clone a corrupt project's object store, keeping its working tree and local branches
+    Repair {
+        /// Project to repair by path
+        path: String,
+    },
+
+    /// Maintain bare mirror clones of every project in a backup location
+    Backup {
+        /// Directory to keep the mirrors in
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Push every ref of each project to the remote its `mirror` setting names
+    Mirror,
+
+    /// Manage the worktrees of projects cloned with the worktree strategy
+    #[command(subcommand)]
+    Worktree(WorktreeCommand),
+
+    /// Move projects between machines as git bundles
+    #[command(subcommand)]
+    Bundle(BundleCommand),
+
+    /// Generate aggregate build manifests for a workspace
+    #[command(subcommand, visible_alias = "generate")]
+    Export(ExportCommand),
+
+    /// Store an access token for HTTPS clones from a host in the OS keyring,
+    /// read from stdin or asked for without echoing it
+    Login {
+        /// Host as the config names it, e.g. github or git.example.com
+        host: String,
+    },
+
+    /// Remove the token stored with `login` for a host
+    Logout {
+        /// Host as the config names it, e.g. github or git.example.com
+        host: String,
+    },
+
+    /// Update workspaces to the latest release
+    SelfUpdate {
+        /// Only check whether a newer release is available
+        #[arg(long)]
+        check: bool,
+        /// Reinstall the latest release even if it is not newer
+        #[arg(long)]
+        force: bool,
+    },
+
     /// Show config path
     Config {
-        /// Quiet extraneous output
-        #[arg(short, long)]
-        quiet: bool,
+        #[command(subcommand)]
+        cmd: Option<ConfigCommand>,
+    },
+
+    /// Generate roff man pages
+    #[command(hide = true)]
+    Man {
+        /// Write a page for every command into this directory instead of
+        /// printing the page of the main command
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+}
+
+impl Commands {
+    /// Tags given with --tag to narrow the config down to
+    fn tags(&self) -> &[String] {
+        match self {
+            Commands::List { tags, .. }
+            | Commands::Doctor { tags, .. }
+            | Commands::Exec { tags, .. }
+            | Commands::Foreach { tags, .. }
+            | Commands::Pull { tags, .. }
+            | Commands::Push { tags, .. }
+            | Commands::Restore(RestoreCommand::Workspace { tags, .. }) => tags,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Text => OutputFormat::Text,
+            Format::Json => OutputFormat::Json,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+impl From<Color> for ColorChoice {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::Auto => ColorChoice::Auto,
+            Color::Always => ColorChoice::Always,
+            Color::Never => ColorChoice::Never,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// Function `name` that passes its arguments to `workspaces jump` and
+    /// changes to the printed directory.
+    fn jump_function(self, name: &str) -> String {
+        match self {
+            Shell::Bash | Shell::Zsh => format!(
+                r#"{name}() {{
+    local dir
+    dir="$(command workspaces jump "$@")" && cd "$dir"
+}}
+"#
+            ),
+            Shell::Fish => format!(
+                r#"function {name}
+    set -l dir (command workspaces jump $argv); and cd $dir
+end
+"#
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Asks `question` on stderr, only a yes answer counts. Refuses when there is
+/// no terminal to ask on.
+fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!("Not asking without a terminal, pass --yes"));
+    }
+    eprint!("{question} [y/N] ");
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("Failed to read answer")?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Reads a line from stdin, asking with `prompt` on stderr and without echoing
+/// what is typed when stdin is a terminal.
+fn read_secret(prompt: &str) -> Result<String> {
+    if !io::stdin().is_terminal() {
+        let mut secret = String::new();
+        io::stdin()
+            .read_line(&mut secret)
+            .context("Failed to read stdin")?;
+        return Ok(secret.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    eprint!("{prompt}");
+    terminal::enable_raw_mode().context("Failed to switch the terminal to raw mode")?;
+    let mut secret = String::new();
+    let read = loop {
+        let key = match event::read() {
+            Ok(event::Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e).context("Failed to read input"),
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => break Ok(()),
+            KeyCode::Char('c') if ctrl => break Err(anyhow::anyhow!("Cancelled")),
+            KeyCode::Backspace => {
+                secret.pop();
+            }
+            KeyCode::Char(c) if !ctrl => secret.push(c),
+            _ => {}
+        }
+    };
+    let _ = terminal::disable_raw_mode();
+    eprintln!();
+    read.map(|()| secret)
+}
+
+fn init_logging(level: Option<LogLevel>, file: Option<&PathBuf>) -> Result<()> {
+    let default = match file {
+        Some(_) => LogLevel::Info,
+        None => LogLevel::Off,
+    };
+    let level = LevelFilter::from(level.unwrap_or(default));
+    if level == LevelFilter::OFF {
+        return Ok(());
+    }
+
+    let logger = tracing_subscriber::fmt().with_max_level(level);
+    match file {
+        Some(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open log file {}", path.to_string_lossy()))?;
+            logger.with_ansi(false).with_writer(Mutex::new(file)).init();
+        }
+        None => logger.with_writer(io::stderr).init(),
+    }
+    Ok(())
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Open the config in $VISUAL or $EDITOR and validate it once saved
+    Edit {
+        /// Restore the previous version if the edited config is invalid
+        #[arg(long)]
+        revert: bool,
+    },
+
+    /// Clone or fast-forward the repository set as config_repo
+    Pull,
+
+    /// Look for projects cloning the same repo, workspaces and projects
+    /// sharing a directory, and empty or unreachable entries
+    Lint {
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+
+    /// Print the config file
+    Show {
+        /// Print the git settings every project ends up with instead, once
+        /// workspace and global settings and the defaults are applied
+        #[arg(long)]
+        resolved: bool,
+        /// Output format of --resolved
+        #[arg(long, value_enum, default_value_t = Format::Text, requires = "resolved")]
+        format: Format,
     },
 }
 
 #[derive(Subcommand)]
 enum ListCommand {
     /// List workspace paths
-    Workspaces,
+    Workspaces(ListFilter),
 
     /// List project paths
-    Projects,
+    Projects {
+        #[command(flatten)]
+        filter: ListFilter,
+        /// Also show the branch, HEAD, state, host and clone strategy of each
+        #[arg(short, long)]
+        long: bool,
+    },
+
+    /// Show workspaces and projects nested as in the config, marking the
+    /// missing ones
+    Tree,
+}
+
+#[derive(Args)]
+struct ListFilter {
+    /// Only list paths, relative to the root, that match this glob or lie in
+    /// a directory that does, e.g. 'clients/*'
+    pattern: Option<String>,
+    /// Only list paths at most this many levels below the root
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// Only list paths that do not exist
+    #[arg(long, conflicts_with = "existing")]
+    missing: bool,
+    /// Only list paths that exist
+    #[arg(long)]
+    existing: bool,
+}
+
+impl From<&ListFilter> for PathFilter {
+    fn from(filter: &ListFilter) -> Self {
+        PathFilter {
+            pattern: filter.pattern.clone(),
+            max_depth: filter.max_depth,
+            exists: match (filter.missing, filter.existing) {
+                (true, _) => Some(false),
+                (_, true) => Some(true),
+                _ => None,
+            },
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -54,6 +836,8 @@ Examples:
    workspaces restore workspace path/of/workspace
    workspaces restore workspace path/of/workspace --include-projects
    workspaces restore workspace --all
+   workspaces restore workspace --all --include-projects --jobs 8
+   workspaces restore workspace --all --include-projects --tag work
 "#))]
     Workspace {
         /// Restore a workspace by path
@@ -64,6 +848,13 @@ Examples:
         /// Restore all workspaces
         #[arg(long)]
         all: bool,
+        /// Number of projects to clone concurrently, defaults to the config's
+        /// `jobs` or 1
+        #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Only restore what is tagged with one of these tags
+        #[arg(long = "tag")]
+        tags: Vec<String>,
     },
     #[command(long_about = Some(r#"
 Restore a project by relative path
@@ -74,52 +865,758 @@ Example:
     Project(RestoreProjectCommand),
 }
 
+#[derive(Subcommand)]
+enum AddCommand {
+    #[command(long_about = Some(r#"
+Add a git project to a workspace in the config and clone it
+
+Example:
+   workspaces add project path/of/workspace czifro/dev-workspaces
+   workspaces add project path/of/workspace czifro/dev-workspaces --name workspaces
+"#))]
+    Project(AddProjectCommand),
+}
+
+#[derive(Subcommand)]
+enum ImportCommand {
+    #[command(long_about = Some(r#"
+Add every repository of a GitHub organization or user as a project of a
+workspace, named after the repository. Archived repositories and forks are
+left out unless asked for. $GITHUB_TOKEN is used to see private repositories
+and for higher rate limits.
+
+Example:
+   workspaces import github --org myorg --workspace work/myorg
+   workspaces import github --org myorg --workspace work/myorg --topic rust --dry-run
+"#))]
+    /// Add the repositories of a GitHub organization or user
+    Github {
+        /// Organization or user to list the repositories of
+        #[arg(long)]
+        org: String,
+        #[command(flatten)]
+        opts: ImportArgs,
+    },
+
+    #[command(long_about = Some(r#"
+Add every project of a GitLab group as a project of a workspace, named after
+the project. With --recursive the projects of subgroups are added too, each
+subgroup becoming a nested workspace. Archived projects and forks are left
+out unless asked for. $GITLAB_TOKEN is used to see private projects.
+
+Example:
+   workspaces import gitlab --group platform --workspace work/platform --recursive
+   workspaces import gitlab --host git.corp.com --group infra --workspace infra
+"#))]
+    /// Add the projects of a GitLab group
+    Gitlab {
+        /// Group to list the projects of, e.g. platform or platform/infra
+        #[arg(long)]
+        group: String,
+        /// Self-hosted GitLab instance, defaults to gitlab.com
+        #[arg(long)]
+        host: Option<String>,
+        /// Add the projects of subgroups as nested workspaces
+        #[arg(long)]
+        recursive: bool,
+        #[command(flatten)]
+        opts: ImportArgs,
+    },
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    /// Workspace to add the projects to, created if it is not in the config
+    #[arg(long)]
+    workspace: String,
+    /// Import archived repositories too
+    #[arg(long)]
+    archived: bool,
+    /// Import forks too
+    #[arg(long)]
+    forks: bool,
+    /// Only import repositories with this topic, repeat for any of several
+    #[arg(long = "topic")]
+    topics: Vec<String>,
+    /// Show what would be added without changing the config
+    #[arg(long)]
+    dry_run: bool,
+    /// Rewrite the config file even if that drops its comments
+    #[arg(long)]
+    force: bool,
+}
+
+impl From<&ImportArgs> for ImportOptions {
+    fn from(args: &ImportArgs) -> Self {
+        Self {
+            ws_path: PathBuf::from(&args.workspace),
+            archived: args.archived,
+            forks: args.forks,
+            topics: args.topics.clone(),
+            dry_run: args.dry_run,
+            force: args.force,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum WorktreeCommand {
+    /// Check out a branch in a new worktree at <project>/<branch>, creating the
+    /// branch from origin's or else from HEAD if needed
+    Add {
+        /// Project path or name
+        project: String,
+        /// Branch to check out
+        branch: String,
+    },
+
+    /// List the worktrees of a project
+    List {
+        /// Project path or name
+        project: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+    },
+
+    /// Remove the worktree of a branch, keeping the branch
+    Remove {
+        /// Project path or name
+        project: String,
+        /// Branch whose worktree to remove
+        branch: String,
+        /// Remove it even with uncommitted changes or untracked files
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BundleCommand {
+    /// Bundle every project into a single archive
+    Export {
+        /// Archive to write
+        archive: String,
+    },
+
+    /// Clone projects from an archive created by `bundle export`
+    Import {
+        /// Archive to read
+        archive: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Generate a Cargo workspace manifest for Rust projects
+    Cargo(ExportManifestCommand),
+
+    /// Generate a pnpm-workspace.yaml for JS projects
+    Pnpm(ExportManifestCommand),
+
+    /// Generate an npm workspaces package.json for JS projects
+    Npm(ExportManifestCommand),
+
+    /// Generate a flake.nix whose dev shell merges those of the projects' flakes
+    Nix(ExportManifestCommand),
+}
+
+#[derive(Args)]
+struct ExportManifestCommand {
+    /// Workspace to generate the manifest for
+    path: String,
+    /// Write the manifest into the workspace directory instead of printing it
+    #[arg(long)]
+    write: bool,
+    /// Replace a manifest in the workspace that workspaces did not generate
+    #[arg(long, requires = "write")]
+    force: bool,
+}
+
 #[derive(Args)]
 struct RestoreProjectCommand {
-    /// Restore a project by path
+    /// Restore a project by path, alias, or name if only one project has it
     path: String,
 }
 
-fn main() -> Result<()> {
-    let config = Config::from_config_file()?;
+#[derive(Args)]
+struct AddProjectCommand {
+    /// Workspace to add the project to by path
+    workspace: String,
+    /// Repository as configured under `git.repo`, e.g. owner/name
+    repo: String,
+    /// Project directory name, defaults to the repository name
+    #[arg(long)]
+    name: Option<String>,
+    /// Rewrite the config file even if that drops its comments
+    #[arg(long)]
+    force: bool,
+}
+
+/// Exit status of a command that already reported why it failed.
+#[derive(Debug)]
+struct ExitStatus(u8);
 
-    let workspace_paths = config.collect_workspace_paths();
+impl std::fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exit status {}", self.0)
+    }
+}
 
-    let project_paths = config.collect_project_paths();
+impl std::error::Error for ExitStatus {}
 
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    set_color(cli.color.into());
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => match e.downcast_ref::<ExitStatus>() {
+            Some(ExitStatus(status)) => ExitCode::from(*status),
+            None => {
+                print_error(&e);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
 
-    match &cli.command {
-        Commands::List(cmd) => {
-            match &cmd {
-                ListCommand::Workspaces => {
-                    for p in workspace_paths.iter() {
-                        let p = <PathBuf as Clone>::clone(p)
-                            .into_os_string()
-                            .into_string()
-                            .unwrap();
-                        println!("{p}");
-                    }
+/// Runs `command` with `exec`, printing the output of each project and a
+/// summary, and fails if it failed in any project.
+fn exec_in_projects(
+    config: &Config,
+    command: &[String],
+    opts: &ExecOptions,
+    quiet: bool,
+) -> Result<()> {
+    let summary = exec(config, command, opts, |event| match event {
+        ExecEvent::Started(path) => {
+            if !quiet {
+                println!("==> {}", path.to_string_lossy());
+            }
+        }
+        ExecEvent::Finished(result) => {
+            let path = result.path.to_string_lossy();
+            if let Some(output) = &result.output {
+                if !quiet || !output.is_empty() {
+                    println!("==> {path}");
                 }
-                ListCommand::Projects => {
-                    for p in project_paths.iter() {
-                        let p = <PathBuf as Clone>::clone(p)
-                            .into_os_string()
-                            .into_string()
-                            .unwrap();
-                        println!("{p}");
+                print!("{output}");
+            }
+            match &result.outcome {
+                ExecOutcome::Succeeded => {}
+                ExecOutcome::Failed(reason) => eprintln!("Failed in {path}: {reason}"),
+                ExecOutcome::Missing => {
+                    if !quiet {
+                        eprintln!("Skipped {path}, it is not restored");
                     }
                 }
+            }
+        }
+    })
+    .context("Failed to run command in projects")?;
+    if !quiet {
+        println!();
+        println!(
+            "{} succeeded, {} failed, {} missing",
+            summary.succeeded, summary.failed, summary.missing
+        );
+    }
+    if summary.failed > 0 {
+        return Err(anyhow::anyhow!(
+            "Command failed in {} projects",
+            summary.failed
+        ));
+    }
+    Ok(())
+}
+
+/// Prints the summary of a restore unless `quiet`, whose failures then need
+/// not be repeated in the error.
+fn finish_restore(report: RestoreReport, quiet: bool) -> Result<()> {
+    if quiet {
+        return Ok(report.into_result()?);
+    }
+    report.print();
+    match report.failures.len() {
+        0 => Ok(()),
+        1 => Err(anyhow::anyhow!("1 failure, see the summary above")),
+        failed => Err(anyhow::anyhow!("{failed} failures, see the summary above")),
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    init_logging(cli.log_level, cli.log_file.as_ref())?;
+    if let Some(path) = &cli.config {
+        // Read by `Config::file_path`, so every command and anything it runs
+        // sees the same config
+        std::env::set_var("WORKSPACES_CONFIG", path);
+    }
+    if let Some(profile) = &cli.profile {
+        std::env::set_var("WORKSPACES_PROFILE", profile);
+    }
+    let verbosity = match (cli.quiet, cli.verbose) {
+        (true, _) => Verbosity::Quiet,
+        (_, true) => Verbosity::Verbose,
+        _ => Verbosity::Normal,
+    };
+
+    if let Commands::Login { host } = &cli.command {
+        let token = read_secret(&format!("Token for {host}: "))?;
+        let domain = login(host, &token).context("Failed to log in")?;
+        if !cli.quiet {
+            println!("Stored the token for {domain} in the OS keyring");
+        }
+        return Ok(());
+    }
+
+    if let Commands::Logout { host } = &cli.command {
+        let removed = logout(host).context("Failed to log out")?;
+        if !removed && !cli.quiet {
+            println!("No token was stored for {host}");
+        }
+        return Ok(());
+    }
+
+    if let Commands::SelfUpdate { check, force } = &cli.command {
+        let update = self_update(*check, *force).context("Failed to self-update")?;
+        update.print();
+        return Ok(());
+    }
+
+    if let Commands::Adopt {
+        path,
+        depth,
+        dry_run,
+        force,
+    } = &cli.command
+    {
+        let opts = AdoptOptions {
+            depth: *depth,
+            dry_run: *dry_run,
+            force: *force,
+        };
+        let report =
+            adopt(path.clone().map(PathBuf::from), &opts).context("Failed to adopt clones")?;
+        report.print();
+        return Ok(());
+    }
+
+    if let Commands::Man { out_dir } = &cli.command {
+        let cmd = Cli::command();
+        match out_dir {
+            Some(dir) => {
+                std::fs::create_dir_all(dir).context("Failed to create man page directory")?;
+                clap_mangen::generate_to(cmd, dir).context("Failed to write man pages")?;
+            }
+            None => clap_mangen::Man::new(cmd)
+                .render(&mut io::stdout())
+                .context("Failed to write man page")?,
+        }
+        return Ok(());
+    }
+
+    if let Commands::ShellInit { shell, name } = &cli.command {
+        print!("{}", shell.jump_function(name));
+        return Ok(());
+    }
+
+    if let Commands::Config {
+        cmd: Some(ConfigCommand::Edit { revert }),
+    } = &cli.command
+    {
+        Config::edit(*revert).context("Failed to edit config")?;
+        if !cli.quiet {
+            println!("Saved {}", Config::file_path()?.to_string_lossy());
+        }
+        return Ok(());
+    }
+
+    if let Commands::Config {
+        cmd: Some(ConfigCommand::Pull),
+    } = &cli.command
+    {
+        let reporter = ConsoleReporter::new(verbosity);
+        let pull = pull_config(&reporter).context("Failed to pull config")?;
+        if pull.updated {
+            println!("Updated config from {}", pull.repo);
+        } else if !cli.quiet {
+            println!("Config from {} is up to date", pull.repo);
+        }
+        return Ok(());
+    }
+
+    if let Commands::Watch {
+        interval,
+        restore,
+        jobs,
+        format,
+    } = &cli.command
+    {
+        let opts = WatchOptions {
+            interval: Duration::from_secs(*interval),
+            restore: *restore,
+            jobs: jobs.map(usize::from),
+        };
+        let reporter = ConsoleReporter::new(verbosity);
+        watch(&opts, &reporter, |event| match event {
+            WatchEvent::ConfigReloaded { added } => {
+                eprintln!("Reloaded config");
+                for path in added {
+                    eprintln!("Added {}", path.to_string_lossy());
+                }
+            }
+            WatchEvent::ConfigInvalid(e) => {
+                eprintln!("Ignoring config change: {:#}", anyhow::Error::new(e))
+            }
+            WatchEvent::Diagnosis(diagnosis) => {
+                if let Err(e) = emit(&diagnosis, (*format).into()) {
+                    eprintln!("Failed to print diagnosis: {e}");
+                }
+            }
+            WatchEvent::DoctorFailed(e) => {
+                eprintln!("Doctor failed: {:#}", anyhow::Error::new(e))
+            }
+            WatchEvent::Restored(path) => println!("Restored {}", path.to_string_lossy()),
+            WatchEvent::RestoreFailed(path, e) => eprintln!(
+                "Failed to restore {}: {:#}",
+                path.to_string_lossy(),
+                anyhow::Error::new(e)
+            ),
+        })
+        .context("Failed to watch config")?;
+        return Ok(());
+    }
+
+    if let Commands::Prune { dry_run, yes } = &cli.command {
+        // Not narrowed down by the profile, whatever it leaves out is still
+        // managed
+        let config = Config::from_file(&Config::file_path()?)?;
+        let unmanaged = find_unmanaged(&config).context("Failed to find unmanaged directories")?;
+        if unmanaged.is_empty() {
+            if !cli.quiet {
+                println!("Nothing to prune");
+            }
+            return Ok(());
+        }
+        for dir in unmanaged.iter() {
+            match dir.clones {
+                0 => println!("{}", dir.path.to_string_lossy()),
+                n => println!("{}	{n} git clones", dir.path.to_string_lossy()),
+            }
+        }
+        if *dry_run {
+            return Ok(());
+        }
+        if !*yes && !confirm(&format!("Delete these {} directories?", unmanaged.len()))? {
+            return Err(anyhow::anyhow!("Nothing was deleted"));
+        }
+
+        let dirs = unmanaged.into_iter().map(|d| d.path).collect::<Vec<_>>();
+        let reporter = ConsoleReporter::new(verbosity);
+        let report = prune(&config, &dirs, &reporter).context("Failed to prune")?;
+        if !cli.quiet {
+            println!("Deleted {} directories", report.removed.len());
+        }
+        for (path, e) in report.failures.iter() {
+            eprintln!("Failed to delete {}: {e}", path.to_string_lossy());
+        }
+        if !report.failures.is_empty() {
+            let failed = report.failures.len();
+            return Err(anyhow::anyhow!("{failed} directories could not be deleted"));
+        }
+        return Ok(());
+    }
+
+    if let Commands::Init { force } = &cli.command {
+        let path = Config::init(*force).context("Failed to create config")?;
+        println!("Created {}", path.to_string_lossy());
+        if cli.quiet {
+            return Ok(());
+        }
+        println!("Edit it to describe your workspaces, then run `workspaces restore workspace --all --include-projects`");
+        return Ok(());
+    }
+
+    let mut config = Config::from_config_file()?;
+    let tags = cli.command.tags();
+    if !tags.is_empty() {
+        config.retain_tagged(tags);
+    }
+    if verbosity == Verbosity::Verbose {
+        eprintln!(
+            "Loaded config from {}",
+            Config::file_path()?.to_string_lossy()
+        );
+    }
+    let reporter = ConsoleReporter::new(verbosity);
+
+    match &cli.command {
+        Commands::List { cmd, format, .. } => {
+            let format = (*format).into();
+            let (paths, filter) = match cmd {
+                ListCommand::Workspaces(filter) => (config.collect_workspace_paths(), filter),
+                ListCommand::Projects { filter, .. } => (config.collect_project_paths(), filter),
+                ListCommand::Tree => {
+                    emit(&config_tree(&config), format)?;
+                    return Ok(());
+                }
+            };
+            let mut paths = config
+                .filter_paths(paths, &filter.into())
+                .context("Failed to filter paths")?;
+            if let ListCommand::Projects { long: true, .. } = cmd {
+                paths.sort();
+                let details =
+                    project_details(&config, &paths).context("Failed to read project details")?;
+                emit(&details, format)?;
+            } else {
+                emit(paths.as_slice(), format)?;
+            }
+        }
+        Commands::Doctor {
+            upstream,
+            fix,
+            repair_remotes,
+            check,
+            jobs,
+            format,
+            ..
+        } => {
+            let opts = DoctorOptions {
+                check_upstream: *upstream,
+            };
+            let diagnosis = doctor(&config, &opts).context("Tried to generate doctor diagnosis")?;
+            if *check {
+                if !cli.quiet || matches!(format, Format::Json) {
+                    emit(&diagnosis, (*format).into())?;
+                }
+                return match diagnosis.check_status() {
+                    0 => Ok(()),
+                    status => Err(ExitStatus(status).into()),
+                };
+            }
+            if !*fix && !*repair_remotes {
+                emit(&diagnosis, (*format).into())?;
+                return Ok(());
+            }
+
+            let fixed = if *repair_remotes {
+                diagnosis.repair_remotes(&config)
+            } else {
+                let jobs = jobs.map_or(config.jobs(), usize::from);
+                diagnosis.fix(&config, jobs, &reporter)
+            };
+            if !cli.quiet || matches!(format, Format::Json) {
+                emit(&fixed, (*format).into())?;
+            }
+            let failed = fixed.failures.len();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} fixes failed"));
+            }
+        }
+        Commands::Status { fetch, jobs } => {
+            let opts = StatusOptions {
+                fetch: *fetch,
+                jobs: jobs.map_or(config.jobs(), usize::from),
+            };
+            let report =
+                status(&config, &opts, &reporter).context("Failed to read project status")?;
+            report.print();
+        }
+        Commands::Pull {
+            rebase,
+            autostash,
+            jobs,
+            format,
+            ..
+        } => {
+            let opts = PullOptions {
+                rebase: *rebase,
+                autostash: *autostash,
+                jobs: jobs.map_or(config.jobs(), usize::from),
+            };
+            let report = pull(&config, &opts, &reporter).context("Failed to pull projects")?;
+            emit(&report, (*format).into())?;
+            let failed = report.failed();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} checkouts failed"));
+            }
+        }
+        Commands::Push {
+            dry_run,
+            jobs,
+            format,
+            ..
+        } => {
+            let opts = PushOptions {
+                dry_run: *dry_run,
+                jobs: jobs.map_or(config.jobs(), usize::from),
+            };
+            let report = push(&config, &opts, &reporter).context("Failed to push projects")?;
+            emit(&report, (*format).into())?;
+            let failed = report.failed();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} checkouts failed"));
+            }
+        }
+        Commands::Import(cmd) => {
+            let (source, opts) = match cmd {
+                ImportCommand::Github { org, opts } => {
+                    (ImportSource::GitHub { owner: org.clone() }, opts)
+                }
+                ImportCommand::Gitlab {
+                    group,
+                    host,
+                    recursive,
+                    opts,
+                } => (
+                    ImportSource::GitLab {
+                        host: host.clone(),
+                        group: group.clone(),
+                        recursive: *recursive,
+                    },
+                    opts,
+                ),
+            };
+            let report =
+                import(&config, source, &opts.into()).context("Failed to import repositories")?;
+            report.print();
+        }
+        Commands::Add(AddCommand::Project(AddProjectCommand {
+            workspace,
+            repo,
+            name,
+            force,
+        })) => {
+            let path = add_project(
+                &config,
+                PathBuf::from(workspace),
+                repo.clone(),
+                name.clone(),
+                *force,
+                &reporter,
+            )
+            .context("Failed to add project")?;
+            println!("Added {}", path.to_string_lossy());
+        }
+        Commands::Open { project } => {
+            open_project(&config, project, &reporter).context("Failed to open project")?;
+        }
+        Commands::Archive { project, force } => {
+            let archive = archive_project(&config, project, *force, &reporter)
+                .context("Failed to archive project")?;
+            println!("Archived to {}", archive.to_string_lossy());
+        }
+        Commands::History {
+            project,
+            limit,
+            format,
+        } => {
+            let history =
+                history(&config, project.as_deref(), *limit).context("Failed to read history")?;
+            emit(&history, (*format).into())?;
+        }
+        Commands::Ui => {
+            ui(&config).context("Failed to run the terminal UI")?;
+        }
+        Commands::Search { pattern, format } => {
+            let results = search(&config, pattern);
+            emit(&results, (*format).into())?;
+            if results.matches.is_empty() {
+                return Err(anyhow::anyhow!("Nothing matches `{pattern}`"));
+            }
+        }
+        Commands::Exec {
+            workspace,
+            jobs,
+            command,
+            ..
+        } => {
+            let opts = ExecOptions {
+                ws_path: workspace.as_ref().map(PathBuf::from),
+                jobs: usize::from(*jobs),
+                template: false,
             };
+            exec_in_projects(&config, command, &opts, cli.quiet)?;
+        }
+        Commands::Foreach {
+            command,
+            workspace,
+            jobs,
+            ..
+        } => {
+            let opts = ExecOptions {
+                ws_path: workspace.as_ref().map(PathBuf::from),
+                jobs: usize::from(*jobs),
+                template: true,
+            };
+            exec_in_projects(&config, std::slice::from_ref(command), &opts, cli.quiet)?;
+        }
+        Commands::Jump { query, interactive } => {
+            let query = query.as_deref().unwrap_or_default();
+            let path = if *interactive || query.is_empty() {
+                pick_project(&config, query)
+                    .context("Failed to pick project")?
+                    .ok_or_else(|| anyhow::anyhow!("No project picked"))?
+            } else {
+                best_project(&config, query)
+                    .ok_or_else(|| anyhow::anyhow!("No project matches `{query}`"))?
+            };
+            println!("{}", path.to_string_lossy());
+        }
+        Commands::Tmux { workspace, detach } => {
+            let session = tmux_session(&config, &PathBuf::from(workspace))
+                .context("Failed to create tmux session")?;
+            if *detach {
+                println!("{session}");
+            } else {
+                tmux_attach(&session).context("Failed to attach to tmux session")?;
+            }
+        }
+        Commands::New {
+            path,
+            template,
+            repo,
+            create_remote,
+            public,
+            force,
+        } => {
+            let opts = NewProjectOptions {
+                path: PathBuf::from(path),
+                template: template.clone(),
+                repo: repo.clone(),
+                create_remote: *create_remote,
+                private: !*public,
+                force: *force,
+            };
+            let path = new_project(&config, opts, &reporter).context("Failed to create project")?;
+            println!("Created {}", path.to_string_lossy());
+        }
+        Commands::Config {
+            cmd: Some(ConfigCommand::Show { resolved, format }),
+        } => {
+            if *resolved {
+                emit(&resolved_config(&config), (*format).into())?;
+            } else {
+                let path = Config::source_path()?;
+                let contents = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.to_string_lossy()))?;
+                print!("{contents}");
+            }
         }
-        Commands::Doctor { .. } => {
-            let diagnosis = doctor(&config).context("Tried to generate doctor diagnosis")?;
-            diagnosis.print();
+        Commands::Config {
+            cmd: Some(ConfigCommand::Lint { format }),
+        } => {
+            let lint = lint_config(&config);
+            emit(&lint, (*format).into())?;
+            if !lint.issues.is_empty() {
+                return Err(ExitStatus(1).into());
+            }
         }
-        Commands::Config { quiet } => {
+        Commands::Config { .. } => {
             let config_path = Config::file_path()?;
-            let config_path = config_path.into_os_string().into_string().unwrap();
-            if *quiet {
+            let config_path = config_path.to_string_lossy();
+            if cli.quiet {
                 println!("{config_path}");
             } else {
                 println!("Workspaces config path: {config_path}");
@@ -131,38 +1628,144 @@ fn main() -> Result<()> {
                     path,
                     include_projects,
                     all,
+                    jobs,
+                    ..
                 } => {
+                    let jobs = jobs.map_or(config.jobs(), usize::from);
                     if *all {
-                        return restore(
+                        let report = restore_with_report(
                             &config,
                             RestoreOption::AllWorkspaces {
                                 include_projects: *include_projects,
                             },
+                            jobs,
+                            &reporter,
                         )
-                        .context("Failed to restore all");
+                        .context("Failed to restore all")?;
+                        return finish_restore(report, cli.quiet).context("Failed to restore all");
                     }
                     let path = path
                         .clone()
                         .ok_or_else(|| anyhow::anyhow!("Workspace path is required"))?;
-                    restore(
+                    let report = restore_with_report(
                         &config,
                         RestoreOption::Workspace {
                             ws_path: PathBuf::from(path),
                             include_projects: *include_projects,
                         },
+                        jobs,
+                        &reporter,
                     )
                     .context("Failed to restore workspace")?;
+                    finish_restore(report, cli.quiet).context("Failed to restore workspace")?;
                 }
                 RestoreCommand::Project(RestoreProjectCommand { path }) => {
-                    restore(
+                    let proj_path = config.find_project(path)?;
+                    let report = restore_with_report(
                         &config,
-                        RestoreOption::Project {
-                            proj_path: PathBuf::from(path),
-                        },
-                    ).context("Failed to restore project")?;
-                },
+                        RestoreOption::Project { proj_path },
+                        1,
+                        &reporter,
+                    )
+                    .context("Failed to restore project")?;
+                    finish_restore(report, cli.quiet).context("Failed to restore project")?;
+                }
             };
         }
+        Commands::Verify { fsck } => {
+            let report = verify(&config, *fsck).context("Failed to verify repositories")?;
+            report.print();
+            let failed = report.failed().len();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} repositories failed verification"));
+            }
+        }
+        Commands::Repair { path } => {
+            let report = repair(&config, PathBuf::from(path), &reporter)
+                .context("Failed to repair project")?;
+            report.print();
+        }
+        Commands::Backup { to } => {
+            let report =
+                backup(&config, PathBuf::from(to)).context("Failed to back up projects")?;
+            report.print();
+            let failed = report.failed();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} repositories failed to back up"));
+            }
+        }
+        Commands::Mirror => {
+            let report = mirror(&config).context("Failed to mirror projects")?;
+            report.print();
+            let failed = report.failed();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} repositories failed to mirror"));
+            }
+        }
+        Commands::Worktree(WorktreeCommand::Add { project, branch }) => {
+            let path = add_worktree(&config, project, branch, &reporter)
+                .context("Failed to add worktree")?;
+            println!("{}", path.to_string_lossy());
+        }
+        Commands::Worktree(WorktreeCommand::List { project, format }) => {
+            let worktrees = list_worktrees(&config, project).context("Failed to list worktrees")?;
+            emit(&worktrees, (*format).into())?;
+        }
+        Commands::Worktree(WorktreeCommand::Remove {
+            project,
+            branch,
+            force,
+        }) => {
+            let path = remove_worktree(&config, project, branch, *force, &reporter)
+                .context("Failed to remove worktree")?;
+            println!("Removed {}", path.to_string_lossy());
+        }
+        Commands::Bundle(cmd) => {
+            let report = match &cmd {
+                BundleCommand::Export { archive } => {
+                    export_bundles(&config, PathBuf::from(archive))
+                        .context("Failed to export bundles")?
+                }
+                BundleCommand::Import { archive } => {
+                    import_bundles(&config, PathBuf::from(archive))
+                        .context("Failed to import bundles")?
+                }
+            };
+            report.print();
+            let failed = report.failed();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{failed} projects failed"));
+            }
+        }
+        Commands::SelfUpdate { .. }
+        | Commands::Login { .. }
+        | Commands::Logout { .. }
+        | Commands::Init { .. }
+        | Commands::Adopt { .. }
+        | Commands::Man { .. }
+        | Commands::ShellInit { .. }
+        | Commands::Watch { .. }
+        | Commands::Prune { .. } => {
+            unreachable!("handled before loading the config")
+        }
+        Commands::Export(cmd) => {
+            let (format, ExportManifestCommand { path, write, force }) = match &cmd {
+                ExportCommand::Cargo(args) => (ExportFormat::Cargo, args),
+                ExportCommand::Pnpm(args) => (ExportFormat::Pnpm, args),
+                ExportCommand::Npm(args) => (ExportFormat::Npm, args),
+                ExportCommand::Nix(args) => (ExportFormat::Nix, args),
+            };
+            let manifest = export_manifest(&config, PathBuf::from(path), format)
+                .context("Failed to generate workspace manifest")?;
+            if *write {
+                manifest
+                    .write(*force)
+                    .context("Failed to write workspace manifest")?;
+                println!("Wrote {}", manifest.path.to_string_lossy());
+            } else {
+                print!("{}", manifest.contents);
+            }
+        }
     };
 
     Ok(())
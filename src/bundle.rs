@@ -0,0 +1,295 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use tempfile::TempDir;
+
+use crate::{
+    expand_home,
+    git::{self, Git},
+    path_buf_to_string, Config, WorkspacesError,
+};
+
+const MANIFEST: &str = "manifest.json";
+
+#[derive(Serialize, Deserialize)]
+struct BundleManifest {
+    version: u32,
+    projects: Vec<BundledProject>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BundledProject {
+    path: String,
+    bundle: String,
+    worktree: bool,
+    origin: String,
+}
+
+impl BundledProject {
+    /// Whether the project and its bundle are plain relative paths, which
+    /// keeps a crafted manifest from reaching outside the root or the archive.
+    fn inside_archive(&self) -> bool {
+        [&self.path, &self.bundle].iter().all(|p| {
+            Path::new(p)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)))
+        })
+    }
+}
+
+pub enum BundleStatus {
+    Exported,
+    Imported,
+    Skipped(String),
+    Failed(String),
+}
+
+pub struct BundleEntry {
+    pub path: PathBuf,
+    pub status: BundleStatus,
+}
+
+pub struct BundleReport {
+    pub archive: PathBuf,
+    pub entries: Vec<BundleEntry>,
+}
+
+impl BundleReport {
+    pub fn failed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, BundleStatus::Failed(_)))
+            .count()
+    }
+
+    pub fn print(&self) {
+        for e in self.entries.iter() {
+            let path = e.path.to_string_lossy();
+            match e.status {
+                BundleStatus::Exported => println!("exported\t{path}"),
+                BundleStatus::Imported => println!("imported\t{path}"),
+                BundleStatus::Skipped(ref reason) => println!("skipped\t\t{path} ({reason})"),
+                BundleStatus::Failed(ref reason) => {
+                    println!("FAILED\t\t{path}");
+                    println!("\t\t{reason}");
+                }
+            }
+        }
+        println!();
+
+        println!(
+            "Processed {} projects with {}, {} failed",
+            self.entries.len(),
+            self.archive.to_string_lossy(),
+            self.failed()
+        );
+    }
+}
+
+/// A directory of its own to put bundles together in, removed when dropped.
+fn staging() -> Result<TempDir> {
+    tempfile::Builder::new()
+        .prefix("workspaces-bundle-")
+        .tempdir()
+        .context("Tried creating staging directory")
+}
+
+pub fn export_bundles(config: &Config, archive: PathBuf) -> Result<BundleReport, WorkspacesError> {
+    let archive = expand_home(archive);
+    let staging = staging()?;
+
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter_map(|(path, p)| p.git.clone().map(|g| (path, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut manifest = BundleManifest {
+        version: 1,
+        projects: vec![],
+    };
+    let mut entries = vec![];
+    for (path, proj_git) in projects {
+        if !path.exists() {
+            entries.push(BundleEntry {
+                path,
+                status: BundleStatus::Skipped("missing".to_string()),
+            });
+            continue;
+        }
+
+        let relative = path_buf_to_string(config.relative_path(&path).to_path_buf())?;
+        let bundle = format!("{relative}.bundle");
+        let g = Git::new(path.clone(), proj_git);
+
+        let dest = staging.path().join(&bundle);
+        let bundled = dest
+            .parent()
+            .map(fs::create_dir_all)
+            .transpose()
+            .context("Tried creating staging directory")
+            .and_then(|_| g.bundle(&dest));
+        let status = match bundled {
+            Ok(_) => {
+                manifest.projects.push(BundledProject {
+                    path: relative,
+                    bundle,
+                    worktree: g.is_worktree(),
+                    origin: g.url(),
+                });
+                BundleStatus::Exported
+            }
+            Err(e) => BundleStatus::Failed(format!("{e:#}")),
+        };
+        entries.push(BundleEntry { path, status });
+    }
+
+    fs::write(
+        staging.path().join(MANIFEST),
+        serde_json::to_string_pretty(&manifest).context("Tried writing bundle manifest")?,
+    )
+    .context("Tried writing bundle manifest")?;
+
+    let file = fs::File::create(&archive).context("Tried creating bundle archive")?;
+    let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+    tar.append_path_with_name(staging.path().join(MANIFEST), MANIFEST)
+        .context("Tried writing bundle archive")?;
+    for p in manifest.projects.iter() {
+        tar.append_path_with_name(staging.path().join(&p.bundle), &p.bundle)
+            .context("Tried writing bundle archive")?;
+    }
+    tar.into_inner()
+        .and_then(|gz| gz.finish())
+        .context("Tried writing bundle archive")?;
+
+    Ok(BundleReport { archive, entries })
+}
+
+pub fn import_bundles(config: &Config, archive: PathBuf) -> Result<BundleReport, WorkspacesError> {
+    let archive = expand_home(archive);
+    let staging = staging()?;
+
+    let file = fs::File::open(&archive).context("Tried opening bundle archive")?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(staging.path())
+        .context("Tried extracting bundle archive")?;
+
+    let manifest = fs::read_to_string(staging.path().join(MANIFEST))
+        .context("Tried reading bundle manifest")
+        .and_then(|m| {
+            serde_json::from_str::<BundleManifest>(&m).context("Tried parsing bundle manifest")
+        })?;
+    if manifest.version != 1 {
+        return Err(anyhow!("Unsupported bundle manifest version {}", manifest.version).into());
+    }
+
+    let entries = manifest
+        .projects
+        .iter()
+        .map(|p| {
+            let path = config.resolve_path(Path::new(&p.path));
+            let status = if !p.inside_archive() {
+                BundleStatus::Failed("manifest entry escapes the workspace root".to_string())
+            } else if path.exists() {
+                BundleStatus::Skipped("already exists".to_string())
+            } else {
+                let bundle = staging.path().join(&p.bundle);
+                match git::unbundle(&bundle, &path, p.worktree, &p.origin) {
+                    Ok(_) => BundleStatus::Imported,
+                    Err(e) => BundleStatus::Failed(format!("{e:#}")),
+                }
+            };
+            BundleEntry { path, status }
+        })
+        .collect();
+
+    Ok(BundleReport { archive, entries })
+}
+
+#[cfg(test)]
+mod should {
+    use std::{fs, path::Path};
+
+    use flate2::{write::GzEncoder, Compression};
+    use rstest::*;
+
+    use super::{export_bundles, import_bundles, BundleStatus};
+    use crate::Config;
+
+    fn config(root: &Path) -> Config {
+        Config::from_str(&format!(
+            "root: {}\nworkspaces:\n  w0:\n    projects:\n      p0:\n        git: {{ repo: czifro/p0 }}\n",
+            root.to_string_lossy()
+        ))
+        .unwrap()
+    }
+
+    #[rstest]
+    fn import_what_it_exported() {
+        let dir = std::env::temp_dir().join(format!("workspaces-bundle-{}", std::process::id()));
+        let repo = git2::Repository::init(dir.join("from/w0/p0")).unwrap();
+        let sig = git2::Signature::now("workspaces", "workspaces@example.com").unwrap();
+        let tree = repo.treebuilder(None).unwrap().write().unwrap();
+        let tree = repo.find_tree(tree).unwrap();
+        let head = repo
+            .commit(Some("HEAD"), &sig, &sig, "base", &tree, &[])
+            .unwrap();
+        let archive = dir.join("bundles.tar.gz");
+
+        let exported = export_bundles(&config(&dir.join("from")), archive.clone()).unwrap();
+        let imported = import_bundles(&config(&dir.join("to")), archive.clone()).unwrap();
+        let again = import_bundles(&config(&dir.join("to")), archive).unwrap();
+        let copy = git2::Repository::open(dir.join("to/w0/p0")).unwrap();
+        let copied_head = copy.head().unwrap().target();
+        let origin = copy.find_remote("origin").unwrap().url().map(String::from);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(exported.entries[0].status, BundleStatus::Exported));
+        assert!(matches!(imported.entries[0].status, BundleStatus::Imported));
+        assert!(matches!(again.entries[0].status, BundleStatus::Skipped(_)));
+        assert_eq!(copied_head, Some(head));
+        assert_eq!(origin.as_deref(), Some("https://github.com/czifro/p0.git"));
+    }
+
+    #[rstest]
+    #[case::parent_project("../escaped", "escaped.bundle")]
+    #[case::absolute_project("/escaped", "escaped.bundle")]
+    #[case::parent_bundle("escaped", "../escaped.bundle")]
+    fn refuse_entries_escaping_the_root(#[case] path: &str, #[case] bundle: &str) {
+        let dir =
+            std::env::temp_dir().join(format!("workspaces-bundle-escape-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = serde_json::json!({
+            "version": 1,
+            "projects": [{ "path": path, "bundle": bundle, "worktree": false, "origin": "" }],
+        });
+        let manifest = serde_json::to_vec(&manifest).unwrap();
+        let archive = dir.join("bundles.tar.gz");
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            fs::File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, super::MANIFEST, manifest.as_slice())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let report = import_bundles(&config(&dir.join("root")), archive).unwrap();
+        let escaped = dir.join("escaped").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(matches!(
+            report.entries[0].status,
+            BundleStatus::Failed(ref reason) if reason.contains("escapes")
+        ));
+        assert!(!escaped);
+    }
+}
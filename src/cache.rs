@@ -0,0 +1,200 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{data_dir, Config};
+
+/// An included file with its mtime and content hash.
+type IncludeFingerprint = (PathBuf, (u64, u32), u64);
+
+/// Resolved config as parsed from `path`, reused while the file's mtime and
+/// contents are unchanged and its includes still match the same files.
+#[derive(Deserialize, Serialize)]
+struct CachedConfig {
+    version: String,
+    path: PathBuf,
+    mtime: (u64, u32),
+    hash: u64,
+    #[serde(default)]
+    includes: Vec<IncludeFingerprint>,
+    config: Config,
+}
+
+fn cache_path() -> PathBuf {
+    data_dir().join("config-cache.json")
+}
+
+fn fingerprint(path: &Path, contents: &str) -> Option<((u64, u32), u64)> {
+    let mtime = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?;
+
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+
+    Some(((mtime.as_secs(), mtime.subsec_nanos()), hasher.finish()))
+}
+
+pub(crate) fn load(path: &Path, contents: &str) -> Option<Config> {
+    load_from(&cache_path(), path, contents)
+}
+
+fn load_from(cache_path: &Path, path: &Path, contents: &str) -> Option<Config> {
+    let (mtime, hash) = fingerprint(path, contents)?;
+    let cached = fs::read(cache_path).ok()?;
+    let cached: CachedConfig = serde_json::from_slice(&cached).ok()?;
+
+    if cached.version != env!("CARGO_PKG_VERSION")
+        || cached.path != path
+        || cached.mtime != mtime
+        || cached.hash != hash
+        || include_fingerprints(path, &cached.config)? != cached.includes
+    {
+        return None;
+    }
+
+    Some(cached.config)
+}
+
+fn include_fingerprints(path: &Path, config: &Config) -> Option<Vec<IncludeFingerprint>> {
+    config
+        .included_files(path)
+        .ok()?
+        .into_iter()
+        .map(|file| {
+            let contents = fs::read_to_string(&file).ok()?;
+            let (mtime, hash) = fingerprint(&file, &contents)?;
+            Some((file, mtime, hash))
+        })
+        .collect()
+}
+
+/// Best effort, a cache that cannot be written only costs a re-parse.
+pub(crate) fn store(path: &Path, contents: &str, config: &Config) {
+    store_in(&cache_path(), path, contents, config)
+}
+
+fn store_in(cache_path: &Path, path: &Path, contents: &str, config: &Config) {
+    let Some((mtime, hash)) = fingerprint(path, contents) else {
+        return;
+    };
+    let Some(includes) = include_fingerprints(path, config) else {
+        return;
+    };
+    let cached = CachedConfig {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        path: path.to_path_buf(),
+        mtime,
+        hash,
+        includes,
+        config: config.clone(),
+    };
+    let Ok(serialized) = serde_json::to_vec(&cached) else {
+        return;
+    };
+
+    let tmp = cache_path.with_extension(format!("json.{}", std::process::id()));
+    let _ = fs::create_dir_all(cache_path.parent().unwrap_or(Path::new("")))
+        .and_then(|_| fs::write(&tmp, serialized))
+        .and_then(|_| fs::rename(&tmp, cache_path));
+}
+
+#[cfg(test)]
+mod should {
+    use std::{
+        fs::{self, File},
+        path::Path,
+        time::Duration,
+    };
+
+    use rstest::*;
+
+    use super::{load_from, store_in};
+    use crate::Config;
+
+    const CONFIG: &str = "root: /dev
+include: [work.yaml]
+jobs: { max: 4, per_host: { gitlab.com: 2 } }
+workspaces:
+  oss:
+    projects: [czifro/dotfiles, group/sub/api]
+";
+
+    const INCLUDE: &str = "workspaces:
+  work:
+    projects:
+      web:
+        git: { repo: team/web, host: gitlab }
+";
+
+    /// Writes the config and its include into `dir`, parses and caches it.
+    fn cached_config(dir: &Path) -> (Config, std::path::PathBuf) {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("config.yaml");
+        fs::write(&path, CONFIG).unwrap();
+        fs::write(dir.join("work.yaml"), INCLUDE).unwrap();
+        let config = Config::parse(&path, CONFIG).unwrap();
+        store_in(&dir.join("cache/config-cache.json"), &path, CONFIG, &config);
+        (config, path)
+    }
+
+    #[rstest]
+    fn load_the_config_it_stored() {
+        let dir = std::env::temp_dir().join(format!("workspaces-cache-{}", std::process::id()));
+        let (config, path) = cached_config(&dir);
+
+        let loaded = load_from(&dir.join("cache/config-cache.json"), &path, CONFIG);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let loaded = serde_json::to_value(loaded.unwrap()).unwrap();
+        assert_eq!(loaded, serde_json::to_value(&config).unwrap());
+        assert_eq!(loaded["jobs"]["per_host"]["gitlab.com"], 2);
+        assert!(loaded["workspaces"]["oss"]["projects"]["dotfiles"].is_object());
+        assert!(loaded["workspaces"]["work"]["projects"]["web"].is_object());
+    }
+
+    #[rstest]
+    #[case("config.yaml", true)]
+    #[case("config.yaml", false)]
+    #[case("work.yaml", true)]
+    #[case("work.yaml", false)]
+    fn miss_after_a_file_changes(#[case] file: &str, #[case] contents: bool) {
+        let dir = std::env::temp_dir().join(format!(
+            "workspaces-cache-{}-{file}-{contents}",
+            std::process::id()
+        ));
+        let (_, path) = cached_config(&dir);
+
+        let changed = dir.join(file);
+        let mtime = fs::metadata(&changed).unwrap().modified().unwrap();
+        if contents {
+            // Same mtime, so only the contents give the change away
+            let before = fs::read_to_string(&changed).unwrap();
+            fs::write(&changed, format!("{before}# changed\n")).unwrap();
+            File::options()
+                .write(true)
+                .open(&changed)
+                .and_then(|f| f.set_modified(mtime))
+                .unwrap();
+        } else {
+            File::options()
+                .write(true)
+                .open(&changed)
+                .and_then(|f| f.set_modified(mtime + Duration::from_secs(1)))
+                .unwrap();
+        }
+        let contents = fs::read_to_string(&path).unwrap();
+        let loaded = load_from(&dir.join("cache/config-cache.json"), &path, &contents);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(loaded.is_none());
+    }
+}
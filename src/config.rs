@@ -1,147 +1,1050 @@
 use std::{
     collections::HashMap,
     fs,
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use anyhow::{anyhow, Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use tracing::{debug, instrument};
 
-use crate::git::{GitCloneProtocol, GitCloneStrategy, GitHost};
+use crate::{
+    cache, config_dir, expand_home,
+    git::{GitBackend, GitCloneProtocol, GitCloneStrategy, GitHost},
+    state_dir, ConfigRepo, GlobalHooks, Hooks, Link, SilentReporter, TmuxSettings,
+    ToolchainManager, WorkspacesError,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
-    pub(crate) root: String,
+    pub(crate) root: PathBuf,
+    /// Other directories top-level workspaces can be kept in, by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) roots: HashMap<String, PathBuf>,
+    #[serde(default)]
     pub(crate) git: GitConfig,
+    #[serde(deserialize_with = "null_entries_as_default")]
     pub(crate) workspaces: HashMap<String, Workspace>,
+    /// Globs of config fragments, relative to the config file, whose
+    /// workspaces are merged into this config
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) include: Vec<String>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) profiles: HashMap<String, Profile>,
+    /// Generate an `.envrc` for direnv from the `env` of workspaces and
+    /// projects
+    #[serde(default)]
+    pub(crate) direnv: bool,
+    /// Version manager `restore` installs the toolchains of cloned projects
+    /// with, e.g. from their `.tool-versions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) toolchains: Option<ToolchainManager>,
+    /// devcontainer.json contents by name, for projects to pick with
+    /// `devcontainer`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) devcontainers: HashMap<String, serde_json::Value>,
+    /// Command `workspaces open` runs, with `{path}` standing for the
+    /// project directory
+    pub(crate) editor: Option<String>,
+    /// Window layout of `workspaces tmux` unless a workspace sets its own
+    pub(crate) tmux: Option<TmuxSettings>,
+    /// Directory `workspaces archive` packs projects into
+    pub(crate) archive_dir: Option<PathBuf>,
+    /// How many projects are cloned at once unless `--jobs` is given
+    #[serde(
+        default,
+        deserialize_with = "jobs_or_limits",
+        skip_serializing_if = "JobLimits::is_empty"
+    )]
+    pub(crate) jobs: JobLimits,
+    /// Commands run around restores and syncs
+    #[serde(default, skip_serializing_if = "GlobalHooks::is_empty")]
+    pub(crate) hooks: GlobalHooks,
+    /// Keeps the state file and the lock here instead of in the user's state
+    /// and config directories
+    #[serde(skip)]
+    pub(crate) state_dir: Option<PathBuf>,
+    #[serde(skip)]
+    index: HashMap<PathBuf, Node>,
+}
+
+/// Either `jobs: 4`, or `max` along with limits for the projects of a host,
+/// e.g. `jobs: { max: 4, per_host: { gitlab.example.com: 2 } }`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct JobLimits {
+    pub(crate) max: Option<usize>,
+    /// By host domain, without a port
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) per_host: HashMap<String, usize>,
+}
+
+impl JobLimits {
+    fn is_empty(&self) -> bool {
+        self.max.is_none() && self.per_host.is_empty()
+    }
+
+    /// How many projects of `host` may be cloned at once, if limited.
+    pub(crate) fn host_limit(&self, host: &str) -> Option<usize> {
+        self.per_host.get(host).map(|&jobs| jobs.max(1))
+    }
+}
+
+/// Narrows down the paths listed, see `Config::filter_paths`.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    /// Glob of paths relative to the root, also matching what is in a
+    /// directory it matches. `*` does not cross a `/`, `**` does.
+    pub pattern: Option<String>,
+    /// Levels below the root
+    pub max_depth: Option<usize>,
+    /// Whether the path is on disk, as doctor sees missing paths
+    pub exists: Option<bool>,
+}
+
+/// A config file pulled in through `include`.
+#[derive(Deserialize)]
+struct Fragment {
+    #[serde(default, deserialize_with = "null_entries_as_default")]
+    workspaces: HashMap<String, Workspace>,
+}
+
+/// Subset of the config selected with `--profile` or `WORKSPACES_PROFILE`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Profile {
+    /// Workspaces, relative to the root, kept with everything in them
+    #[serde(default)]
+    pub(crate) workspaces: Vec<String>,
+    /// Tags of the projects kept in addition
+    #[serde(default)]
+    pub(crate) tags: Vec<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Location of a workspace or project in the config tree, as the chain of
+/// workspace keys leading to it.
+#[derive(Debug, Clone)]
+enum Node {
+    Workspace(Vec<String>),
+    Project(Vec<String>, String),
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct GitConfig {
     pub(crate) clone_strategy: Option<GitCloneStrategy>,
     pub(crate) protocol: Option<GitCloneProtocol>,
     pub(crate) host: Option<GitHost>,
+    pub(crate) askpass: Option<String>,
+    /// Private key to authenticate SSH clones with before trying the agent
+    pub(crate) ssh_key: Option<PathBuf>,
+    /// Environment variable holding an access token for HTTPS clones
+    pub(crate) token_env: Option<String>,
+    pub(crate) backend: Option<GitBackend>,
+    /// Remote `mirror` pushes every ref to, `{repo}` and `{name}` stand for
+    /// the project's repo and the last segment of it
+    pub(crate) mirror: Option<String>,
+    /// Times a clone that failed is tried again, waiting twice as long
+    /// before each attempt
+    pub(crate) retries: Option<u32>,
+    /// Seconds after which a clone is aborted
+    pub(crate) timeout: Option<u64>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Workspace {
+    /// By name, or a list of repos cloned as the last segment of each
+    #[serde(default, deserialize_with = "projects_or_repos")]
     pub(crate) projects: HashMap<String, Project>,
+    /// Name of the `roots` entry a top-level workspace is kept in instead of
+    /// `root`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) root: Option<String>,
     pub(crate) git: Option<GitConfig>,
+    #[serde(default, deserialize_with = "null_entries_as_default")]
+    pub(crate) workspaces: HashMap<String, Workspace>,
+    /// Tags of the workspace, inherited by everything in it
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
+    /// Commands run in the workspace once projects were restored into it
+    #[serde(
+        default,
+        deserialize_with = "command_or_list",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub(crate) bootstrap: Vec<String>,
+    /// Symlinks to create in the workspace, by path to target
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) links: HashMap<String, String>,
+    /// Environment variables, see `Config::direnv`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) env: HashMap<String, String>,
+    pub(crate) tmux: Option<TmuxSettings>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Project {
     pub(crate) git: Option<ProjectGitSettings>,
+    /// Short name commands taking a project accept instead of its path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) alias: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Hooks::is_empty")]
+    pub(crate) hooks: Hooks,
+    /// Symlinks to create in the project, by path to target
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) links: HashMap<String, String>,
+    /// Environment variables, see `Config::direnv`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) env: HashMap<String, String>,
+    /// Name of the `devcontainers` template restore writes to
+    /// `.devcontainer/devcontainer.json` when the project has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) devcontainer: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ProjectGitSettings {
     pub(crate) repo: String,
+    /// URL to clone from as is, instead of the one built from `host`,
+    /// `protocol` and `repo`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) url: Option<String>,
+    /// Branch to check out instead of the remote's HEAD
+    pub(crate) branch: Option<String>,
+    /// Remotes to add next to origin, by name to repo or URL
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub(crate) remotes: HashMap<String, String>,
+    /// Directories to check out with a cone-mode sparse checkout instead of
+    /// the whole tree
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) sparse_paths: Vec<String>,
     #[serde(flatten)]
     pub(crate) core_settings: GitConfig,
 }
 
+impl ProjectGitSettings {
+    pub(crate) fn new(repo: String) -> Self {
+        Self {
+            repo,
+            url: None,
+            branch: None,
+            remotes: HashMap::new(),
+            sparse_paths: vec![],
+            core_settings: GitConfig::default(),
+        }
+    }
+}
+
+/// Adds workspaces from another config file. A workspace defined in both is
+/// merged, as long as they do not both define a project, git or tmux settings.
+fn merge_workspaces(
+    workspaces: &mut HashMap<String, Workspace>,
+    other: HashMap<String, Workspace>,
+) -> Result<()> {
+    for (name, ws) in other {
+        let Some(existing) = workspaces.get_mut(&name) else {
+            workspaces.insert(name, ws);
+            continue;
+        };
+
+        for (proj_name, project) in ws.projects {
+            if existing.projects.contains_key(&proj_name) {
+                return Err(anyhow!(
+                    "Project {name}/{proj_name} is defined more than once"
+                ));
+            }
+            existing.projects.insert(proj_name, project);
+        }
+        match (&existing.git, ws.git) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!(
+                    "Workspace {name} has git settings in more than one file"
+                ))
+            }
+            (None, git) => existing.git = git,
+            (Some(_), None) => {}
+        }
+        match (&existing.tmux, ws.tmux) {
+            (Some(_), Some(_)) => {
+                return Err(anyhow!(
+                    "Workspace {name} has tmux settings in more than one file"
+                ))
+            }
+            (None, tmux) => existing.tmux = tmux,
+            (Some(_), None) => {}
+        }
+        match (&existing.root, ws.root) {
+            (Some(a), Some(b)) if *a != b => {
+                return Err(anyhow!("Workspace {name} is kept in more than one root"))
+            }
+            (None, root) => existing.root = root,
+            _ => {}
+        }
+        for (link, target) in ws.links {
+            if existing.links.contains_key(&link) {
+                return Err(anyhow!(
+                    "Link {link} of workspace {name} is defined more than once"
+                ));
+            }
+            existing.links.insert(link, target);
+        }
+        for (var, value) in ws.env {
+            if existing.env.contains_key(&var) {
+                return Err(anyhow!(
+                    "Variable {var} of workspace {name} is defined more than once"
+                ));
+            }
+            existing.env.insert(var, value);
+        }
+        if !ws.bootstrap.is_empty() {
+            if !existing.bootstrap.is_empty() {
+                return Err(anyhow!(
+                    "Workspace {name} has a bootstrap in more than one file"
+                ));
+            }
+            existing.bootstrap = ws.bootstrap;
+        }
+        for tag in ws.tags {
+            if !existing.tags.contains(&tag) {
+                existing.tags.push(tag);
+            }
+        }
+        merge_workspaces(&mut existing.workspaces, ws.workspaces)
+            .with_context(|| format!("Tried merging workspace {name}"))?;
+    }
+    Ok(())
+}
+
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Accepts a single command, e.g. a script, as well as a list of them.
+fn command_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Commands {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match Option::<Commands>::deserialize(deserializer)? {
+        None => vec![],
+        Some(Commands::One(command)) => vec![command],
+        Some(Commands::Many(commands)) => commands,
+    })
+}
+
+fn jobs_or_limits<'de, D>(deserializer: D) -> Result<JobLimits, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Jobs {
+        Max(usize),
+        Limits(JobLimits),
+    }
+
+    Ok(match Option::<Jobs>::deserialize(deserializer)? {
+        None => JobLimits::default(),
+        Some(Jobs::Max(max)) => JobLimits {
+            max: Some(max),
+            ..Default::default()
+        },
+        Some(Jobs::Limits(limits)) => limits,
+    })
+}
+
+/// The only path in `matches`, the projects `query` names.
+fn single_project(query: &str, mut matches: Vec<PathBuf>) -> Result<PathBuf, WorkspacesError> {
+    matches.sort();
+    match matches.len() {
+        0 => Err(WorkspacesError::ProjectNotFound(PathBuf::from(query))),
+        1 => Ok(matches.remove(0)),
+        _ => Err(anyhow!(
+            "{query} matches several projects:\n\t{}",
+            matches
+                .iter()
+                .map(|p| p.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("\n\t")
+        )
+        .into()),
+    }
+}
+
+/// Project name a repo is cloned as by default, e.g. `Hello-World` for
+/// `octocat/Hello-World.git`.
+pub(crate) fn repo_name(repo: &str) -> Result<String> {
+    repo.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .map(|n| n.trim_end_matches(".git"))
+        .filter(|n| !n.is_empty())
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Could not derive a project name from {repo}"))
+}
+
+/// Accepts `projects` as a list of repos as well as by name.
+fn projects_or_repos<'de, D>(deserializer: D) -> Result<HashMap<String, Project>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Projects {
+        Repos(Vec<String>),
+        ByName(HashMap<String, Option<Project>>),
+    }
+
+    let projects = match Option::<Projects>::deserialize(deserializer)? {
+        None => return Ok(HashMap::new()),
+        Some(Projects::ByName(projects)) => projects,
+        Some(Projects::Repos(repos)) => {
+            let mut projects = HashMap::new();
+            for repo in repos {
+                let name = repo_name(&repo).map_err(serde::de::Error::custom)?;
+                let project = Project {
+                    git: Some(ProjectGitSettings::new(repo)),
+                    ..Default::default()
+                };
+                if projects.insert(name.clone(), Some(project)).is_some() {
+                    return Err(serde::de::Error::custom(format!(
+                        "More than one repo would be cloned as {name}"
+                    )));
+                }
+            }
+            projects
+        }
+    };
+    Ok(projects
+        .into_iter()
+        .map(|(name, project)| (name, project.unwrap_or_default()))
+        .collect())
+}
+
+/// Like `null_as_default`, additionally accepting `name: null` entries.
+fn null_entries_as_default<'de, D, T>(deserializer: D) -> Result<HashMap<String, T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    let entries: HashMap<String, Option<T>> = null_as_default(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|(name, entry)| (name, entry.unwrap_or_default()))
+        .collect())
+}
+
+const CONFIG_TEMPLATE: &str = r#"# Directory every workspace path is relative to
+root: ~/dev
+
+# Defaults for projects with `git.repo` set, workspaces and projects can
+# override any of them
+git:
+  host: github
+  protocol: https
+  clone_strategy: branch
+
+workspaces:
+  example:
+    projects:
+      # Cloned from github.com/octocat/Hello-World by `workspaces restore`
+      hello-world:
+        git:
+          repo: octocat/Hello-World
+      # Projects without git settings are plain directories
+      scratch:
+"#;
+
 impl Config {
-    pub fn file_path() -> Result<PathBuf> {
+    /// How many projects are cloned at once when `--jobs` is not given.
+    pub fn jobs(&self) -> usize {
+        self.jobs.max.unwrap_or(1).max(1)
+    }
+
+    /// Directory of the state file `history` reads.
+    pub(crate) fn state_dir(&self) -> PathBuf {
+        self.state_dir.clone().unwrap_or_else(state_dir)
+    }
+
+    /// Directory of the lock held while the file system is changed.
+    pub(crate) fn lock_dir(&self) -> PathBuf {
+        self.state_dir.clone().unwrap_or_else(config_dir)
+    }
+
+    /// Writes a starter config, refusing to replace an existing one unless
+    /// `force` is set.
+    pub fn init(force: bool) -> Result<PathBuf, WorkspacesError> {
+        let path = Self::file_path()?;
+        if path.exists() && !force {
+            return Err(anyhow!(
+                "{} already exists, pass --force to overwrite it",
+                path.to_string_lossy()
+            )
+            .into());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Tried creating config directory")?;
+        }
+        fs::write(&path, CONFIG_TEMPLATE).context("Tried writing config file")?;
+
+        Ok(path)
+    }
+
+    /// Opens the config file in `$VISUAL` or `$EDITOR` and validates it once
+    /// the editor exits. An invalid config is replaced by the previous
+    /// version when `revert_invalid` is set.
+    pub fn edit(revert_invalid: bool) -> Result<Self, WorkspacesError> {
+        let path = Self::source_path()?;
+        if !path.exists() {
+            return Err(WorkspacesError::ConfigNotFound(path));
+        }
+        let previous = fs::read(&path).context("Tried reading config file")?;
+
+        let editor = ["VISUAL", "EDITOR"]
+            .iter()
+            .find_map(|v| std::env::var(v).ok().filter(|e| !e.is_empty()))
+            .unwrap_or_else(|| "vi".to_string());
+        #[cfg(unix)]
+        let mut cmd = {
+            // Editors are commonly set with arguments, e.g. `code --wait`
+            let mut cmd = std::process::Command::new("sh");
+            cmd.arg("-c")
+                .arg(format!("{editor} \"$@\""))
+                .arg(&editor)
+                .arg(&path);
+            cmd
+        };
+        #[cfg(not(unix))]
+        let mut cmd = crate::shell_command(&format!("{editor} \"{}\"", path.to_string_lossy()));
+
+        let status = cmd
+            .status()
+            .with_context(|| format!("Tried running {editor}"))?;
+        if !status.success() {
+            return Err(anyhow!("{editor} exited with {status}").into());
+        }
+
+        match Self::from_file(&path) {
+            Ok(config) => Ok(config),
+            // `Other` rather than `into`, which would unwrap the parse error
+            // and drop the note on what became of the file
+            Err(e) if revert_invalid => {
+                fs::write(&path, previous).context("Tried restoring previous config")?;
+                Err(WorkspacesError::Other(anyhow::Error::new(e).context(
+                    "The edited config is invalid, restored the previous version",
+                )))
+            }
+            Err(e) => Err(WorkspacesError::Other(anyhow::Error::new(e).context(
+                "The edited config is invalid, fix it with `workspaces config edit`",
+            ))),
+        }
+    }
+
+    /// The config file, `$WORKSPACES_CONFIG` when it is set and
+    /// `$XDG_CONFIG_HOME/workspaces/workspaces.yaml` otherwise. A config left
+    /// at `~/.config/workspaces/workspaces.yaml` keeps being used until one
+    /// is created under `XDG_CONFIG_HOME`.
+    pub fn file_path() -> Result<PathBuf, WorkspacesError> {
+        if let Some(path) = std::env::var_os("WORKSPACES_CONFIG").filter(|p| !p.is_empty()) {
+            let path = expand_home(path);
+            return std::path::absolute(&path)
+                .with_context(|| format!("Tried resolving config path {}", path.to_string_lossy()))
+                .map_err(Into::into);
+        }
+
+        let path = config_dir().join("workspaces.yaml");
         let home_dir = home::home_dir().expect("Could not determine home directory");
-        Ok(home_dir.clone().join(".config/workspaces/workspaces.yaml"))
+        let fallback = home_dir.join(".config/workspaces/workspaces.yaml");
+        if !path.exists() && fallback.exists() {
+            return Ok(fallback);
+        }
+        Ok(path)
     }
 
-    pub fn from_config_file() -> Result<Self> {
-        let config_file = fs::read_to_string(Self::file_path()?)
-            .context("Tried reading ~/.config/workspaces/workspaces.yaml")?;
+    /// The file the config is read from, the config file or the file it
+    /// points at with `config_repo`.
+    pub fn source_path() -> Result<PathBuf, WorkspacesError> {
+        let path = Self::file_path()?;
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Ok(path);
+        };
+        Ok(match ConfigRepo::from_config(&path, &contents)? {
+            Some(repo) => repo.config_file(),
+            None => path,
+        })
+    }
 
-        Self::from_str(config_file.as_str())
+    /// Loads the config file, narrowed down to the profile named by
+    /// `WORKSPACES_PROFILE` if set.
+    pub fn from_config_file() -> Result<Self, WorkspacesError> {
+        let mut c = Self::from_file(&Self::file_path()?)?;
+        if let Some(profile) = std::env::var("WORKSPACES_PROFILE")
+            .ok()
+            .filter(|p| !p.is_empty())
+        {
+            c.select_profile(&profile)?;
+        }
+        Ok(c)
     }
 
-    pub(crate) fn from_str(contents: &str) -> Result<Self> {
-        serde_yaml::from_str(contents)
-            .context("Tried loading config from ~/.config/workspaces/workspaces.yaml")
-            .and_then(|c: Self| {
-                let mut c = c;
-                c.root = super::absolute_path(c.root);
+    pub fn from_file(path: &Path) -> Result<Self, WorkspacesError> {
+        Self::load(path, true)
+    }
 
-                for ws in c.workspaces.values_mut() {
-                    ws.overlay_git_config(c.git.clone());
-                }
+    #[instrument(level = "debug", err)]
+    fn load(path: &Path, follow_config_repo: bool) -> Result<Self, WorkspacesError> {
+        if !path.exists() {
+            return Err(WorkspacesError::ConfigNotFound(path.to_path_buf()));
+        }
+        let config_file = fs::read_to_string(path)
+            .with_context(|| format!("Tried reading {}", path.to_string_lossy()))?;
+
+        if let Some(mut c) = cache::load(path, &config_file) {
+            debug!("using cached config");
+            c.build_index();
+            return Ok(c);
+        }
+
+        if follow_config_repo {
+            if let Some(repo) = ConfigRepo::from_config(path, &config_file)? {
+                debug!(repo = repo.git.repo, "loading config from config repo");
+                let file = repo.checkout(&SilentReporter)?;
+                return Self::load(&file, false);
+            }
+        }
 
-                Ok(c)
+        let c = Self::parse(path, &config_file)?;
+        debug!("parsed config, caching it");
+        cache::store(path, &config_file, &c);
+        Ok(c)
+    }
+
+    /// Parses `contents` as the config file at `path`, merging in the
+    /// fragments it includes.
+    pub(crate) fn parse(path: &Path, contents: &str) -> Result<Self, WorkspacesError> {
+        let mut c: Self =
+            serde_yaml::from_str(contents).map_err(|source| WorkspacesError::ConfigParse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+
+        for file in c.included_files(path)? {
+            debug!(path = %file.to_string_lossy(), "including config fragment");
+            let contents = fs::read_to_string(&file)
+                .with_context(|| format!("Tried reading {}", file.to_string_lossy()))?;
+            let fragment: Fragment =
+                serde_yaml::from_str(&contents).map_err(|source| WorkspacesError::ConfigParse {
+                    path: file.clone(),
+                    source,
+                })?;
+            merge_workspaces(&mut c.workspaces, fragment.workspaces)
+                .with_context(|| format!("Tried including {}", file.to_string_lossy()))?;
+        }
+        c.check_roots()?;
+
+        let c = c.resolve();
+        c.check_devcontainers()?;
+        Ok(c)
+    }
+
+    /// Only top-level workspaces can be kept in another root, and only in one
+    /// listed in `roots`.
+    fn check_roots(&self) -> Result<()> {
+        fn nested_root(path: &Path, ws: &Workspace) -> Option<PathBuf> {
+            ws.workspaces.iter().find_map(|(name, nested)| {
+                let path = path.join(name);
+                match nested.root {
+                    Some(_) => Some(path),
+                    None => nested_root(&path, nested),
+                }
             })
+        }
+
+        for (name, ws) in self.workspaces.iter() {
+            if let Some(root) = ws.root.as_ref().filter(|r| !self.roots.contains_key(*r)) {
+                return Err(anyhow!(
+                    "Workspace {name} is kept in the root {root}, which `roots` does not list"
+                ));
+            }
+            if let Some(path) = nested_root(Path::new(name), ws) {
+                return Err(anyhow!(
+                    "Workspace {} sets a root, only top-level workspaces can",
+                    path.to_string_lossy()
+                ));
+            }
+        }
+        Ok(())
     }
 
-    pub fn collect_workspace_paths(&self) -> Vec<PathBuf> {
-        let parent = PathBuf::from(self.root.clone());
+    /// Every `devcontainer` a project names is one of the `devcontainers`.
+    fn check_devcontainers(&self) -> Result<()> {
+        let mut projects = self.collect_projects();
+        projects.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (path, proj) in projects {
+            if let Some(name) = proj
+                .devcontainer
+                .as_ref()
+                .filter(|name| !self.devcontainers.contains_key(*name))
+            {
+                return Err(anyhow!(
+                    "Project {} uses the devcontainer template {name}, which `devcontainers` does not list",
+                    self.relative_path(&path).to_string_lossy()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub(crate) fn from_str(contents: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(contents).map(Self::resolve)
+    }
+
+    fn resolve(mut self) -> Self {
+        self.root = super::expand_home(&self.root);
+        for root in self.roots.values_mut() {
+            *root = super::expand_home(&*root);
+        }
+
+        for ws in self.workspaces.values_mut() {
+            ws.overlay_git_config(self.git.clone());
+            ws.inherit_tags(&[]);
+        }
+
+        self.build_index();
+        self
+    }
+
+    /// Drops the projects not tagged with any of `tags`, along with the
+    /// workspaces left without a tag or project.
+    pub fn retain_tagged(&mut self, tags: &[String]) {
+        self.retain(&[], tags);
+    }
+
+    /// Drops everything not selected by the profile `name`.
+    pub fn select_profile(&mut self, name: &str) -> Result<(), WorkspacesError> {
+        let profile = self
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Profile {name} is not defined in the config"))?;
 
+        let mut workspaces = vec![];
+        for ws in profile.workspaces.iter() {
+            let keys = self.workspace_keys(Path::new(ws)).map_err(|e| {
+                WorkspacesError::Other(
+                    anyhow::Error::new(e).context(format!("Tried selecting profile {name}")),
+                )
+            })?;
+            workspaces.push(keys.iter().collect::<PathBuf>());
+        }
+        self.retain(&workspaces, &profile.tags);
+        Ok(())
+    }
+
+    fn retain(&mut self, workspaces: &[PathBuf], tags: &[String]) {
         self.workspaces
+            .retain(|name, ws| ws.retain(Path::new(name), workspaces, tags));
+        self.build_index();
+    }
+
+    /// Files matched by the `include` globs of the config file at `path`, in
+    /// the order they are merged.
+    pub(crate) fn included_files(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let base = path.parent().unwrap_or(Path::new(""));
+        let base = glob::Pattern::escape(&base.to_string_lossy());
+        let mut files: Vec<PathBuf> = vec![];
+        for include in self.include.iter() {
+            let pattern = Path::new(&base).join(expand_home(include));
+            let mut matched = glob::glob(&pattern.to_string_lossy())
+                .with_context(|| format!("Tried expanding include {include}"))?
+                .collect::<Result<Vec<PathBuf>, _>>()
+                .with_context(|| format!("Tried expanding include {include}"))?;
+            matched.sort();
+            for file in matched {
+                if !files.contains(&file) {
+                    files.push(file);
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    pub fn collect_workspace_paths(&self) -> Vec<PathBuf> {
+        self.index
             .iter()
-            .map(|(name, _ws)| {
-                let path = parent.clone().join(name);
-                path
-            })
+            .filter(|(_, node)| matches!(node, Node::Workspace(_)))
+            .map(|(path, _)| path.clone())
             .collect::<Vec<PathBuf>>()
     }
 
+    /// The workspaces `path` is nested in, found through the index by its
+    /// ancestors rather than by going over every workspace in the config.
+    pub(crate) fn enclosing_workspaces(&self, path: &Path) -> Vec<(PathBuf, &Workspace)> {
+        self.resolve_path(path)
+            .ancestors()
+            .skip(1)
+            .filter_map(|p| match self.index.get(p) {
+                Some(Node::Workspace(keys)) => {
+                    Some((p.to_path_buf(), self.workspace_by_keys(keys)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Resolves a project by path, relative to the root or absolute, by its
+    /// alias, or else by its name or the end of its path, e.g. `api` or
+    /// `work/api`, as long as that names a single project.
+    pub fn find_project(&self, query: &str) -> Result<PathBuf, WorkspacesError> {
+        if self.lookup_project(Path::new(query)).is_ok() {
+            return Ok(self.resolve_path(Path::new(query)));
+        }
+        if let Some(path) = self.find_alias(query)? {
+            return Ok(path);
+        }
+
+        let suffix = Path::new(query.trim_matches('/'));
+        let matches = self
+            .collect_project_paths()
+            .into_iter()
+            .filter(|p| !suffix.as_os_str().is_empty() && p.ends_with(suffix))
+            .collect::<Vec<PathBuf>>();
+        single_project(query, matches)
+    }
+
+    /// The project with the alias `alias`, if any. A project path wins over
+    /// an alias spelled the same.
+    fn find_alias(&self, alias: &str) -> Result<Option<PathBuf>, WorkspacesError> {
+        if self.lookup_project(Path::new(alias)).is_ok() {
+            return Ok(None);
+        }
+        let matches = self
+            .collect_projects()
+            .into_iter()
+            .filter(|(_, p)| p.alias.as_deref() == Some(alias))
+            .map(|(path, _)| path)
+            .collect::<Vec<PathBuf>>();
+        if matches.is_empty() {
+            return Ok(None);
+        }
+        single_project(alias, matches).map(Some)
+    }
+
+    /// Every symlink set up with `links`, sorted by path.
+    pub(crate) fn collect_links(&self) -> Result<Vec<Link>> {
+        let mut links = vec![];
+        for (path, node) in self.index.iter() {
+            let entries = match node {
+                Node::Workspace(keys) => &self.workspace_by_keys(keys).links,
+                Node::Project(keys, name) => &self.workspace_by_keys(keys).projects[name].links,
+            };
+            for (link, target) in entries {
+                links.push(Link::new(path, link, target)?);
+            }
+        }
+        links.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(links)
+    }
+
+    /// Directories of the workspaces and projects that declare `env`.
+    pub(crate) fn collect_envs(&self) -> Vec<(PathBuf, &HashMap<String, String>)> {
+        let mut envs = self
+            .index
+            .iter()
+            .map(|(path, node)| match node {
+                Node::Workspace(keys) => (path.clone(), &self.workspace_by_keys(keys).env),
+                Node::Project(keys, name) => (
+                    path.clone(),
+                    &self.workspace_by_keys(keys).projects[name].env,
+                ),
+            })
+            .filter(|(_, env)| !env.is_empty())
+            .collect::<Vec<_>>();
+        envs.sort_by(|a, b| a.0.cmp(&b.0));
+        envs
+    }
+
+    /// Keeps the `paths` below the root that pass `filter`.
+    pub fn filter_paths(
+        &self,
+        paths: Vec<PathBuf>,
+        filter: &PathFilter,
+    ) -> Result<Vec<PathBuf>, WorkspacesError> {
+        let pattern = filter
+            .pattern
+            .as_deref()
+            .map(glob::Pattern::new)
+            .transpose()
+            .context("Tried parsing the path pattern")?;
+        let opts = glob::MatchOptions {
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        Ok(paths
+            .into_iter()
+            .filter(|path| {
+                let relative = self.relative_path(path);
+                filter.exists.is_none_or(|exists| path.exists() == exists)
+                    && filter
+                        .max_depth
+                        .is_none_or(|d| relative.components().count() <= d)
+                    && pattern.as_ref().is_none_or(|p| {
+                        relative
+                            .ancestors()
+                            .take_while(|a| !a.as_os_str().is_empty())
+                            .any(|a| p.matches_path_with(a, opts))
+                    })
+            })
+            .collect())
+    }
+
     pub fn collect_project_paths(&self) -> Vec<PathBuf> {
-        let parent = PathBuf::from(self.root.clone());
+        self.collect_projects()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<PathBuf>>()
+    }
 
-        self.workspaces
+    pub(crate) fn collect_projects(&self) -> Vec<(PathBuf, &Project)> {
+        self.index
             .iter()
-            .map(|(name, ws)| {
-                let path = parent.clone().join(name);
-                ws.collect_project_paths(&path)
+            .filter_map(|(path, node)| match node {
+                Node::Project(keys, name) => {
+                    Some((path.clone(), &self.workspace_by_keys(keys).projects[name]))
+                }
+                Node::Workspace(_) => None,
             })
-            .collect::<Vec<Vec<PathBuf>>>()
-            .concat()
+            .collect::<Vec<(PathBuf, &Project)>>()
     }
 
-    pub(crate) fn lookup_workspace(&self, ws_path: &PathBuf) -> Result<&Workspace> {
-        let mut ws_path = ws_path.clone();
-        if ws_path.starts_with(&self.root) {
-            ws_path = ws_path.strip_prefix(&self.root).unwrap().to_path_buf();
+    fn build_index(&mut self) {
+        let mut index = HashMap::new();
+
+        for (name, ws) in self.workspaces.iter() {
+            let path = self.workspace_root(ws).join(name);
+            ws.index(&path, vec![name.clone()], &mut index);
         }
-        let ws_path = ws_path;
 
-        let ws = self
-            .workspaces
-            .get(&ws_path.clone().into_os_string().into_string().unwrap());
-        let Some(workspace) = ws else {
-            return Err(anyhow!(
-                "Could not find workspace: {:}",
-                ws_path.clone().into_os_string().into_string().unwrap()
-            ));
-        };
+        self.index = index;
+    }
 
-        Ok(workspace)
+    /// Directory the top-level workspace `ws` is kept in.
+    pub(crate) fn workspace_root(&self, ws: &Workspace) -> &Path {
+        ws.root
+            .as_ref()
+            .and_then(|name| self.roots.get(name))
+            .unwrap_or(&self.root)
     }
 
-    pub(crate) fn lookup_project(&self, proj_path: &PathBuf) -> Result<&Project> {
-        let Some(ws_path) = proj_path.parent() else {
-            return Err(anyhow!("Expected project path to be sub path to workspace"));
-        };
-        let ws_path = &ws_path.to_path_buf();
-        let proj_name = proj_path.strip_prefix(ws_path).unwrap().to_path_buf();
-        let workspace = self.lookup_workspace(ws_path)?;
+    /// `root` followed by the other `roots` in path order.
+    pub(crate) fn root_dirs(&self) -> Vec<&Path> {
+        let mut others = self
+            .roots
+            .values()
+            .map(PathBuf::as_path)
+            .filter(|r| *r != self.root)
+            .collect::<Vec<_>>();
+        others.sort();
+        others.dedup();
+        [vec![self.root.as_path()], others].concat()
+    }
 
-        let Some(project) = workspace
-            .projects
-            .get(&proj_name.into_os_string().into_string().unwrap())
-        else {
-            return Err(anyhow!(
-                "Could not find project: {:}",
-                proj_path.clone().into_os_string().into_string().unwrap()
-            ));
-        };
+    /// The root `path` lies in, the deepest one when roots are nested, `root`
+    /// for paths outside all of them.
+    pub(crate) fn root_of(&self, path: &Path) -> &Path {
+        self.root_dirs()
+            .into_iter()
+            .filter(|r| path.starts_with(r))
+            .max_by_key(|r| r.components().count())
+            .unwrap_or(&self.root)
+    }
+
+    /// `path` relative to the root it lies in.
+    pub(crate) fn relative_path<'p>(&self, path: &'p Path) -> &'p Path {
+        path.strip_prefix(self.root_of(path)).unwrap_or(path)
+    }
 
-        Ok(project)
+    /// Resolves a path relative to the root (or an absolute one) to the
+    /// normalized form used as index key. A relative path is looked up in
+    /// the other `roots` too, so a workspace is named the same wherever it is
+    /// kept.
+    pub(crate) fn resolve_path(&self, path: &Path) -> PathBuf {
+        let resolve = |root: &Path| root.join(path).components().collect::<PathBuf>();
+        if path.is_absolute() || self.roots.is_empty() {
+            return resolve(&self.root);
+        }
+        self.root_dirs()
+            .into_iter()
+            .map(resolve)
+            .find(|p| p.ancestors().any(|a| self.index.contains_key(a)))
+            .unwrap_or_else(|| resolve(&self.root))
+    }
+
+    fn workspace_by_keys(&self, keys: &[String]) -> &Workspace {
+        let mut ws = &self.workspaces[&keys[0]];
+        for k in keys[1..].iter() {
+            ws = &ws.workspaces[k];
+        }
+        ws
+    }
+
+    /// Chain of workspace keys in the config file leading to `ws_path`.
+    pub(crate) fn workspace_keys(&self, ws_path: &Path) -> Result<&[String], WorkspacesError> {
+        match self.index.get(&self.resolve_path(ws_path)) {
+            Some(Node::Workspace(keys)) => Ok(keys),
+            _ => Err(WorkspacesError::WorkspaceNotFound(ws_path.to_path_buf())),
+        }
+    }
+
+    #[instrument(level = "trace", skip(self), err(level = "trace"))]
+    pub(crate) fn lookup_workspace(&self, ws_path: &Path) -> Result<&Workspace, WorkspacesError> {
+        self.workspace_keys(ws_path)
+            .map(|keys| self.workspace_by_keys(keys))
+    }
+
+    #[instrument(level = "trace", skip(self), err(level = "trace"))]
+    pub(crate) fn lookup_project(&self, proj_path: &Path) -> Result<&Project, WorkspacesError> {
+        match self.index.get(&self.resolve_path(proj_path)) {
+            Some(Node::Project(keys, name)) => Ok(&self.workspace_by_keys(keys).projects[name]),
+            _ => Err(WorkspacesError::ProjectNotFound(proj_path.to_path_buf())),
+        }
     }
 }
 
 impl Workspace {
-    pub(crate) fn collect_project_paths(&self, parent: &PathBuf) -> Vec<PathBuf> {
-        self.projects
-            .iter()
-            .map(|(name, _)| parent.clone().join(name))
-            .collect::<Vec<PathBuf>>()
+    fn index(&self, path: &Path, keys: Vec<String>, index: &mut HashMap<PathBuf, Node>) {
+        for name in self.projects.keys() {
+            index.insert(path.join(name), Node::Project(keys.clone(), name.clone()));
+        }
+        for (name, ws) in self.workspaces.iter() {
+            let mut keys = keys.clone();
+            keys.push(name.clone());
+            ws.index(&path.join(name), keys, index);
+        }
+        index.insert(path.to_path_buf(), Node::Workspace(keys));
+    }
+
+    pub(crate) fn collect_project_paths(&self, parent: &Path) -> Vec<PathBuf> {
+        let mut paths = self
+            .projects
+            .keys()
+            .map(|name| parent.join(name))
+            .collect::<Vec<PathBuf>>();
+        for (name, ws) in self.workspaces.iter() {
+            paths.extend(ws.collect_project_paths(&parent.join(name)));
+        }
+        paths
     }
 
     pub(crate) fn overlay_git_config(&mut self, g: GitConfig) {
@@ -152,13 +1055,65 @@ impl Workspace {
         ws_git.host = ws_git.host.or(g.host);
         ws_git.protocol = ws_git.protocol.or(g.protocol);
         ws_git.clone_strategy = ws_git.clone_strategy.or(g.clone_strategy);
+        ws_git.askpass = ws_git.askpass.or(g.askpass);
+        ws_git.ssh_key = ws_git.ssh_key.or(g.ssh_key);
+        ws_git.token_env = ws_git.token_env.or(g.token_env);
+        ws_git.backend = ws_git.backend.or(g.backend);
+        ws_git.mirror = ws_git.mirror.or(g.mirror);
+        ws_git.retries = ws_git.retries.or(g.retries);
+        ws_git.timeout = ws_git.timeout.or(g.timeout);
 
         for p in self.projects.values_mut() {
             p.overlay_git_config(ws_git.clone());
         }
 
+        for ws in self.workspaces.values_mut() {
+            ws.overlay_git_config(ws_git.clone());
+        }
+
         self.git = Some(ws_git.clone());
     }
+
+    fn inherit_tags(&mut self, inherited: &[String]) {
+        let mut tags = inherited.to_vec();
+        for tag in self.tags.drain(..) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+
+        for p in self.projects.values_mut() {
+            for tag in tags.iter() {
+                if !p.tags.contains(tag) {
+                    p.tags.push(tag.clone());
+                }
+            }
+        }
+
+        for ws in self.workspaces.values_mut() {
+            ws.inherit_tags(&tags);
+        }
+
+        self.tags = tags;
+    }
+
+    /// Whether anything is left after dropping what is neither in one of
+    /// `workspaces` nor tagged with one of `tags`, `path` being this
+    /// workspace's chain of keys.
+    fn retain(&mut self, path: &Path, workspaces: &[PathBuf], tags: &[String]) -> bool {
+        if workspaces.iter().any(|w| w == path) {
+            return true;
+        }
+
+        self.projects
+            .retain(|_, p| p.tags.iter().any(|t| tags.contains(t)));
+        self.workspaces
+            .retain(|name, ws| ws.retain(&path.join(name), workspaces, tags));
+
+        self.tags.iter().any(|t| tags.contains(t))
+            || !self.projects.is_empty()
+            || !self.workspaces.is_empty()
+    }
 }
 
 impl Project {
@@ -171,8 +1126,475 @@ impl Project {
         proj_git.core_settings.protocol = proj_git.core_settings.protocol.or(g.protocol);
         proj_git.core_settings.clone_strategy =
             proj_git.core_settings.clone_strategy.or(g.clone_strategy);
+        proj_git.core_settings.askpass = proj_git.core_settings.askpass.or(g.askpass);
+        proj_git.core_settings.ssh_key = proj_git.core_settings.ssh_key.or(g.ssh_key);
+        proj_git.core_settings.token_env = proj_git.core_settings.token_env.or(g.token_env);
+        proj_git.core_settings.backend = proj_git.core_settings.backend.or(g.backend);
+        proj_git.core_settings.mirror = proj_git.core_settings.mirror.or(g.mirror);
+        proj_git.core_settings.retries = proj_git.core_settings.retries.or(g.retries);
+        proj_git.core_settings.timeout = proj_git.core_settings.timeout.or(g.timeout);
 
         self.git = Some(proj_git);
     }
 }
 
+#[cfg(test)]
+mod should {
+    use std::path::{Path, PathBuf};
+
+    use rstest::*;
+
+    use super::{repo_name, Config, PathFilter, CONFIG_TEMPLATE};
+    use crate::git::GitCloneStrategy;
+    use crate::WorkspacesError;
+
+    #[rstest]
+    fn parse_init_template() {
+        let config = Config::from_str(CONFIG_TEMPLATE).unwrap();
+
+        let mut projects = config.collect_project_paths();
+        projects.sort();
+
+        let root = config.root.clone();
+        assert_eq!(
+            projects,
+            [
+                root.join("example/hello-world"),
+                root.join("example/scratch")
+            ]
+        );
+    }
+
+    #[rstest]
+    fn parse_null_entries() {
+        let contents = r#"---
+root: /some/root
+workspaces:
+  w0:
+    projects:
+      p0: null
+      p1:
+    workspaces:
+      w1: ~
+"#;
+
+        let config = Config::from_str(contents).unwrap();
+
+        let mut paths = config.collect_project_paths();
+        paths.extend(config.collect_workspace_paths());
+        paths.sort();
+
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("/some/root/w0"),
+                PathBuf::from("/some/root/w0/p0"),
+                PathBuf::from("/some/root/w0/p1"),
+                PathBuf::from("/some/root/w0/w1"),
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("jobs: 4", 4, None)]
+    #[case("jobs: { max: 3, per_host: { gitlab.example.com: 0 } }", 3, Some(1))]
+    #[case("", 1, None)]
+    fn parse_job_limits(
+        #[case] jobs: &str,
+        #[case] expected: usize,
+        #[case] gitlab_limit: Option<usize>,
+    ) {
+        let config =
+            Config::from_str(&format!("root: /some/root\n{jobs}\nworkspaces: {{}}\n")).unwrap();
+
+        assert_eq!(config.jobs(), expected);
+        assert_eq!(config.jobs.host_limit("gitlab.example.com"), gitlab_limit);
+    }
+
+    #[rstest]
+    #[case(Some("clients/*"), None, &["clients/acme/api", "clients/shared"])]
+    #[case(Some("clients/*"), Some(2), &["clients/shared"])]
+    #[case(Some("*/acme"), None, &["clients/acme/api"])]
+    #[case(None, Some(2), &["clients/shared", "oss/tool"])]
+    fn filter_paths_by_pattern_and_depth(
+        #[case] pattern: Option<&str>,
+        #[case] max_depth: Option<usize>,
+        #[case] expected: &[&str],
+    ) {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  clients:
+    workspaces:
+      acme:
+        projects:
+          api:
+    projects:
+      shared:
+  oss:
+    projects:
+      tool:
+"#,
+        )
+        .unwrap();
+
+        let filter = PathFilter {
+            pattern: pattern.map(String::from),
+            max_depth,
+            exists: None,
+        };
+        let mut paths = config
+            .filter_paths(config.collect_project_paths(), &filter)
+            .unwrap();
+        paths.sort();
+
+        assert_eq!(
+            paths,
+            expected
+                .iter()
+                .map(|p| Path::new("/some/root").join(p))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[rstest]
+    fn report_unknown_paths_by_kind() {
+        let config = Config::from_str(CONFIG_TEMPLATE).unwrap();
+        let root = config.root.clone();
+
+        assert!(matches!(
+            config.lookup_workspace(&root.join("missing")),
+            Err(WorkspacesError::WorkspaceNotFound(p)) if p == root.join("missing")
+        ));
+        assert!(matches!(
+            config.lookup_project(&root.join("example/missing")),
+            Err(WorkspacesError::ProjectNotFound(_))
+        ));
+    }
+
+    #[rstest]
+    fn merge_included_fragments() {
+        let dir = std::env::temp_dir().join(format!("workspaces-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("conf.d")).unwrap();
+        let main = r#"---
+root: /some/root
+include:
+  - conf.d/*.yaml
+git:
+  clone_strategy: worktree
+workspaces:
+  w0:
+    projects:
+      p0:
+"#;
+        std::fs::write(dir.join("workspaces.yaml"), main).unwrap();
+        let fragment = r#"---
+workspaces:
+  w0:
+    tmux:
+      layout: tiled
+    projects:
+      p1:
+        git:
+          repo: owner/p1
+  w1:
+"#;
+        std::fs::write(dir.join("conf.d/w1.yaml"), fragment).unwrap();
+
+        let config = Config::parse(&dir.join("workspaces.yaml"), main).unwrap();
+        std::fs::write(
+            dir.join("conf.d/dup.yaml"),
+            "workspaces:\n  w0:\n    projects:\n      p0:\n",
+        )
+        .unwrap();
+        let duplicate = Config::parse(&dir.join("workspaces.yaml"), main);
+        std::fs::remove_file(dir.join("conf.d/dup.yaml")).unwrap();
+        std::fs::write(
+            dir.join("conf.d/tmux.yaml"),
+            "workspaces:\n  w0:\n    tmux:\n      layout: even-horizontal\n",
+        )
+        .unwrap();
+        let two_tmux = Config::parse(&dir.join("workspaces.yaml"), main);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut paths = config.collect_project_paths();
+        paths.extend(config.collect_workspace_paths());
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("/some/root/w0"),
+                PathBuf::from("/some/root/w0/p0"),
+                PathBuf::from("/some/root/w0/p1"),
+                PathBuf::from("/some/root/w1"),
+            ]
+        );
+        let p1 = config
+            .lookup_project(Path::new("/some/root/w0/p1"))
+            .unwrap();
+        assert_eq!(
+            p1.git.as_ref().unwrap().core_settings.clone_strategy,
+            Some(GitCloneStrategy::Worktree)
+        );
+        assert!(duplicate.is_err());
+        let w0 = config.lookup_workspace(Path::new("/some/root/w0")).unwrap();
+        assert_eq!(w0.tmux.as_ref().unwrap().layout.as_deref(), Some("tiled"));
+        assert!(two_tmux.is_err_and(|e| format!("{e:#}").contains("tmux")));
+    }
+
+    #[rstest]
+    #[case(&["work"], &["/some/root/w0/p0", "/some/root/w0/p1", "/some/root/w1/p2"])]
+    #[case(&["oss"], &["/some/root/w1/p3"])]
+    #[case(&["oss", "archived"], &["/some/root/w1/p3"])]
+    #[case(&["archived"], &[])]
+    fn retain_tagged_projects(#[case] tags: &[&str], #[case] expected: &[&str]) {
+        let mut config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  w0:
+    tags: [work]
+    projects:
+      p0:
+      p1:
+  w1:
+    projects:
+      p2:
+        tags: [work]
+      p3:
+        tags: [oss]
+    workspaces:
+      w2:
+        tags: [archived]
+"#,
+        )
+        .unwrap();
+
+        config.retain_tagged(&tags.iter().map(|t| t.to_string()).collect::<Vec<_>>());
+
+        let mut projects = config.collect_project_paths();
+        projects.sort();
+        assert_eq!(
+            projects,
+            expected.iter().map(PathBuf::from).collect::<Vec<_>>()
+        );
+        if tags.contains(&"archived") {
+            assert!(config
+                .lookup_workspace(Path::new("/some/root/w1/w2"))
+                .is_ok());
+        }
+    }
+
+    #[rstest]
+    #[case("work", Some(&["/some/root/w0/p0", "/some/root/w1/p2"][..]))]
+    #[case("personal", Some(&["/some/root/w1/w2/p4"][..]))]
+    #[case("missing", None)]
+    #[case("typo", None)]
+    fn select_profile(#[case] profile: &str, #[case] expected: Option<&[&str]>) {
+        let mut config = Config::from_str(
+            r#"---
+root: /some/root
+profiles:
+  work:
+    workspaces: [w0]
+    tags: [work]
+  personal:
+    workspaces: [w1/w2]
+  typo:
+    workspaces: [w3]
+workspaces:
+  w0:
+    projects:
+      p0:
+  w1:
+    projects:
+      p2:
+        tags: [work]
+      p3:
+    workspaces:
+      w2:
+        projects:
+          p4:
+"#,
+        )
+        .unwrap();
+
+        let result = config.select_profile(profile);
+
+        let mut projects = config.collect_project_paths();
+        projects.sort();
+        match expected {
+            Some(expected) => {
+                result.unwrap();
+                assert_eq!(
+                    projects,
+                    expected.iter().map(PathBuf::from).collect::<Vec<_>>()
+                );
+            }
+            None => assert!(result.is_err()),
+        }
+    }
+
+    #[rstest]
+    #[case("w0/p0", Some("/some/root/w0/p0"))]
+    #[case("/some/root/w1/w2/p0", Some("/some/root/w1/w2/p0"))]
+    #[case("p1", Some("/some/root/w0/p1"))]
+    #[case("w2/p0", Some("/some/root/w1/w2/p0"))]
+    #[case("p0", None)]
+    #[case("p2", None)]
+    #[case("be", Some("/some/root/w1/p3"))]
+    #[case("dup", None)]
+    fn find_projects_by_path_or_name(#[case] query: &str, #[case] expected: Option<&str>) {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  w0:
+    projects:
+      p0:
+        alias: dup
+      p1:
+        alias: w0/p0
+  w1:
+    projects:
+      p3:
+        alias: be
+    workspaces:
+      w2:
+        projects:
+          p0:
+            alias: dup
+"#,
+        )
+        .unwrap();
+
+        let found = config.find_project(query);
+
+        assert_eq!(found.ok(), expected.map(PathBuf::from));
+    }
+
+    #[rstest]
+    fn report_config_parse_errors_with_path() {
+        let path =
+            std::env::temp_dir().join(format!("workspaces-parse-{}.yaml", std::process::id()));
+        std::fs::write(&path, "root: [unclosed").unwrap();
+
+        let result = Config::from_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(
+            result,
+            Err(WorkspacesError::ConfigParse { path: p, .. }) if p == path
+        ));
+    }
+
+    #[rstest]
+    fn keep_workspaces_in_other_roots() {
+        let config = Config::from_str(
+            r#"---
+root: /dev
+roots:
+  big: /mnt/big/dev
+workspaces:
+  work:
+    projects:
+      api:
+  media:
+    root: big
+    projects:
+      photos:
+    workspaces:
+      raw:
+"#,
+        )
+        .unwrap();
+
+        let mut projects = config.collect_project_paths();
+        projects.sort();
+        assert_eq!(
+            projects,
+            [
+                PathBuf::from("/dev/work/api"),
+                PathBuf::from("/mnt/big/dev/media/photos")
+            ]
+        );
+        for (path, resolved) in [
+            ("media/raw", "/mnt/big/dev/media/raw"),
+            ("media/raw/new", "/mnt/big/dev/media/raw/new"),
+            ("work/api", "/dev/work/api"),
+            ("elsewhere", "/dev/elsewhere"),
+        ] {
+            assert_eq!(config.resolve_path(Path::new(path)), Path::new(resolved));
+        }
+        assert!(config.lookup_workspace(Path::new("media/raw")).is_ok());
+        assert_eq!(
+            config.relative_path(Path::new("/mnt/big/dev/media/photos")),
+            Path::new("media/photos")
+        );
+    }
+
+    #[rstest]
+    #[case("  media:\n    root: nope\n", "which `roots` does not list")]
+    #[case(
+        "  media:\n    workspaces:\n      raw:\n        root: big\n",
+        "media/raw sets a root"
+    )]
+    fn reject_misplaced_roots(#[case] workspaces: &str, #[case] error: &str) {
+        let contents = format!("root: /dev\nroots:\n  big: /mnt/big\nworkspaces:\n{workspaces}");
+
+        let result = Config::parse(Path::new("workspaces.yaml"), &contents);
+
+        assert!(format!("{:#}", result.unwrap_err()).contains(error));
+    }
+
+    #[rstest]
+    fn reject_unknown_devcontainer_templates() {
+        let contents = "root: /dev\ndevcontainers:\n  rust:\n    image: rust:1\nworkspaces:\n  w:\n    projects:\n      api:\n        devcontainer: go\n";
+
+        let result = Config::parse(Path::new("workspaces.yaml"), contents);
+
+        assert_eq!(
+            format!("{:#}", result.unwrap_err()),
+            "Project w/api uses the devcontainer template go, which `devcontainers` does not list"
+        );
+    }
+
+    #[rstest]
+    #[case("octocat/Hello-World", "Hello-World")]
+    #[case("group/subgroup/project.git", "project")]
+    #[case("czifro/dev-workspaces/", "dev-workspaces")]
+    fn derive_project_name_from_repo(#[case] repo: &str, #[case] name: &str) {
+        assert_eq!(repo_name(repo).unwrap(), name);
+    }
+
+    #[rstest]
+    fn parse_projects_as_list_of_repos() {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  w:
+    projects: [czifro/dotfiles, group/sub/api.git]
+"#,
+        )
+        .unwrap();
+
+        let repo = |p: &str| {
+            let project = config.lookup_project(Path::new(p)).unwrap();
+            project.git.as_ref().unwrap().repo.clone()
+        };
+        assert_eq!(repo("/some/root/w/dotfiles"), "czifro/dotfiles");
+        assert_eq!(repo("/some/root/w/api"), "group/sub/api.git");
+
+        let duplicate = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  w:
+    projects: [a/api, b/api]
+"#,
+        );
+        assert!(duplicate.is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::{data_dir, git::Git, Config, ProjectGitSettings, Reporter, WorkspacesError};
+
+/// A git repository the config is kept in, set with `config_repo` in the
+/// local config file in place of the config itself.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConfigRepo {
+    /// Config file within the repository
+    #[serde(default = "default_file")]
+    pub(crate) file: String,
+    #[serde(flatten)]
+    pub(crate) git: ProjectGitSettings,
+}
+
+fn default_file() -> String {
+    "workspaces.yaml".to_string()
+}
+
+#[derive(Deserialize)]
+struct Pointer {
+    config_repo: Option<ConfigRepo>,
+}
+
+impl ConfigRepo {
+    /// The repository `contents` of the config file at `path` point at, if any.
+    pub(crate) fn from_config(
+        path: &Path,
+        contents: &str,
+    ) -> Result<Option<Self>, WorkspacesError> {
+        let pointer: Pointer =
+            serde_yaml::from_str(contents).map_err(|source| WorkspacesError::ConfigParse {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        Ok(pointer.config_repo)
+    }
+
+    fn checkout_dir(&self) -> PathBuf {
+        let name = self
+            .git
+            .repo
+            .trim_matches('/')
+            .replace(['/', '~', ':'], "-");
+        data_dir().join("config-repos").join(name)
+    }
+
+    pub(crate) fn config_file(&self) -> PathBuf {
+        self.checkout_dir().join(&self.file)
+    }
+
+    fn git(&self) -> Git {
+        Git::new(self.checkout_dir(), self.git.clone())
+    }
+
+    /// Clones the repository unless it already was, returning the config
+    /// file in it.
+    pub(crate) fn checkout(&self, reporter: &dyn Reporter) -> Result<PathBuf> {
+        self.git()
+            .clone(reporter)
+            .with_context(|| format!("Tried cloning config repo {}", self.git.repo))?;
+        Ok(self.config_file())
+    }
+}
+
+pub struct ConfigPull {
+    pub repo: String,
+    /// Config file in the local checkout of the repository
+    pub path: PathBuf,
+    pub updated: bool,
+}
+
+/// Clones or fast-forwards the repository the config file points at with
+/// `config_repo`.
+pub fn pull_config(reporter: &dyn Reporter) -> Result<ConfigPull, WorkspacesError> {
+    let path = Config::file_path()?;
+    if !path.exists() {
+        return Err(WorkspacesError::ConfigNotFound(path));
+    }
+    let contents = std::fs::read_to_string(&path).context("Tried reading config file")?;
+    let repo = ConfigRepo::from_config(&path, &contents)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} does not set config_repo, there is nothing to pull",
+            path.to_string_lossy()
+        )
+    })?;
+
+    let updated = if repo.checkout_dir().exists() {
+        repo.git()
+            .fast_forward(reporter)
+            .with_context(|| format!("Tried pulling config repo {}", repo.git.repo))?
+    } else {
+        repo.checkout(reporter)?;
+        true
+    };
+    // Fail here rather than on the next command if the pulled config is broken
+    Config::from_file(&repo.config_file())?;
+
+    Ok(ConfigPull {
+        repo: repo.git.repo.clone(),
+        path: repo.config_file(),
+        updated,
+    })
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::Path;
+
+    use rstest::*;
+
+    use super::ConfigRepo;
+
+    #[rstest]
+    #[case("config_repo:\n  repo: owner/dotfiles\n", Some("workspaces.yaml"))]
+    #[case(
+        "config_repo:\n  repo: owner/dotfiles\n  file: ws/main.yaml\n  host: gitlab\n",
+        Some("ws/main.yaml")
+    )]
+    #[case("root: ~/dev\nworkspaces:\n", None)]
+    fn find_config_repo(#[case] contents: &str, #[case] file: Option<&str>) {
+        let repo = ConfigRepo::from_config(Path::new("workspaces.yaml"), contents).unwrap();
+
+        assert_eq!(repo.map(|r| r.file), file.map(String::from));
+    }
+}
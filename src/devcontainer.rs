@@ -0,0 +1,91 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use tracing::{debug, instrument};
+
+use crate::{Config, Event, Reporter};
+
+/// Writes `.devcontainer/devcontainer.json` from the template each restored
+/// project names in `devcontainer`, unless the project has one already.
+/// Returns the projects whose file could not be written.
+#[instrument(level = "debug", skip_all)]
+pub(crate) fn write_devcontainers(
+    config: &Config,
+    restored: &[(PathBuf, Result<()>)],
+    reporter: &dyn Reporter,
+) -> Vec<(PathBuf, anyhow::Error)> {
+    restored
+        .iter()
+        .filter(|(_, r)| r.is_ok())
+        .filter_map(|(path, _)| {
+            let name = config.lookup_project(path).ok()?.devcontainer.as_ref()?;
+            let written = config
+                .devcontainers
+                .get(name)
+                .ok_or_else(|| anyhow!("There is no devcontainer template named {name}"))
+                .and_then(|template| write_devcontainer(path, template, reporter));
+            written.err().map(|e| (path.clone(), e))
+        })
+        .collect()
+}
+
+/// The template, named after the project unless it sets a `name` itself.
+fn render(proj_path: &Path, template: &Value) -> Result<String> {
+    let mut devcontainer = template.clone();
+    let Value::Object(fields) = &mut devcontainer else {
+        return Err(anyhow!("The devcontainer template is not a mapping"));
+    };
+    if let Some(name) = proj_path.file_name() {
+        fields
+            .entry("name")
+            .or_insert_with(|| Value::String(name.to_string_lossy().to_string()));
+    }
+    let mut contents =
+        serde_json::to_string_pretty(&devcontainer).context("Tried rendering devcontainer.json")?;
+    contents.push('\n');
+    Ok(contents)
+}
+
+#[instrument(level = "debug", skip(template, reporter))]
+fn write_devcontainer(proj_path: &Path, template: &Value, reporter: &dyn Reporter) -> Result<()> {
+    let dir = proj_path.join(".devcontainer");
+    let path = dir.join("devcontainer.json");
+    if path.exists() {
+        debug!("project has a devcontainer.json already");
+        return Ok(());
+    }
+
+    let contents = render(proj_path, template)?;
+    fs::create_dir_all(&dir).context("Tried creating .devcontainer")?;
+    fs::write(&path, contents).context("Tried writing devcontainer.json")?;
+    reporter.report(&Event::DevcontainerWritten { path });
+    Ok(())
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::Path;
+
+    use rstest::*;
+    use serde_json::json;
+
+    use super::render;
+
+    #[rstest]
+    #[case(json!({"image": "rust:1"}), "api")]
+    #[case(json!({"name": "API", "image": "rust:1"}), "API")]
+    fn name_devcontainers_after_the_project(
+        #[case] template: serde_json::Value,
+        #[case] expected: &str,
+    ) {
+        let contents = render(Path::new("/r/w/api"), &template).unwrap();
+
+        let devcontainer: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        assert_eq!(devcontainer["name"], expected);
+        assert_eq!(devcontainer["image"], "rust:1");
+    }
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use tracing::{debug, instrument};
+
+use crate::{Config, Event, Reporter};
+
+/// First line of every generated `.envrc`, an `.envrc` without it was written
+/// by hand and is left alone.
+const HEADER: &str = "# Generated by workspaces from its config, edits are overwritten";
+
+/// Writes the `.envrc` of each workspace and project below `scope` that
+/// declares `env`, when the config opts in with `direnv: true`. Returns the
+/// directories whose `.envrc` could not be written or allowed.
+pub(crate) fn write_envrcs(
+    config: &Config,
+    scope: &Path,
+    reporter: &dyn Reporter,
+) -> Vec<(PathBuf, anyhow::Error)> {
+    if !config.direnv {
+        return vec![];
+    }
+
+    config
+        .collect_envs()
+        .into_iter()
+        .filter(|(dir, _)| dir.starts_with(scope) && dir.is_dir())
+        .filter_map(|(dir, env)| write_envrc(&dir, env, reporter).err().map(|e| (dir, e)))
+        .collect()
+}
+
+fn render(env: &HashMap<String, String>) -> String {
+    let mut vars = env.iter().collect::<Vec<_>>();
+    vars.sort();
+
+    let mut envrc = format!("{HEADER}\nsource_up_if_exists\n");
+    for (name, value) in vars {
+        envrc.push_str(&format!(
+            "export {name}='{}'\n",
+            value.replace('\'', r"'\''")
+        ));
+    }
+    envrc
+}
+
+#[instrument(level = "debug", skip(env, reporter))]
+fn write_envrc(dir: &Path, env: &HashMap<String, String>, reporter: &dyn Reporter) -> Result<()> {
+    let path = dir.join(".envrc");
+    let envrc = render(env);
+    match fs::read_to_string(&path) {
+        Ok(existing) if existing == envrc => {
+            debug!("envrc is up to date");
+            return Ok(());
+        }
+        Ok(existing) if !existing.starts_with(HEADER) => {
+            return Err(anyhow!(
+                "{} was not generated by workspaces, leaving it alone",
+                path.to_string_lossy()
+            ));
+        }
+        _ => {}
+    }
+
+    fs::write(&path, envrc).context("Tried writing .envrc")?;
+    let output = Command::new("direnv")
+        .arg("allow")
+        .arg(dir)
+        .output()
+        .context("Tried running `direnv allow`, is direnv installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "direnv allow exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    reporter.report(&Event::EnvrcWritten { path });
+    Ok(())
+}
+
+#[cfg(test)]
+mod should {
+    use std::collections::HashMap;
+
+    use rstest::*;
+
+    use super::{render, HEADER};
+
+    #[rstest]
+    fn render_quoted_exports() {
+        let env = HashMap::from([
+            ("B".to_string(), "it's".to_string()),
+            ("A".to_string(), "$HOME".to_string()),
+        ]);
+
+        assert_eq!(
+            render(&env),
+            format!("{HEADER}\nsource_up_if_exists\nexport A='$HOME'\nexport B='it'\\''s'\n")
+        );
+    }
+}
@@ -0,0 +1,585 @@
+use std::{fs, path::PathBuf, sync::Arc};
+
+use anyhow::{Context, Result};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::{
+    block_on, bootstrap_workspaces, devcontainer::write_devcontainers, git::Git,
+    install_toolchains, lock::Lock, restore_projects, state::archived_projects, BrokenLink,
+    CancellationToken, Config, Event, LinkIssue, Reporter, WorkspacesError,
+};
+
+#[derive(Default)]
+pub struct DoctorOptions {
+    /// Ask the git hosts whether configured repos were renamed or transferred
+    pub check_upstream: bool,
+}
+
+#[derive(Serialize)]
+pub struct DoctorDiagnosis {
+    pub(crate) root: PathBuf,
+    pub(crate) missing_workspaces: Vec<PathBuf>,
+    pub(crate) missing_projects: Vec<PathBuf>,
+    pub(crate) archived_projects: Vec<ArchivedProject>,
+    pub(crate) unhealthy_projects: Vec<UnhealthyProject>,
+    pub(crate) wrong_remotes: Vec<WrongRemote>,
+    pub(crate) broken_links: Vec<BrokenLink>,
+    pub(crate) renamed_repos: Vec<RenamedRepo>,
+    pub(crate) unchecked_repos: Vec<UncheckedRepo>,
+}
+
+/// A project removed by `workspaces archive`, which is not missing
+#[derive(Serialize)]
+pub struct ArchivedProject {
+    pub path: PathBuf,
+    pub archive: PathBuf,
+}
+
+pub struct UnhealthyProject {
+    pub path: PathBuf,
+    pub issue: ProjectIssue,
+}
+
+/// A configured remote that is missing from a clone or has another URL, or
+/// an origin that does not point at the URL the config gives the project
+#[derive(Serialize)]
+pub struct WrongRemote {
+    pub path: PathBuf,
+    pub name: String,
+    pub expected: String,
+    pub found: Option<String>,
+}
+
+/// A repo whose upstream could not be asked whether it was renamed
+#[derive(Serialize)]
+pub struct UncheckedRepo {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// What `fix` changed on disk
+#[derive(Serialize)]
+pub struct DoctorFix {
+    pub created_workspaces: Vec<PathBuf>,
+    pub restored_projects: Vec<PathBuf>,
+    /// Projects whose remotes were added or pointed at the configured URL
+    pub updated_remotes: Vec<PathBuf>,
+    pub created_links: Vec<PathBuf>,
+    pub failures: Vec<FixFailure>,
+    /// Projects that exist but need attention, which are left alone
+    pub needs_attention: usize,
+}
+
+#[derive(Serialize)]
+pub struct FixFailure {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+pub struct RenamedRepo {
+    pub path: PathBuf,
+    pub configured: String,
+    pub canonical: String,
+    pub url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectIssue {
+    /// The directory exists but holds no repository
+    NotARepo,
+    /// `.git` is a file pointing at a git directory that does not exist
+    BrokenGitPointer(PathBuf),
+    /// The clone does not match the configured `clone_strategy`
+    WrongLayout { expected: String, found: String },
+    /// A worktree clone has `.bare` but no `.git` file pointing at it
+    MissingGitPointer,
+    /// A worktree clone has no worktree checked out
+    NoWorktrees,
+}
+
+impl Serialize for UnhealthyProject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("UnhealthyProject", 3)?;
+        s.serialize_field("path", &self.path)?;
+        s.serialize_field("issue", &self.issue)?;
+        s.serialize_field("description", &self.issue.describe())?;
+        s.end()
+    }
+}
+
+impl ProjectIssue {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::NotARepo => "exists but is not a git repository".to_string(),
+            Self::BrokenGitPointer(target) => {
+                format!(".git points at missing {}", target.to_string_lossy())
+            }
+            Self::WrongLayout { expected, found } => {
+                format!("expected a {expected} layout but found a {found} layout")
+            }
+            Self::MissingGitPointer => "has .bare but no .git file pointing at it".to_string(),
+            Self::NoWorktrees => "has no worktree checked out".to_string(),
+        }
+    }
+
+    pub fn remediation(&self, rel_path: &str) -> String {
+        match self {
+            Self::NotARepo => {
+                format!("move the directory aside and run `workspaces restore project {rel_path}`")
+            }
+            Self::BrokenGitPointer(_) => format!("run `workspaces repair {rel_path}`"),
+            Self::WrongLayout { .. } => format!(
+                "move the directory aside and run `workspaces restore project {rel_path}`, \
+                 or change `clone_strategy` to match the clone"
+            ),
+            Self::MissingGitPointer => format!("run `echo 'gitdir: ./.bare' > {rel_path}/.git`"),
+            Self::NoWorktrees => format!("run `workspaces worktree add {rel_path} <branch>`"),
+        }
+    }
+}
+
+pub fn doctor(config: &Config, opts: &DoctorOptions) -> Result<DoctorDiagnosis, WorkspacesError> {
+    let missing_workspaces = config
+        .collect_workspace_paths()
+        .iter()
+        .filter(|p| !p.exists())
+        .map(Clone::clone)
+        .collect::<Vec<PathBuf>>();
+    let mut archives = archived_projects(config);
+    let (archived_projects, missing_projects): (Vec<_>, Vec<_>) = config
+        .collect_project_paths()
+        .into_iter()
+        .filter(|p| !p.exists())
+        .partition(|p| archives.contains_key(p));
+    let archived_projects = archived_projects
+        .into_iter()
+        .filter_map(|path| {
+            let archive = archives.remove(&path)?;
+            Some(ArchivedProject { path, archive })
+        })
+        .collect::<Vec<ArchivedProject>>();
+
+    let mut unhealthy_projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(p, _)| p.exists())
+        .filter_map(|(p, project)| {
+            let proj_git = project.git.clone()?;
+            let issue = Git::new(p.clone(), proj_git).inspect_layout()?;
+            Some(UnhealthyProject { path: p, issue })
+        })
+        .collect::<Vec<UnhealthyProject>>();
+    unhealthy_projects.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut wrong_remotes = vec![];
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(p, _)| p.exists())
+        .filter_map(|(p, project)| project.git.clone().map(|g| (p, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, proj_git) in projects {
+        if unhealthy_projects.iter().any(|u| u.path == path) {
+            continue;
+        }
+        // A clone that cannot be opened is reported as unhealthy already
+        let Ok(wrong) = Git::new(path.clone(), proj_git).wrong_remotes() else {
+            continue;
+        };
+        wrong_remotes.extend(
+            wrong
+                .into_iter()
+                .map(|(name, expected, found)| WrongRemote {
+                    path: path.clone(),
+                    name,
+                    expected,
+                    found,
+                }),
+        );
+    }
+
+    let broken_links = config
+        .collect_links()?
+        .into_iter()
+        .filter_map(|link| link.issue().map(|issue| BrokenLink { link, issue }))
+        .collect::<Vec<BrokenLink>>();
+
+    let mut renamed_repos = vec![];
+    let mut unchecked_repos = vec![];
+    if opts.check_upstream {
+        let mut projects = config
+            .collect_projects()
+            .into_iter()
+            .filter_map(|(p, project)| project.git.clone().map(|g| (p, g)))
+            .collect::<Vec<_>>();
+        projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (path, proj_git) in projects {
+            let configured = proj_git.repo.clone();
+            match Git::new(path.clone(), proj_git).upstream_rename() {
+                Ok(Some((canonical, url))) => renamed_repos.push(RenamedRepo {
+                    path,
+                    configured,
+                    canonical,
+                    url,
+                }),
+                Ok(None) => {}
+                Err(e) => unchecked_repos.push(UncheckedRepo {
+                    path,
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+    }
+
+    Ok(DoctorDiagnosis {
+        root: config.root.clone(),
+        missing_workspaces,
+        missing_projects,
+        archived_projects,
+        unhealthy_projects,
+        wrong_remotes,
+        broken_links,
+        renamed_repos,
+        unchecked_repos,
+    })
+}
+
+impl DoctorDiagnosis {
+    /// Exit status of `doctor --check`, adding up 4 for missing workspaces,
+    /// 8 for missing projects and 16 for any other problem. Archived projects
+    /// and repos the hosts could not be asked about are not problems.
+    pub fn check_status(&self) -> u8 {
+        let mut status = 0;
+        if !self.missing_workspaces.is_empty() {
+            status |= 4;
+        }
+        if !self.missing_projects.is_empty() {
+            status |= 8;
+        }
+        if !self.unhealthy_projects.is_empty()
+            || !self.wrong_remotes.is_empty()
+            || !self.broken_links.is_empty()
+            || !self.renamed_repos.is_empty()
+        {
+            status |= 16;
+        }
+        status
+    }
+
+    /// Creates the missing workspaces and restores the missing projects,
+    /// cloning up to `jobs` projects at once, then sets up missing or
+    /// misdirected links.
+    pub fn fix(&self, config: &Config, jobs: usize, reporter: &dyn Reporter) -> DoctorFix {
+        let _lock = match Lock::acquire(&config.lock_dir(), reporter) {
+            Ok(lock) => lock,
+            Err(e) => {
+                return DoctorFix {
+                    created_workspaces: vec![],
+                    restored_projects: vec![],
+                    updated_remotes: vec![],
+                    created_links: vec![],
+                    failures: vec![FixFailure {
+                        path: self.root.clone(),
+                        error: format!("{e:#}"),
+                    }],
+                    needs_attention: self.unhealthy_projects.len(),
+                }
+            }
+        };
+        let mut missing_workspaces = self.missing_workspaces.clone();
+        missing_workspaces.sort();
+
+        let mut created_workspaces = vec![];
+        let mut failures = vec![];
+        for ws_path in missing_workspaces {
+            match fs::create_dir_all(&ws_path) {
+                Ok(()) => {
+                    reporter.report(&Event::WorkspaceCreated {
+                        path: ws_path.clone(),
+                    });
+                    created_workspaces.push(ws_path)
+                }
+                Err(e) => failures.push(FixFailure {
+                    path: ws_path,
+                    error: format!("Tried restoring workspace: {e}"),
+                }),
+            }
+        }
+
+        let mut restored_projects = vec![];
+        let projects = self.missing_projects.clone();
+        let restored = block_on(restore_projects(
+            Arc::new(config.clone()),
+            projects,
+            jobs,
+            reporter,
+            &CancellationToken::new(),
+        ));
+        let results = match restored {
+            Ok(results) => results,
+            Err(e) => {
+                failures.push(FixFailure {
+                    path: self.root.clone(),
+                    error: format!("{e:#}"),
+                });
+                vec![]
+            }
+        };
+        // Origin may have been pointed elsewhere on purpose, it takes
+        // `repair_remotes` to change it
+        let (updated_remotes, remote_failures) = self.update_remotes(config, false);
+        failures.extend(remote_failures);
+        let mut created_links = vec![];
+        for broken in self.broken_links.iter() {
+            let link = &broken.link;
+            if matches!(broken.issue, LinkIssue::NotALink) || !link.base.is_dir() {
+                continue;
+            }
+            match link.create(reporter) {
+                Ok(()) => created_links.push(link.path.clone()),
+                Err(e) => failures.push(FixFailure {
+                    path: link.path.clone(),
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+        for (proj_path, e) in write_devcontainers(config, &results, reporter) {
+            failures.push(FixFailure {
+                path: proj_path,
+                error: format!("Tried writing devcontainer.json: {e:#}"),
+            });
+        }
+        for (proj_path, e) in install_toolchains(config, &results, reporter) {
+            failures.push(FixFailure {
+                path: proj_path,
+                error: format!("Tried installing toolchains: {e:#}"),
+            });
+        }
+        for (ws_path, e) in bootstrap_workspaces(config, &results, reporter) {
+            failures.push(FixFailure {
+                path: ws_path,
+                error: format!("Tried bootstrapping workspace: {e:#}"),
+            });
+        }
+        for (proj_path, result) in results {
+            match result {
+                Ok(()) => restored_projects.push(proj_path),
+                Err(e) => failures.push(FixFailure {
+                    path: proj_path,
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+
+        DoctorFix {
+            created_workspaces,
+            restored_projects,
+            updated_remotes,
+            created_links,
+            failures,
+            needs_attention: self.unhealthy_projects.len(),
+        }
+    }
+
+    /// Points every remote that is missing or points elsewhere at the URL
+    /// the config gives it, origin included.
+    pub fn repair_remotes(&self, config: &Config) -> DoctorFix {
+        let (updated_remotes, failures) = self.update_remotes(config, true);
+        DoctorFix {
+            created_workspaces: vec![],
+            restored_projects: vec![],
+            updated_remotes,
+            created_links: vec![],
+            failures,
+            needs_attention: self.unhealthy_projects.len(),
+        }
+    }
+
+    /// Fixes the wrong remotes of each project, leaving origin alone unless
+    /// `origin` is set. Returns the projects updated and the failures.
+    fn update_remotes(&self, config: &Config, origin: bool) -> (Vec<PathBuf>, Vec<FixFailure>) {
+        let mut updated_remotes = vec![];
+        let mut failures = vec![];
+        let wrong_remotes = self
+            .wrong_remotes
+            .iter()
+            .filter(|wrong| origin || wrong.name != "origin");
+        for wrong in wrong_remotes {
+            if updated_remotes.contains(&wrong.path) {
+                continue;
+            }
+            let updated = config
+                .lookup_project(&wrong.path)
+                .map_err(anyhow::Error::from)
+                .and_then(|p| {
+                    let proj_git = p.git.clone().context("Tried finding git settings")?;
+                    let git = Git::new(wrong.path.clone(), proj_git);
+                    if origin {
+                        git.set_origin_url()?;
+                    }
+                    git.add_remotes()
+                });
+            match updated {
+                Ok(()) => updated_remotes.push(wrong.path.clone()),
+                Err(e) => failures.push(FixFailure {
+                    path: wrong.path.clone(),
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+        (updated_remotes, failures)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::{Path, PathBuf};
+
+    use rstest::*;
+
+    use super::{doctor, DoctorFix, DoctorOptions};
+    use crate::{Config, SilentReporter};
+
+    /// Parses `workspaces`, indented as under the config's `workspaces` key,
+    /// into a config rooted at `root` with its state kept there too.
+    fn config(root: &Path, workspaces: &str) -> Config {
+        let contents = format!(
+            "root: {}\nworkspaces:\n{workspaces}",
+            root.to_string_lossy()
+        );
+        let mut config = Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+        config
+    }
+
+    fn fix(config: &Config) -> DoctorFix {
+        doctor(config, &DoctorOptions::default())
+            .unwrap()
+            .fix(config, 2, &SilentReporter)
+    }
+
+    #[rstest]
+    fn create_missing_workspaces_and_projects() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-fix-missing-{}", std::process::id()));
+        let config = config(
+            &root,
+            "  w0:\n    projects:\n      p0:\n    workspaces:\n      w1:\n",
+        );
+
+        let fixed = fix(&config);
+        let exists = ["w0/p0", "w0/w1"].map(|p| root.join(p).is_dir());
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(fixed.failures.is_empty());
+        assert_eq!(
+            fixed.created_workspaces,
+            [root.join("w0"), root.join("w0/w1")]
+        );
+        assert_eq!(fixed.restored_projects, [root.join("w0/p0")]);
+        assert_eq!(exists, [true, true]);
+    }
+
+    #[rstest]
+    fn add_missing_remotes_but_leave_origin() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-fix-remotes-{}", std::process::id()));
+        let config = config(
+            &root,
+            "  w0:\n    projects:\n      p0:\n        git: { repo: czifro/p0, remotes: { upstream: rust-lang/p0 } }\n",
+        );
+        let repo = git2::Repository::init(root.join("w0/p0")).unwrap();
+        repo.remote("origin", "https://example.com/moved/p0.git")
+            .unwrap();
+        let url = |name: &str| {
+            git2::Repository::open(root.join("w0/p0"))
+                .unwrap()
+                .find_remote(name)
+                .ok()
+                .and_then(|r| r.url().map(String::from))
+        };
+
+        let fixed = fix(&config);
+        let after_fix = (url("origin"), url("upstream"));
+        let repaired = doctor(&config, &DoctorOptions::default())
+            .unwrap()
+            .repair_remotes(&config);
+        let after_repair = url("origin");
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(fixed.failures.is_empty());
+        assert_eq!(fixed.updated_remotes, [root.join("w0/p0")]);
+        assert_eq!(
+            after_fix,
+            (
+                Some("https://example.com/moved/p0.git".to_string()),
+                Some("https://github.com/rust-lang/p0.git".to_string())
+            )
+        );
+        assert_eq!(repaired.updated_remotes, [root.join("w0/p0")]);
+        assert_eq!(
+            after_repair.as_deref(),
+            Some("https://github.com/czifro/p0.git")
+        );
+    }
+
+    #[rstest]
+    fn create_links_without_replacing_files() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-fix-links-{}", std::process::id()));
+        let config = config(
+            &root,
+            "  w0:\n    links: { missing: target, moved: target, file: target }\n",
+        );
+        std::fs::create_dir_all(root.join("w0")).unwrap();
+        std::fs::write(root.join("w0/file"), "mine").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink("elsewhere", root.join("w0/moved")).unwrap();
+        #[cfg(windows)]
+        std::os::windows::fs::symlink_file("elsewhere", root.join("w0/moved")).unwrap();
+
+        let fixed = fix(&config);
+        let targets =
+            ["missing", "moved"].map(|l| std::fs::read_link(root.join("w0").join(l)).ok());
+        let file = std::fs::read_to_string(root.join("w0/file"));
+        let _ = std::fs::remove_dir_all(&root);
+
+        let mut created = fixed.created_links.clone();
+        created.sort();
+        assert!(fixed.failures.is_empty());
+        assert_eq!(created, [root.join("w0/missing"), root.join("w0/moved")]);
+        assert_eq!(
+            targets,
+            [Some(root.join("w0/target")), Some(root.join("w0/target"))]
+        );
+        assert_eq!(file.unwrap(), "mine");
+    }
+
+    #[rstest]
+    fn leave_unhealthy_projects_alone() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-fix-unhealthy-{}", std::process::id()));
+        let config = config(
+            &root,
+            "  w0:\n    projects:\n      p0:\n        git: { repo: czifro/p0 }\n",
+        );
+        std::fs::create_dir_all(root.join("w0/p0")).unwrap();
+        std::fs::write(root.join("w0/p0/notes"), "mine").unwrap();
+
+        let fixed = fix(&config);
+        let entries = std::fs::read_dir(root.join("w0/p0"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect::<Vec<_>>();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(fixed.failures.is_empty());
+        assert!(fixed.restored_projects.is_empty());
+        assert_eq!(fixed.needs_attention, 1);
+        assert_eq!(entries, [PathBuf::from("notes").into_os_string()]);
+    }
+}
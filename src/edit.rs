@@ -0,0 +1,473 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde_yaml::{Mapping, Value};
+
+use crate::{config::repo_name, Config};
+
+/// The config file as a YAML document, for commands that change it. Edits are
+/// made to `serde_yaml::Value` and, line by line, to the file as it was read,
+/// so that its comments and layout survive. Files whose layout cannot be
+/// edited in place are rendered from the `Value` instead, which drops their
+/// comments, so that needs `force` when they have any.
+pub(crate) struct ConfigDocument {
+    path: PathBuf,
+    doc: Value,
+    /// The file with the edits so far, unless one could not be made in place
+    text: Option<String>,
+    commented: bool,
+    force: bool,
+}
+
+fn as_mapping(value: &mut Value) -> Result<&mut Mapping> {
+    // `projects:` and `workspaces:` are commonly left empty
+    if value.is_null() {
+        *value = Value::Mapping(Mapping::new());
+    }
+    value
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow!("Expected a mapping in the config file"))
+}
+
+/// The `name: {git: {repo}}` entries a list of repos stands for.
+fn expand_repos(repos: &[Value]) -> Result<Mapping> {
+    let mut projects = Mapping::new();
+    for repo in repos {
+        let repo = repo
+            .as_str()
+            .ok_or_else(|| anyhow!("Expected a repo in the list of projects"))?;
+        let mut git = Mapping::new();
+        git.insert("repo".into(), repo.into());
+        let mut project = Mapping::new();
+        project.insert("git".into(), Value::Mapping(git));
+        projects.insert(repo_name(repo)?.into(), Value::Mapping(project));
+    }
+    Ok(projects)
+}
+
+/// The repo `project` can be listed as in a list of repos, if it only sets
+/// `git.repo` and is named after it.
+fn as_repo<'a>(name: &str, project: &'a Value) -> Option<&'a str> {
+    project
+        .get("git")
+        .filter(|git| git.as_mapping().is_some_and(|g| g.len() == 1))
+        .and_then(|git| git.get("repo"))
+        .and_then(Value::as_str)
+        .filter(|r| repo_name(r).is_ok_and(|n| n == name))
+}
+
+impl ConfigDocument {
+    /// Reads the config file. Unless `force` is set, edits that would drop
+    /// its comments are refused.
+    pub(crate) fn load(force: bool) -> Result<Self> {
+        let path = Config::source_path()?;
+        let contents = fs::read_to_string(&path).context("Tried reading config file")?;
+        let doc = serde_yaml::from_str(&contents).context("Tried parsing config file")?;
+
+        Ok(Self {
+            path,
+            doc,
+            commented: contents.lines().any(has_comment),
+            text: Some(contents),
+            force,
+        })
+    }
+
+    /// A document for a config file that does not exist yet.
+    pub(crate) fn new(root: &str) -> Result<Self> {
+        let mut doc = Mapping::new();
+        doc.insert("root".into(), root.into());
+        doc.insert("workspaces".into(), Value::Mapping(Mapping::new()));
+
+        Ok(Self {
+            path: Config::file_path()?,
+            doc: Value::Mapping(doc),
+            text: None,
+            commented: false,
+            force: false,
+        })
+    }
+
+    /// Mapping of the workspace reached through `keys`, see
+    /// `Config::workspace_keys`. Workspaces missing along the way are added.
+    fn workspace_mut(&mut self, keys: &[String]) -> Result<&mut Mapping> {
+        let mut ws = &mut self.doc;
+        for k in keys {
+            let workspaces = as_mapping(ws)?
+                .entry("workspaces".into())
+                .or_insert(Value::Null);
+            ws = as_mapping(workspaces)?
+                .entry(k.as_str().into())
+                .or_insert(Value::Null);
+        }
+
+        as_mapping(ws)
+    }
+
+    pub(crate) fn insert_project(
+        &mut self,
+        ws_keys: &[String],
+        name: &str,
+        project: Value,
+    ) -> Result<()> {
+        let ws = self.workspace_mut(ws_keys)?;
+        let projects = ws.entry("projects".into()).or_insert(Value::Null);
+        let mut listed = false;
+        if let Value::Sequence(repos) = projects {
+            // Kept a list as long as the project can be written as its repo
+            if let Some(repo) = as_repo(name, &project) {
+                repos.push(repo.into());
+                listed = true;
+            } else {
+                *projects = Value::Mapping(expand_repos(repos)?);
+            }
+        }
+        if !listed {
+            let projects = as_mapping(projects)?;
+            if projects.contains_key(name) {
+                return Err(anyhow!("Project {name} is already in the config file"));
+            }
+            projects.insert(name.into(), project.clone());
+        }
+
+        let Some(text) = self.text.take() else {
+            return Ok(());
+        };
+        self.text = insert_lines(&text, ws_keys, name, &project, listed);
+        if self.text.is_none() {
+            self.check_rewrite()?;
+        }
+        Ok(())
+    }
+
+    /// Fails if rendering the document anew would drop comments that `force`
+    /// does not allow dropping.
+    fn check_rewrite(&self) -> Result<()> {
+        if self.commented && !self.force {
+            return Err(anyhow!(
+                "{} has comments that editing its layout would drop, pass --force to edit it anyway",
+                self.path.to_string_lossy()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces the config file, provided the edited document still parses as
+    /// a config.
+    pub(crate) fn save(&self) -> Result<Config> {
+        // The edits made in place must amount to those made to the document
+        let in_place = self
+            .text
+            .as_ref()
+            .filter(|text| serde_yaml::from_str::<Value>(text).is_ok_and(|doc| doc == self.doc));
+        let contents = match in_place {
+            Some(text) => text.clone(),
+            None => {
+                self.check_rewrite()?;
+                let contents =
+                    serde_yaml::to_string(&self.doc).context("Tried rendering config")?;
+                drop_nulls(&contents)
+            }
+        };
+        let config =
+            Config::parse(&self.path, &contents).context("Tried validating edited config")?;
+
+        let tmp = self
+            .path
+            .with_extension(format!("yaml.{}", std::process::id()));
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("Tried creating config directory")?;
+        }
+        fs::write(&tmp, &contents)
+            .and_then(|_| fs::rename(&tmp, &self.path))
+            .context("Tried writing config file")?;
+
+        Ok(config)
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Whether the line holds anything besides whitespace and comments.
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+/// Whether the line has a comment, or something that may be one.
+fn has_comment(line: &str) -> bool {
+    line.trim_start().starts_with('#') || line.contains(" #")
+}
+
+/// The key of a `key: value` line, without indentation, and what follows the
+/// colon.
+fn split_key(line: &str) -> Option<(String, &str)> {
+    let line = line.trim_start();
+    let (key, rest) = match line.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = line[1..].find(quote)? + 1;
+            (line[1..end].to_string(), line[end + 1..].strip_prefix(':')?)
+        }
+        '-' | '[' | '{' | '?' | '#' | '&' | '*' | '!' | '|' | '>' => return None,
+        _ => {
+            let colon = line
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| line[i + 1..].is_empty() || line[i + 1..].starts_with(' '))?;
+            (line[..colon].trim_end().to_string(), &line[colon + 1..])
+        }
+    };
+    (rest.is_empty() || rest.starts_with(' ')).then_some((key, rest))
+}
+
+/// What follows a key's colon, without the comment.
+fn inline_value(rest: &str) -> &str {
+    let value = match rest.find(" #") {
+        Some(comment) => &rest[..comment],
+        None => rest,
+    };
+    value.trim()
+}
+
+/// The lines under a key, or under the document for `parent` `None`: from
+/// `start` to after the last line that is not a comment.
+fn block_end(lines: &[String], start: usize, parent: Option<usize>) -> usize {
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        if !is_content(line) {
+            continue;
+        }
+        let nested = match parent {
+            None => true,
+            // A sequence may start at the indentation of its key
+            Some(parent) => {
+                let trimmed = line.trim_start();
+                indent(line) > parent
+                    || (indent(line) == parent && (trimmed == "-" || trimmed.starts_with("- ")))
+            }
+        };
+        if !nested {
+            break;
+        }
+        end = i + 1;
+    }
+    end
+}
+
+/// `contents` with empty entries written the usual way, `name:` rather than
+/// `name: null`. A string "null" is rendered quoted and the lines of block
+/// scalars, e.g. a `bootstrap: |` script, are left alone.
+fn drop_nulls(contents: &str) -> String {
+    let mut scalar: Option<usize> = None;
+    let mut lines = vec![];
+    for line in contents.lines() {
+        if let Some(opened) = scalar {
+            if line.trim().is_empty() || indent(line) > opened {
+                lines.push(line);
+                continue;
+            }
+            scalar = None;
+        }
+        let opens_scalar = line.split_whitespace().last().is_some_and(|last| {
+            let mut chars = last.chars();
+            matches!(chars.next(), Some('|' | '>'))
+                && chars.all(|c| matches!(c, '-' | '+' | '0'..='9'))
+        });
+        if opens_scalar {
+            scalar = Some(indent(line));
+        }
+        lines.push(
+            line.strip_suffix(": null")
+                .map_or(line, |k| &line[..k.len() + 1]),
+        );
+    }
+    lines.join("\n") + "\n"
+}
+
+/// `value` as YAML lines indented by `indent`.
+fn render_lines(value: &Value, indent: usize) -> Option<Vec<String>> {
+    let rendered = serde_yaml::to_string(value).ok()?;
+    Some(
+        drop_nulls(&rendered)
+            .lines()
+            .map(|l| match l.is_empty() {
+                true => String::new(),
+                false => format!("{:indent$}{l}", ""),
+            })
+            .collect(),
+    )
+}
+
+/// `text` with the project `name` added under the workspace `ws_keys` leads
+/// to, as a repo in a list of repos when `listed`. `None` when the file is laid
+/// out in a way that is not edited line by line, e.g. with flow mappings.
+fn insert_lines(
+    text: &str,
+    ws_keys: &[String],
+    name: &str,
+    project: &Value,
+    listed: bool,
+) -> Option<String> {
+    let mut lines = text.lines().map(String::from).collect::<Vec<_>>();
+    let unit = lines
+        .iter()
+        .filter(|l| is_content(l))
+        .map(|l| indent(l))
+        .filter(|&i| i > 0)
+        .min()
+        .unwrap_or(2);
+    let mut path = vec![];
+    for k in ws_keys {
+        path.push("workspaces".to_string());
+        path.push(k.clone());
+    }
+    path.push("projects".to_string());
+
+    // The block the next key of `path` is looked up in
+    let (mut start, mut end, mut parent) = (0, block_end(&lines, 0, None), None);
+    for (depth, key) in path.iter().enumerate() {
+        let children = lines[start..end]
+            .iter()
+            .find(|l| is_content(l))
+            .map(|l| indent(l))
+            .unwrap_or(parent.map_or(0, |p| p + unit));
+        let found = (start..end).find(|&i| {
+            is_content(&lines[i])
+                && indent(&lines[i]) == children
+                && split_key(&lines[i]).is_some_and(|(k, _)| k == *key)
+        });
+        let Some(line) = found else {
+            // Whatever is missing is added at once, ending with the project
+            let mut value = Mapping::new();
+            value.insert(name.into(), project.clone());
+            let mut value = Value::Mapping(value);
+            for k in path[depth..].iter().rev() {
+                let mut parent = Mapping::new();
+                parent.insert(k.as_str().into(), value);
+                value = Value::Mapping(parent);
+            }
+            lines.splice(end..end, render_lines(&value, children)?);
+            return Some(join_lines(text, lines));
+        };
+
+        let (_, rest) = split_key(&lines[line])?;
+        let value = inline_value(rest);
+        let colon = lines[line].len() - rest.len();
+        let value_end = colon + rest.len() - rest.trim_start().len() + value.len();
+        match value {
+            "" => {}
+            // `name: ~` is about to get entries
+            "~" | "null" => lines[line].replace_range(colon..value_end, ""),
+            _ => return None,
+        }
+        start = line + 1;
+        end = block_end(&lines, start, Some(indent(&lines[line])));
+        parent = Some(indent(&lines[line]));
+    }
+
+    let first = lines[start..end].iter().find(|l| is_content(l));
+    let is_list = first.is_some_and(|l| l.trim_start().starts_with('-'));
+    let entry = match (listed, is_list) {
+        (true, true) => {
+            let repo = as_repo(name, project)?;
+            let item = render_lines(&Value::from(repo), 0)?.join("\n");
+            vec![format!("{:1$}- {item}", "", indent(first?))]
+        }
+        (false, false) => {
+            let mut value = Mapping::new();
+            value.insert(name.into(), project.clone());
+            let children = first.map_or(parent? + unit, |l| indent(l));
+            render_lines(&Value::Mapping(value), children)?
+        }
+        _ => return None,
+    };
+    lines.splice(end..end, entry);
+    Some(join_lines(text, lines))
+}
+
+/// `lines` joined with the line endings of `text`.
+fn join_lines(text: &str, lines: Vec<String>) -> String {
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    lines.join(newline) + newline
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+    use serde_yaml::Value;
+
+    use super::{drop_nulls, insert_lines};
+
+    const CONFIG: &str = r#"# Directory every workspace path is relative to
+root: ~/dev
+
+workspaces:
+  example:
+    projects:
+      # Cloned by `workspaces restore`
+      hello-world:
+        git:
+          repo: octocat/Hello-World
+      scratch:
+    # Trailing comment
+  oss:
+    projects:
+    - czifro/dotfiles
+  empty: ~
+"#;
+
+    #[rstest]
+    #[case(
+        &["example"],
+        "api",
+        "{ git: { repo: o/api, branch: dev } }",
+        "      scratch:\n      api:\n        git:\n          repo: o/api\n          branch: dev\n    # Trailing comment\n"
+    )]
+    #[case(&["oss"], "cli", "{ git: { repo: o/cli } }", "    - czifro/dotfiles\n    - o/cli\n")]
+    #[case(&["empty"], "notes", "null", "  empty:\n    projects:\n      notes:\n")]
+    #[case(
+        &["example", "nested"],
+        "notes",
+        "null",
+        "      scratch:\n    workspaces:\n      nested:\n        projects:\n          notes:\n    # Trailing comment\n"
+    )]
+    #[case(&["new"], "notes", "null", "  empty: ~\n  new:\n    projects:\n      notes:\n")]
+    fn insert_projects_keeping_comments(
+        #[case] ws_keys: &[&str],
+        #[case] name: &str,
+        #[case] project: &str,
+        #[case] expected: &str,
+    ) {
+        let keys = ws_keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let project: Value = serde_yaml::from_str(project).unwrap();
+        let listed = ws_keys == ["oss"];
+
+        let edited = insert_lines(CONFIG, &keys, name, &project, listed).unwrap();
+
+        assert!(edited.contains(expected), "{edited}");
+        assert!(edited.starts_with("# Directory every workspace path is relative to\n"));
+        assert!(edited.contains("      # Cloned by `workspaces restore`\n"));
+    }
+
+    #[rstest]
+    #[case("root: /dev\nworkspaces: { w: { projects: { a: } } }\n")]
+    #[case("root: /dev\nworkspaces:\n  w:\n    projects: [czifro/dotfiles]\n")]
+    fn leave_flow_collections_to_a_rewrite(#[case] config: &str) {
+        let keys = vec!["w".to_string()];
+
+        assert!(insert_lines(config, &keys, "notes", &Value::Null, false).is_none());
+    }
+
+    #[rstest]
+    fn drop_nulls_outside_block_scalars() {
+        let contents =
+            "a: null\nb:\n  bootstrap: |-\n    x: null\n    echo done\n  c: null\nd: 'null'\n";
+
+        assert_eq!(
+            drop_nulls(contents),
+            "a:\nb:\n  bootstrap: |-\n    x: null\n    echo done\n  c:\nd: 'null'\n"
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+/// Errors of the public API. Failures without a kind of their own are
+/// `Other`, keeping the context they were reported with.
+#[derive(Debug, Error)]
+pub enum WorkspacesError {
+    #[error("No config found at {}, run `workspaces init` to create one", .0.to_string_lossy())]
+    ConfigNotFound(PathBuf),
+    #[error("Tried loading config from {}", .path.to_string_lossy())]
+    ConfigParse {
+        path: PathBuf,
+        #[source]
+        source: serde_yaml::Error,
+    },
+    #[error("Could not find workspace: {}", .0.to_string_lossy())]
+    WorkspaceNotFound(PathBuf),
+    #[error("Could not find project: {}", .0.to_string_lossy())]
+    ProjectNotFound(PathBuf),
+    #[error("{}", match .branch {
+        Some(branch) => format!("Tried cloning project with branch {branch}"),
+        None => "Tried cloning project".to_string(),
+    })]
+    GitClone {
+        path: PathBuf,
+        branch: Option<String>,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Tried creating {}", .path.to_string_lossy())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<anyhow::Error> for WorkspacesError {
+    /// Errors raised as a `WorkspacesError` internally come back as their
+    /// kind, any context added on the way up is dropped.
+    fn from(e: anyhow::Error) -> Self {
+        e.downcast::<WorkspacesError>().unwrap_or_else(Self::Other)
+    }
+}
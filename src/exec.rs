@@ -0,0 +1,354 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use anyhow::{anyhow, Context};
+use tracing::instrument;
+
+use crate::{git::GitHost, shell_command, Config, Project, WorkspacesError};
+
+pub struct ExecOptions {
+    /// Only run in the projects of this workspace and those nested in it
+    pub ws_path: Option<PathBuf>,
+    /// Projects to run in at once. With more than one, output is collected
+    /// and handed over per project instead of going straight to the terminal
+    pub jobs: usize,
+    /// Replace `{path}`, `{name}`, `{workspace}` and `{repo}` in the command
+    /// with those of each project
+    pub template: bool,
+}
+
+pub enum ExecOutcome {
+    Succeeded,
+    Failed(String),
+    /// The project is not on disk
+    Missing,
+}
+
+pub struct ExecResult {
+    pub path: PathBuf,
+    pub outcome: ExecOutcome,
+    /// What the command printed to stdout, then to stderr, when collected
+    pub output: Option<String>,
+}
+
+/// What `exec` is doing, for the front end to show.
+pub enum ExecEvent {
+    /// The command starts in the project at the path, its output streamed
+    Started(PathBuf),
+    Finished(ExecResult),
+}
+
+/// Names of the variables a command template can hold, in the order of
+/// `ProjectVars::values`
+const VARS: [&str; 4] = ["path", "name", "workspace", "repo"];
+
+/// What the variables of a command template stand for in a project.
+struct ProjectVars {
+    path: String,
+    name: String,
+    /// Path of the workspace relative to the root
+    workspace: String,
+    /// Empty for projects without git settings
+    repo: String,
+}
+
+impl ProjectVars {
+    fn new(config: &Config, path: &Path, project: &Project) -> Self {
+        let workspace = path
+            .parent()
+            .map_or(Path::new(""), |ws| config.relative_path(ws));
+        let repo = project.git.as_ref().map_or("", |git| {
+            GitHost::split_shorthand(&git.repo).map_or(git.repo.as_str(), |(_, slug)| slug)
+        });
+        ProjectVars {
+            path: path.to_string_lossy().to_string(),
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            workspace: workspace.to_string_lossy().to_string(),
+            repo: repo.to_string(),
+        }
+    }
+
+    fn values(&self) -> [&str; 4] {
+        [&self.path, &self.name, &self.workspace, &self.repo]
+    }
+}
+
+/// Replaces every `{var}` of `VARS` in `template` with `value` of its index,
+/// in a single pass so values are never expanded themselves. Other braces are
+/// left alone, shell commands have plenty of those.
+fn expand(template: &str, value: impl Fn(usize) -> String) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let tail = &rest[start + 1..];
+        let var = VARS.iter().position(|v| {
+            tail.strip_prefix(v)
+                .is_some_and(|after| after.starts_with('}'))
+        });
+        match var {
+            Some(i) => {
+                expanded.push_str(&value(i));
+                rest = &tail[VARS[i].len() + 1..];
+            }
+            None => {
+                expanded.push('{');
+                rest = tail;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// The command to run in a project, with the template variables expanded if
+/// there are `vars`.
+fn project_command(command: &[String], vars: Option<&ProjectVars>) -> Command {
+    let values = vars.map(ProjectVars::values);
+    match (command, values) {
+        ([line], None) => shell_command(line),
+        #[cfg(unix)]
+        ([line], Some(values)) => {
+            // The values are passed as arguments rather than pasted into the
+            // script, so they need no quoting
+            let script = expand(line, |i| format!("\"${}\"", i + 1));
+            let mut cmd = shell_command(&script);
+            cmd.arg("workspaces").args(values);
+            cmd
+        }
+        #[cfg(not(unix))]
+        ([line], Some(values)) => {
+            // Windows paths cannot contain quotes
+            shell_command(&expand(line, |i| format!("\"{}\"", values[i])))
+        }
+        ([program, args @ ..], values) => {
+            let expand = |arg: &String| match values {
+                Some(values) => expand(arg, |i| values[i].to_string()),
+                None => arg.clone(),
+            };
+            let mut cmd = Command::new(expand(program));
+            cmd.args(args.iter().map(expand));
+            cmd
+        }
+        ([], _) => unreachable!("checked by exec"),
+    }
+}
+
+pub struct ExecSummary {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub missing: usize,
+}
+
+/// Runs `command` in the directory of every project, in path order. A single
+/// argument is run by the shell, so it can hold pipes and the like, several
+/// are run as a program and its arguments. With `opts.template`, the command
+/// is expanded for each project, see `VARS`.
+pub fn exec(
+    config: &Config,
+    command: &[String],
+    opts: &ExecOptions,
+    mut on_event: impl FnMut(ExecEvent),
+) -> Result<ExecSummary, WorkspacesError> {
+    if command.is_empty() {
+        return Err(anyhow!("No command given").into());
+    }
+    let scope = match &opts.ws_path {
+        Some(ws_path) => {
+            config.lookup_workspace(ws_path)?;
+            Some(config.resolve_path(ws_path))
+        }
+        None => None,
+    };
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(path, _)| scope.as_ref().is_none_or(|s| path.starts_with(s)))
+        .map(|(path, project)| {
+            let vars = opts
+                .template
+                .then(|| ProjectVars::new(config, &path, project));
+            (path, vars)
+        })
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut summary = ExecSummary {
+        succeeded: 0,
+        failed: 0,
+        missing: 0,
+    };
+    let mut record = |result: ExecResult, on_event: &mut dyn FnMut(ExecEvent)| {
+        match result.outcome {
+            ExecOutcome::Succeeded => summary.succeeded += 1,
+            ExecOutcome::Failed(_) => summary.failed += 1,
+            ExecOutcome::Missing => summary.missing += 1,
+        }
+        on_event(ExecEvent::Finished(result));
+    };
+
+    if opts.jobs <= 1 {
+        for (path, vars) in projects {
+            if path.is_dir() {
+                on_event(ExecEvent::Started(path.clone()));
+            }
+            let result = run_in(command, path, vars, false);
+            record(result, &mut on_event);
+        }
+        return Ok(summary);
+    }
+
+    let pending = Mutex::new(projects.into_iter().collect::<VecDeque<_>>());
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|s| {
+        for _ in 0..opts.jobs {
+            let tx = tx.clone();
+            let pending = &pending;
+            s.spawn(move || loop {
+                // Not in a `while let`, which would hold the lock while running
+                let next = pending.lock().unwrap().pop_front();
+                let Some((path, vars)) = next else {
+                    break;
+                };
+                let _ = tx.send(run_in(command, path, vars, true));
+            });
+        }
+        drop(tx);
+        for result in rx {
+            record(result, &mut on_event);
+        }
+    });
+    Ok(summary)
+}
+
+#[instrument(level = "debug", skip(command, vars))]
+fn run_in(
+    command: &[String],
+    path: PathBuf,
+    vars: Option<ProjectVars>,
+    collect: bool,
+) -> ExecResult {
+    if !path.is_dir() {
+        return ExecResult {
+            path,
+            outcome: ExecOutcome::Missing,
+            output: None,
+        };
+    }
+    let mut cmd = project_command(command, vars.as_ref());
+    cmd.current_dir(&path).stdin(Stdio::null());
+
+    let run = if collect {
+        cmd.output().map(|output| {
+            let mut captured = String::from_utf8_lossy(&output.stdout).into_owned();
+            captured.push_str(&String::from_utf8_lossy(&output.stderr));
+            (output.status, Some(captured))
+        })
+    } else {
+        cmd.status().map(|status| (status, None))
+    };
+    let (outcome, output) = match run.with_context(|| format!("Tried running {}", command[0])) {
+        Ok((status, output)) if status.success() => (ExecOutcome::Succeeded, output),
+        Ok((status, output)) => (ExecOutcome::Failed(status.to_string()), output),
+        Err(e) => (ExecOutcome::Failed(format!("{e:#}")), None),
+    };
+    ExecResult {
+        path,
+        outcome,
+        output,
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::{fs, path::PathBuf};
+
+    use rstest::*;
+
+    use super::{exec, expand, ExecEvent, ExecOptions, ExecOutcome};
+    use crate::Config;
+
+    /// Fails in the project holding a `broken` file, marks the others
+    #[cfg(unix)]
+    const MARK_UNLESS_BROKEN: &str = "test ! -e broken && touch ran";
+    #[cfg(windows)]
+    const MARK_UNLESS_BROKEN: &str = "if exist broken (exit 1) else (type nul > ran)";
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    fn run_in_every_project(#[case] jobs: usize) {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-exec-{}-{jobs}", std::process::id()));
+        for dir in ["w/ok", "w/fails", "other/p"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("w/fails/broken"), "").unwrap();
+        let config = Config::from_str(&format!(
+            r#"---
+root: {}
+workspaces:
+  w:
+    projects:
+      ok:
+      fails:
+      missing:
+  other:
+    projects:
+      p:
+"#,
+            root.to_string_lossy()
+        ))
+        .unwrap();
+
+        let opts = ExecOptions {
+            ws_path: Some(PathBuf::from("w")),
+            jobs,
+            template: false,
+        };
+        let mut outcomes = vec![];
+        let summary = exec(&config, &[MARK_UNLESS_BROKEN.to_string()], &opts, |event| {
+            if let ExecEvent::Finished(result) = event {
+                outcomes.push((result.path, result.outcome));
+            }
+        })
+        .unwrap();
+        let ran = root.join("w/ok/ran").exists();
+        let ran_elsewhere = root.join("other/p/ran").exists();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            (summary.succeeded, summary.failed, summary.missing),
+            (1, 1, 1)
+        );
+        assert!(ran && !ran_elsewhere);
+        outcomes.sort_by(|a, b| a.0.cmp(&b.0));
+        assert!(matches!(
+            outcomes.as_slice(),
+            [
+                (_, ExecOutcome::Failed(_)),
+                (_, ExecOutcome::Missing),
+                (_, ExecOutcome::Succeeded)
+            ]
+        ));
+    }
+
+    #[rstest]
+    #[case("code {path}", "code /r/w/api")]
+    #[case("echo {workspace}/{name} {repo}", "echo w/api org/{name}")]
+    #[case("awk '{print}' {nope} {path", "awk '{print}' {nope} {path")]
+    #[case("{{name}}", "{api}")]
+    fn expand_known_variables(#[case] template: &str, #[case] expected: &str) {
+        let values = ["/r/w/api", "api", "w", "org/{name}"];
+
+        assert_eq!(expand(template, |i| values[i].to_string()), expected);
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::{path_buf_to_string, Config, WorkspacesError};
+
+pub enum ExportFormat {
+    Cargo,
+    Pnpm,
+    Npm,
+    /// A flake whose dev shell brings together those of the projects' flakes
+    Nix,
+}
+
+pub struct WorkspaceManifest {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+impl ExportFormat {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Self::Cargo => "Cargo.toml",
+            Self::Pnpm => "pnpm-workspace.yaml",
+            Self::Npm => "package.json",
+            Self::Nix => "flake.nix",
+        }
+    }
+
+    fn is_member(&self, proj_path: &Path) -> bool {
+        match self {
+            Self::Cargo => fs::read_to_string(proj_path.join("Cargo.toml"))
+                .map(|manifest| manifest.lines().any(|l| l.trim() == "[package]"))
+                .unwrap_or(false),
+            Self::Pnpm | Self::Npm => proj_path.join("package.json").is_file(),
+            Self::Nix => proj_path.join("flake.nix").is_file(),
+        }
+    }
+
+    fn render(&self, ws_name: &str, members: &[String]) -> Result<String> {
+        match self {
+            Self::Cargo => {
+                let mut contents = String::from(
+                    "# Generated by `workspaces export cargo`\n[workspace]\nresolver = \"2\"\nmembers = [\n",
+                );
+                for m in members.iter() {
+                    contents.push_str(&format!("    {},\n", toml_string(m)));
+                }
+                contents.push_str("]\n");
+                Ok(contents)
+            }
+            Self::Pnpm => {
+                let mut doc = serde_yaml::Mapping::new();
+                doc.insert("packages".into(), members.into());
+                serde_yaml::to_string(&doc)
+                    .map(|s| format!("# Generated by `workspaces export pnpm`\n{s}"))
+                    .context("Tried rendering pnpm workspace file")
+            }
+            Self::Npm => {
+                let doc = serde_json::json!({
+                    "name": ws_name,
+                    "private": true,
+                    "workspaces": members,
+                });
+                serde_json::to_string_pretty(&doc)
+                    .map(|s| s + "\n")
+                    .context("Tried rendering npm workspace package.json")
+            }
+            Self::Nix => Ok(render_flake(ws_name, members)),
+        }
+    }
+}
+
+/// `s` as a TOML basic string, quoted and escaped.
+fn toml_string(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `s` as a Nix string, quoted and escaped so that `${` in it is not taken
+/// for an interpolation.
+fn nix_string(s: &str) -> String {
+    let escaped = s
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t");
+    format!("\"{escaped}\"")
+}
+
+/// Name of the flake input of the project at `member`, which is relative to
+/// the workspace, e.g. `nested-api` for `nested/api`.
+fn flake_input(member: &str) -> String {
+    member
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+fn render_flake(ws_name: &str, members: &[String]) -> String {
+    let mut contents = format!(
+        "# Generated by `workspaces export nix`\n{{\n  description = {};\n\n  inputs = {{\n    nixpkgs.url = \"github:NixOS/nixpkgs/nixos-unstable\";\n",
+        nix_string(&format!("Development shell of the {ws_name} workspace"))
+    );
+    for m in members.iter() {
+        contents.push_str(&format!(
+            "    \"{}\".url = {};\n",
+            flake_input(m),
+            nix_string(&format!("path:./{m}"))
+        ));
+    }
+    let inputs = members
+        .iter()
+        .map(|m| format!("\"{}\"", flake_input(m)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    contents.push_str(&format!(
+        r#"  }};
+
+  outputs = {{ nixpkgs, ... }}@inputs:
+    let
+      members = [ {inputs} ];
+      forAllSystems = nixpkgs.lib.genAttrs [ "x86_64-linux" "aarch64-linux" "x86_64-darwin" "aarch64-darwin" ];
+    in {{
+      devShells = forAllSystems (system: {{
+        # The dev shells of the projects that have one, merged
+        default = nixpkgs.legacyPackages.${{system}}.mkShell {{
+          inputsFrom = builtins.filter (shell: shell != null)
+            (map (m: inputs.${{m}}.devShells.${{system}}.default or null) members);
+        }};
+      }});
+    }};
+}}
+"#
+    ));
+    contents
+}
+
+pub fn export_manifest(
+    config: &Config,
+    ws_path: PathBuf,
+    format: ExportFormat,
+) -> Result<WorkspaceManifest, WorkspacesError> {
+    let ws = config.lookup_workspace(&ws_path)?;
+    let ws_path = config.resolve_path(&ws_path);
+
+    let mut members = ws
+        .collect_project_paths(&ws_path)
+        .into_iter()
+        .filter(|p| format.is_member(p))
+        .map(|p| path_buf_to_string(p.strip_prefix(&ws_path).unwrap().to_path_buf()))
+        .collect::<Result<Vec<String>>>()?;
+    members.sort();
+
+    let ws_name = ws_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    Ok(WorkspaceManifest {
+        path: ws_path.join(format.file_name()),
+        contents: format.render(&ws_name, &members)?,
+    })
+}
+
+/// Whether `contents` of a manifest were written by `export`, rather than
+/// by hand. package.json has no comments, so only one holding nothing but
+/// what `export npm` writes counts.
+fn is_generated(contents: &str) -> bool {
+    if contents.starts_with("# Generated by `workspaces export") {
+        return true;
+    }
+    match serde_json::from_str::<serde_json::Value>(contents) {
+        Ok(serde_json::Value::Object(doc)) => doc
+            .keys()
+            .all(|k| matches!(k.as_str(), "name" | "private" | "workspaces")),
+        _ => false,
+    }
+}
+
+impl WorkspaceManifest {
+    /// Writes the manifest into the workspace. Unless `force` is set,
+    /// refuses to replace a manifest that `export` did not write.
+    pub fn write(&self, force: bool) -> Result<(), WorkspacesError> {
+        if !force {
+            if let Ok(existing) = fs::read_to_string(&self.path) {
+                if !is_generated(&existing) {
+                    return Err(anyhow!(
+                        "{} was not generated by workspaces, pass --force to replace it",
+                        self.path.to_string_lossy()
+                    )
+                    .into());
+                }
+            }
+        }
+        Ok(fs::write(&self.path, &self.contents).context("Tried writing workspace manifest")?)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+
+    use super::{is_generated, nix_string, toml_string, ExportFormat};
+
+    #[rstest]
+    fn render_cargo_workspace_members() {
+        let members = vec!["p0".to_string(), "nested/p1".to_string()];
+
+        let contents = ExportFormat::Cargo.render("w0", &members).unwrap();
+
+        assert!(contents.contains("[workspace]"));
+        assert!(contents.contains("    \"p0\",\n    \"nested/p1\",\n"));
+    }
+
+    #[rstest]
+    #[case("p0", r#""p0""#)]
+    #[case(r#"say "hi""#, r#""say \"hi\"""#)]
+    #[case(r"dir\p0", r#""dir\\p0""#)]
+    #[case("a\nb\u{7}", r#""a\nb\u0007""#)]
+    fn quote_toml_strings(#[case] s: &str, #[case] quoted: &str) {
+        assert_eq!(toml_string(s), quoted);
+    }
+
+    #[rstest]
+    fn escape_cargo_workspace_members() {
+        let members = vec![r#"odd "p0""#.to_string(), r"back\slash".to_string()];
+
+        let contents = ExportFormat::Cargo.render("w0", &members).unwrap();
+
+        assert!(contents.contains(r#"    "odd \"p0\"","#));
+        assert!(contents.contains(r#"    "back\\slash","#));
+    }
+
+    #[rstest]
+    fn render_nix_flake_inputs() {
+        let members = vec!["api".to_string(), "nested/web.app".to_string()];
+
+        let contents = ExportFormat::Nix.render("w0", &members).unwrap();
+
+        assert!(contents.contains("description = \"Development shell of the w0 workspace\";"));
+        assert!(contents.contains("    \"api\".url = \"path:./api\";\n"));
+        assert!(contents.contains("    \"nested-web-app\".url = \"path:./nested/web.app\";\n"));
+        assert!(contents.contains("members = [ \"api\" \"nested-web-app\" ];"));
+    }
+
+    #[rstest]
+    #[case("api", r#""api""#)]
+    #[case(r#"say "hi""#, r#""say \"hi\"""#)]
+    #[case(r"dir\api", r#""dir\\api""#)]
+    #[case("${pkgs.hello}", r#""\${pkgs.hello}""#)]
+    #[case("$HOME\n", r#""$HOME\n""#)]
+    fn quote_nix_strings(#[case] s: &str, #[case] quoted: &str) {
+        assert_eq!(nix_string(s), quoted);
+    }
+
+    #[rstest]
+    fn escape_nix_flake_strings() {
+        let members = vec![r#"we${ird}/"p0""#.to_string()];
+
+        let contents = ExportFormat::Nix.render(r"w\${0}", &members).unwrap();
+
+        assert!(
+            contents.contains(r#"description = "Development shell of the w\\\${0} workspace";"#)
+        );
+        assert!(contents.contains(r#".url = "path:./we\${ird}/\"p0\"";"#));
+    }
+
+    #[rstest]
+    fn render_npm_workspaces() {
+        let members = vec!["web".to_string()];
+
+        let contents = ExportFormat::Npm.render("w0", &members).unwrap();
+        let doc: serde_json::Value = serde_json::from_str(&contents).unwrap();
+
+        assert_eq!(doc["name"], "w0");
+        assert_eq!(doc["workspaces"][0], "web");
+    }
+
+    #[rstest]
+    #[case(ExportFormat::Cargo)]
+    #[case(ExportFormat::Pnpm)]
+    #[case(ExportFormat::Npm)]
+    #[case(ExportFormat::Nix)]
+    fn recognize_generated_manifests(#[case] format: ExportFormat) {
+        let contents = format.render("w0", &["p0".to_string()]).unwrap();
+
+        assert!(is_generated(&contents));
+    }
+
+    #[rstest]
+    #[case("[workspace]\nmembers = [\"p0\"]\n")]
+    #[case("{\"name\": \"w0\", \"scripts\": {\"build\": \"tsc\"}}")]
+    #[case("packages:\n  - p0\n")]
+    fn refuse_handwritten_manifests(#[case] contents: &str) {
+        assert!(!is_generated(contents));
+    }
+}
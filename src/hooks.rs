@@ -0,0 +1,244 @@
+use std::{ffi::OsString, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{shell_command, Config, Event, Reporter};
+
+/// Shell commands a project runs at points of its restore.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Hooks {
+    /// Run in the project directory after it was cloned
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) post_clone: Vec<String>,
+}
+
+impl Hooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.post_clone.is_empty()
+    }
+}
+
+/// Shell commands the config runs in the root around restores and syncs of
+/// projects, see `run_global_hook` for what they are told.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GlobalHooks {
+    /// Run before projects are cloned, a failure stops the restore
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) pre_restore: Vec<String>,
+    /// Run once every project is restored or failed to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) post_restore: Vec<String>,
+    /// Run before projects are pulled, a failure stops the sync
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) pre_sync: Vec<String>,
+    /// Run once every project is pulled or failed to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) post_sync: Vec<String>,
+}
+
+impl GlobalHooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pre_restore.is_empty()
+            && self.post_restore.is_empty()
+            && self.pre_sync.is_empty()
+            && self.post_sync.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum GlobalHook {
+    PreRestore,
+    PostRestore,
+    PreSync,
+    PostSync,
+}
+
+impl GlobalHook {
+    fn name(self) -> &'static str {
+        match self {
+            GlobalHook::PreRestore => "pre_restore",
+            GlobalHook::PostRestore => "post_restore",
+            GlobalHook::PreSync => "pre_sync",
+            GlobalHook::PostSync => "post_sync",
+        }
+    }
+
+    fn commands(self, hooks: &GlobalHooks) -> &[String] {
+        match self {
+            GlobalHook::PreRestore => &hooks.pre_restore,
+            GlobalHook::PostRestore => &hooks.post_restore,
+            GlobalHook::PreSync => &hooks.pre_sync,
+            GlobalHook::PostSync => &hooks.post_sync,
+        }
+    }
+}
+
+/// Runs the config's `hook` commands in the root. They find the hook in
+/// `WORKSPACES_HOOK`, the root in `WORKSPACES_ROOT`, the workspace or project
+/// the run is about in `WORKSPACES_PATH` and how many projects it covers in
+/// `WORKSPACES_PROJECTS`. Post hooks also get how many of those succeeded and
+/// failed, `done`, in `WORKSPACES_SUCCEEDED` and `WORKSPACES_FAILED`.
+pub(crate) fn run_global_hook(
+    config: &Config,
+    hook: GlobalHook,
+    path: &Path,
+    projects: usize,
+    done: Option<(usize, usize)>,
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    let commands = hook.commands(&config.hooks);
+    if commands.is_empty() {
+        return Ok(());
+    }
+    let mut env = vec![
+        ("WORKSPACES_HOOK", OsString::from(hook.name())),
+        ("WORKSPACES_ROOT", config.root.clone().into_os_string()),
+        ("WORKSPACES_PATH", path.as_os_str().to_os_string()),
+        ("WORKSPACES_PROJECTS", projects.to_string().into()),
+    ];
+    if let Some((succeeded, failed)) = done {
+        env.push(("WORKSPACES_SUCCEEDED", succeeded.to_string().into()));
+        env.push(("WORKSPACES_FAILED", failed.to_string().into()));
+    }
+    for command in commands {
+        run_hook(command, &config.root, &env, reporter)
+            .with_context(|| format!("Tried running {} hooks", hook.name()))?;
+    }
+    Ok(())
+}
+
+/// Runs `commands` one after another in `dir`, stopping at the first one that
+/// fails.
+pub(crate) fn run_hooks(commands: &[String], dir: &Path, reporter: &dyn Reporter) -> Result<()> {
+    for command in commands {
+        run_hook(command, dir, &[], reporter)?;
+    }
+    Ok(())
+}
+
+#[instrument(level = "debug", skip(env, reporter))]
+fn run_hook(
+    command: &str,
+    dir: &Path,
+    env: &[(&str, OsString)],
+    reporter: &dyn Reporter,
+) -> Result<()> {
+    reporter.report(&Event::HookStarted {
+        path: dir.to_path_buf(),
+        command: command.to_string(),
+    });
+    let output = shell_command(command)
+        .current_dir(dir)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .output()
+        .with_context(|| format!("Tried running hook `{command}`"))?;
+
+    let mut captured = String::from_utf8_lossy(&output.stdout).into_owned();
+    captured.push_str(&String::from_utf8_lossy(&output.stderr));
+    debug!(status = %output.status, "hook finished");
+    reporter.report(&Event::HookFinished {
+        path: dir.to_path_buf(),
+        command: command.to_string(),
+        output: captured.clone(),
+    });
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Hook `{command}` failed with {}:\n{}",
+            output.status,
+            captured.trim_end()
+        ));
+    }
+    Ok(())
+}
+
+// The hooks are written for sh
+#[cfg(all(test, unix))]
+mod should {
+    use rstest::*;
+
+    use super::{run_global_hook, run_hooks, GlobalHook};
+    use crate::{Config, SilentReporter};
+
+    #[rstest]
+    #[case(&["echo one > hooked", "echo two >> hooked"], Some("one\ntwo\n"))]
+    #[case(&["echo one > hooked", "echo oops >&2; exit 3", "echo two >> hooked"], None)]
+    fn run_hooks_in_order(#[case] commands: &[&str], #[case] expected: Option<&str>) {
+        let dir = std::env::temp_dir().join(format!(
+            "workspaces-hooks-{}-{}",
+            std::process::id(),
+            commands.len()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let commands = commands.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+
+        let result = run_hooks(&commands, &dir, &SilentReporter);
+        let hooked = std::fs::read_to_string(dir.join("hooked")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        match expected {
+            Some(expected) => {
+                result.unwrap();
+                assert_eq!(hooked, expected);
+            }
+            None => {
+                let error = format!("{:#}", result.unwrap_err());
+                assert!(error.contains("exit status: 3"), "{error}");
+                assert!(error.contains("oops"), "{error}");
+                assert_eq!(hooked, "one\n");
+            }
+        }
+    }
+
+    #[rstest]
+    fn tell_global_hooks_about_the_run() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-global-hooks-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let config = Config::from_str(&format!(
+            r#"---
+root: {}
+hooks:
+  pre_restore:
+    - echo "$WORKSPACES_HOOK $WORKSPACES_PATH $WORKSPACES_PROJECTS" >> ran
+  post_restore:
+    - echo "$WORKSPACES_HOOK $WORKSPACES_SUCCEEDED $WORKSPACES_FAILED" >> ran
+workspaces:
+  w:
+"#,
+            root.to_string_lossy()
+        ))
+        .unwrap();
+        let ws = root.join("w");
+
+        run_global_hook(
+            &config,
+            GlobalHook::PreRestore,
+            &ws,
+            3,
+            None,
+            &SilentReporter,
+        )
+        .unwrap();
+        run_global_hook(&config, GlobalHook::PreSync, &ws, 3, None, &SilentReporter).unwrap();
+        let done = Some((2, 1));
+        run_global_hook(
+            &config,
+            GlobalHook::PostRestore,
+            &ws,
+            3,
+            done,
+            &SilentReporter,
+        )
+        .unwrap();
+        let ran = std::fs::read_to_string(root.join("ran")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            ran,
+            format!("pre_restore {} 3\npost_restore 2 1\n", ws.to_string_lossy())
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+
+use crate::{
+    adopt::{git_project, nearest_workspace},
+    edit::ConfigDocument,
+    git::{GitCloneProtocol, GitCloneStrategy, GitHost},
+    upstream::{self, HostedRepo},
+    Config, WorkspacesError,
+};
+
+/// Where the repositories to import are listed.
+pub enum ImportSource {
+    /// An organization or user on github.com
+    GitHub { owner: String },
+    /// A group on gitlab.com, or the self-hosted instance `host`, whose
+    /// subgroups become nested workspaces if `recursive`
+    GitLab {
+        host: Option<String>,
+        group: String,
+        recursive: bool,
+    },
+}
+
+pub struct ImportOptions {
+    /// Workspace the repositories are added to, created if needed
+    pub ws_path: PathBuf,
+    /// Import archived repositories too
+    pub archived: bool,
+    /// Import forks too
+    pub forks: bool,
+    /// Only import repositories with one of these topics
+    pub topics: Vec<String>,
+    /// Report what would be imported without writing the config
+    pub dry_run: bool,
+    /// Write the config even if that drops its comments
+    pub force: bool,
+}
+
+pub enum ImportStatus {
+    Imported,
+    Skipped(String),
+}
+
+pub struct ImportEntry {
+    pub path: PathBuf,
+    pub repo: String,
+    pub status: ImportStatus,
+}
+
+pub struct ImportReport {
+    pub config_path: PathBuf,
+    pub dry_run: bool,
+    pub entries: Vec<ImportEntry>,
+    /// Repositories left out by the archived, fork and topic filters
+    pub filtered: usize,
+}
+
+impl ImportReport {
+    pub fn imported(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, ImportStatus::Imported))
+            .count()
+    }
+
+    pub fn print(&self) {
+        for e in self.entries.iter() {
+            let path = e.path.to_string_lossy();
+            match &e.status {
+                ImportStatus::Imported => println!("imported\t{path}\t{}", e.repo),
+                ImportStatus::Skipped(reason) => println!("skipped\t{path}\t{reason}"),
+            }
+        }
+        if !self.entries.is_empty() {
+            println!();
+        }
+
+        if self.filtered > 0 {
+            println!("{} repositories did not match the filters", self.filtered);
+        }
+        let config_path = self.config_path.to_string_lossy();
+        if self.dry_run {
+            println!(
+                "{} projects would be added to {config_path}, run without --dry-run to write them",
+                self.imported()
+            );
+        } else {
+            println!("Added {} projects to {config_path}", self.imported());
+        }
+    }
+}
+
+impl ImportOptions {
+    fn wants(&self, repo: &HostedRepo) -> bool {
+        (self.archived || !repo.archived)
+            && (self.forks || !repo.fork)
+            && (self.topics.is_empty() || repo.topics.iter().any(|t| self.topics.contains(t)))
+    }
+}
+
+/// Adds the repositories `source` lists as projects of `opts.ws_path`, named
+/// after each repository, with subgroups as nested workspaces. Nothing is
+/// cloned, `restore` does that.
+pub fn import(
+    config: &Config,
+    source: ImportSource,
+    opts: &ImportOptions,
+) -> Result<ImportReport, WorkspacesError> {
+    let ws_path = config.resolve_path(&opts.ws_path);
+    let root = config.root_of(&ws_path).to_path_buf();
+    let rel_dir = ws_path
+        .strip_prefix(&root)
+        .ok()
+        .filter(|rel| !rel.as_os_str().is_empty())
+        .ok_or_else(|| {
+            anyhow!(
+                "{} is not a workspace below the root {}",
+                ws_path.to_string_lossy(),
+                root.to_string_lossy()
+            )
+        })?;
+    if config.lookup_project(&ws_path).is_ok() {
+        return Err(anyhow!("{} is a project", ws_path.to_string_lossy()).into());
+    }
+
+    let (host, mut repos) = match source {
+        ImportSource::GitHub { owner } => (
+            GitHost::GitHub,
+            upstream::list_github_repos(&owner)
+                .with_context(|| format!("Tried listing the repositories of {owner}"))?,
+        ),
+        ImportSource::GitLab {
+            host,
+            group,
+            recursive,
+        } => {
+            let host = host.map_or(GitHost::GitLab, |h| GitHost::by_name(&h));
+            let repos = upstream::list_gitlab_repos(&host, &group, recursive)
+                .with_context(|| format!("Tried listing the projects of {group} on {host}"))?;
+            (host, repos)
+        }
+    };
+
+    let mut doc = ConfigDocument::load(opts.force)?;
+    let mut entries = vec![];
+    let mut filtered = 0;
+    repos.sort_by(|a, b| (&a.dir, &a.name).cmp(&(&b.dir, &b.name)));
+    for repo in repos {
+        if !opts.wants(&repo) {
+            filtered += 1;
+            continue;
+        }
+        let dir = rel_dir.join(&repo.dir);
+        let path = root.join(&dir).join(&repo.name);
+        let status = if config.lookup_project(&path).is_ok() {
+            ImportStatus::Skipped("already in the config".to_string())
+        } else if config.lookup_workspace(&path).is_ok() {
+            ImportStatus::Skipped("a workspace has this path".to_string())
+        } else {
+            let (mut keys, inherited, missing) = nearest_workspace(Some(config), &root, &dir);
+            keys.extend(missing);
+            let protocol = inherited
+                .protocol
+                .clone()
+                .unwrap_or(GitCloneProtocol::Https);
+            let worktree = inherited
+                .clone_strategy
+                .as_ref()
+                .is_some_and(GitCloneStrategy::is_worktree);
+            let project = git_project(
+                host.clone(),
+                protocol.clone(),
+                &repo.repo,
+                worktree,
+                &inherited,
+            )?;
+            doc.insert_project(&keys, &repo.name, project)?;
+            ImportStatus::Imported
+        };
+        entries.push(ImportEntry {
+            path,
+            repo: repo.repo,
+            status,
+        });
+    }
+
+    let report = ImportReport {
+        config_path: Config::file_path()?,
+        dry_run: opts.dry_run,
+        entries,
+        filtered,
+    };
+    if !opts.dry_run && report.imported() > 0 {
+        doc.save()?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::PathBuf;
+
+    use rstest::*;
+
+    use super::ImportOptions;
+    use crate::upstream::HostedRepo;
+
+    #[rstest]
+    #[case(false, false, &[], true, false, false)]
+    #[case(true, true, &[], true, true, true)]
+    #[case(false, false, &["rust"], true, false, false)]
+    #[case(false, false, &["go"], false, false, false)]
+    fn filter_listed_repos(
+        #[case] archived: bool,
+        #[case] forks: bool,
+        #[case] topics: &[&str],
+        #[case] plain: bool,
+        #[case] archived_repo: bool,
+        #[case] fork: bool,
+    ) {
+        let opts = ImportOptions {
+            ws_path: PathBuf::from("w"),
+            archived,
+            forks,
+            topics: topics.iter().map(|t| t.to_string()).collect(),
+            dry_run: true,
+            force: false,
+        };
+        let repo = |archived, fork| HostedRepo {
+            repo: "org/name".to_string(),
+            name: "name".to_string(),
+            dir: String::new(),
+            archived,
+            fork,
+            topics: vec!["rust".to_string(), "cli".to_string()],
+        };
+
+        assert_eq!(opts.wants(&repo(false, false)), plain);
+        assert_eq!(opts.wants(&repo(true, false)), archived_repo);
+        assert_eq!(opts.wants(&repo(false, true)), fork);
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    cursor::MoveTo,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::Print,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{Config, WorkspacesError};
+
+/// Project paths relative to the root that fuzzily match `query`, best match
+/// first. An empty query matches every project.
+pub fn rank_projects(config: &Config, query: &str) -> Vec<PathBuf> {
+    let mut projects = config
+        .collect_project_paths()
+        .iter()
+        .map(|p| config.relative_path(p).to_path_buf())
+        .collect::<Vec<PathBuf>>();
+    projects.sort();
+
+    let matcher = SkimMatcherV2::default();
+    let mut ranked = projects
+        .into_iter()
+        .filter_map(|p| {
+            let score = matcher.fuzzy_match(&p.to_string_lossy(), query)?;
+            Some((score, p))
+        })
+        .collect::<Vec<(i64, PathBuf)>>();
+    // Stable, so equal scores stay in path order
+    ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    ranked.into_iter().map(|(_, p)| p).collect()
+}
+
+/// Absolute path of the project that best matches `query`.
+pub fn best_project(config: &Config, query: &str) -> Option<PathBuf> {
+    rank_projects(config, query)
+        .first()
+        .map(|p| config.resolve_path(p))
+}
+
+/// Restores the terminal however the picker exits.
+struct Screen;
+
+impl Screen {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode().context("Tried switching the terminal to raw mode")?;
+        let screen = Self;
+        crossterm::execute!(io::stderr(), EnterAlternateScreen)
+            .context("Tried switching to the alternate screen")?;
+        Ok(screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(io::stderr(), LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Lets the user pick a project by typing to narrow them down, drawn on
+/// stderr so stdout can be captured. Returns the absolute path of the chosen
+/// project, or `None` when the picker was dismissed.
+pub fn pick_project(config: &Config, query: &str) -> Result<Option<PathBuf>, WorkspacesError> {
+    let _screen = Screen::enter()?;
+    let mut query = query.to_string();
+    let mut selected = 0;
+    loop {
+        let ranked = rank_projects(config, &query);
+        selected = selected.min(ranked.len().saturating_sub(1));
+        draw(&query, &ranked, selected).context("Tried drawing the picker")?;
+
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read().context("Tried reading input")?
+        else {
+            continue;
+        };
+        let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+        match code {
+            KeyCode::Enter => {
+                return Ok(ranked.get(selected).map(|p| config.resolve_path(p)));
+            }
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if ctrl => return Ok(None),
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Char('p') if ctrl => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected += 1,
+            KeyCode::Char('n') if ctrl => selected += 1,
+            KeyCode::Backspace => {
+                query.pop();
+                selected = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                query.push(c);
+                selected = 0;
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(query: &str, ranked: &[PathBuf], selected: usize) -> io::Result<()> {
+    let mut err = io::stderr().lock();
+    let (_, rows) = terminal::size()?;
+    queue!(
+        err,
+        Clear(ClearType::All),
+        MoveTo(0, 0),
+        Print(format!("> {query}"))
+    )?;
+    // Terminals that do not report their size get every match
+    let visible = match rows {
+        0 => ranked.len(),
+        rows => rows as usize - 1,
+    };
+    // Keep the selection in view
+    let first = selected.saturating_sub(visible.saturating_sub(1));
+    for (row, (i, p)) in ranked
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(visible)
+        .enumerate()
+    {
+        let marker = if i == selected { "> " } else { "  " };
+        queue!(
+            err,
+            MoveTo(0, row as u16 + 1),
+            Print(format!("{marker}{}", p.to_string_lossy()))
+        )?;
+    }
+    queue!(err, MoveTo(2 + query.width() as u16, 0))?;
+    err.flush()
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::PathBuf;
+
+    use rstest::*;
+
+    use super::rank_projects;
+    use crate::Config;
+
+    #[rstest]
+    #[case("", &["src/api", "src/nested/api-client", "work/web"])]
+    #[case("api", &["src/api", "src/nested/api-client"])]
+    #[case("wweb", &["work/web"])]
+    #[case("zzz", &[])]
+    fn rank_fuzzy_matches(#[case] query: &str, #[case] expected: &[&str]) {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  src:
+    projects:
+      api:
+    workspaces:
+      nested:
+        projects:
+          api-client:
+  work:
+    projects:
+      web:
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rank_projects(&config, query),
+            expected.iter().map(PathBuf::from).collect::<Vec<_>>()
+        );
+    }
+}
@@ -1,40 +1,189 @@
 use std::{
+    collections::HashMap,
     fs,
+    future::Future,
     path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+    time::Instant,
 };
 
 use anyhow::{anyhow, Context, Result};
+use tokio::{
+    sync::{mpsc, Semaphore},
+    task::JoinSet,
+};
+use tracing::{debug, instrument, warn, Span};
 
+mod add;
+mod adopt;
+mod archive;
+mod backup;
+mod bundle;
+mod cache;
+mod cancel;
 mod config;
+mod config_repo;
+mod devcontainer;
+mod direnv;
+mod doctor;
+mod edit;
+mod error;
+mod exec;
+mod export;
 mod git;
+mod hooks;
+mod import;
+mod jump;
+mod links;
+mod lint;
+mod lock;
+mod login;
+mod mirror;
+mod new;
+mod open;
+mod output;
+mod prune;
+mod pull;
+mod push;
+mod repair;
+mod report;
+mod resolved;
+mod restore_report;
+mod scan;
+mod search;
+mod self_update;
+mod ssh_config;
+mod state;
+mod status;
+mod tmux;
+mod toolchains;
+mod tree;
+mod ui;
+mod upstream;
+mod verify;
+mod watch;
+mod worktree;
 
+pub use add::*;
+pub use adopt::*;
+pub use archive::*;
+pub use backup::*;
+pub use bundle::*;
+pub use cancel::*;
 pub use config::*;
+pub use config_repo::*;
+use devcontainer::write_devcontainers;
+use direnv::write_envrcs;
+pub use doctor::*;
+pub use error::*;
+pub use exec::*;
+pub use export::*;
 use git::Git;
+pub use hooks::*;
+pub use import::*;
+pub use jump::*;
+pub use links::*;
+pub use lint::*;
+use lock::Lock;
+pub use login::*;
+pub use mirror::*;
+pub use new::*;
+pub use open::*;
+pub use output::*;
+pub use prune::*;
+pub use pull::*;
+pub use push::*;
+pub use repair::*;
+use report::ChannelReporter;
+pub use report::*;
+pub use resolved::*;
+pub use restore_report::*;
+pub use search::*;
+pub use self_update::*;
+pub use state::*;
+use state::{archived_projects, Outcome};
+pub use status::*;
+pub use tmux::*;
+pub use toolchains::*;
+pub use tree::*;
+pub use ui::*;
+pub use verify::*;
+pub use watch::*;
+pub use worktree::*;
 
+/// `path` as text, for the config file and manifests that cannot hold
+/// anything but UTF-8.
 pub(crate) fn path_buf_to_string(path: PathBuf) -> Result<String> {
     path.into_os_string()
         .into_string()
-        .map_err(|os| anyhow!("{:#?}", os))
+        .map_err(|os| anyhow!("{} is not valid UTF-8", os.to_string_lossy()))
         .context("Tried converting path to string")
 }
 
-pub(crate) fn try_absolute_path(path: String) -> Result<String> {
-    let path = PathBuf::from(path);
-    let path: PathBuf = match path.strip_prefix("~") {
-        Err(_) => path,
-        Ok(path) => {
-            let home_dir = home::home_dir().unwrap();
-            home_dir.join(path)
+/// `path` with a leading `~` replaced by the home directory. Works on the path
+/// as is, so names that are not UTF-8 come through unchanged.
+pub(crate) fn expand_home(path: impl AsRef<Path>) -> PathBuf {
+    let path = path.as_ref();
+    match path.strip_prefix("~") {
+        Err(_) => path.to_path_buf(),
+        Ok(rest) => {
+            let home_dir = home::home_dir().expect("Could not determine home directory");
+            home_dir.join(rest)
         }
+    }
+}
+
+/// The `workspaces` directory in the XDG base directory `xdg_var` names, or on
+/// Windows in the known folder `windows_var` names, falling back to
+/// `home_default` in the home directory.
+fn user_dir(xdg_var: &str, windows_var: &str, home_default: &str) -> PathBuf {
+    let from_env = |var: &str| {
+        std::env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
     };
+    from_env(xdg_var)
+        .or_else(|| from_env(windows_var).filter(|_| cfg!(windows)))
+        .unwrap_or_else(|| {
+            let home_dir = home::home_dir().expect("Could not determine home directory");
+            home_dir.join(home_default)
+        })
+        .join("workspaces")
+}
+
+pub(crate) fn config_dir() -> PathBuf {
+    user_dir("XDG_CONFIG_HOME", "APPDATA", ".config")
+}
 
-    path_buf_to_string(path).context("Tried making path absolute")
+pub(crate) fn state_dir() -> PathBuf {
+    user_dir("XDG_STATE_HOME", "LOCALAPPDATA", ".local/state")
 }
 
-pub(crate) fn absolute_path(path: String) -> String {
-    try_absolute_path(path).unwrap()
+pub(crate) fn data_dir() -> PathBuf {
+    user_dir("XDG_DATA_HOME", "LOCALAPPDATA", ".local/share")
 }
 
+/// Runs `command` with the platform's shell, `sh` or else `cmd`.
+pub(crate) fn shell_command(command: &str) -> Command {
+    #[cfg(unix)]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+
+        // cmd does its own parsing, which quoting the command would break
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").raw_arg(command);
+        cmd
+    }
+}
+
+#[derive(Debug)]
 pub enum RestoreOption {
     Workspace {
         ws_path: PathBuf,
@@ -48,160 +197,457 @@ pub enum RestoreOption {
     },
 }
 
-pub fn restore(config: &Config, opt: RestoreOption) -> Result<()> {
-    let diagnosis = doctor(config)?;
+/// Restores like [`restore_async`], blocking until it is done. It starts a
+/// runtime of its own, so async code awaits `restore_async` instead.
+#[instrument(skip(config, reporter), err)]
+pub fn restore(
+    config: &Config,
+    opt: RestoreOption,
+    jobs: usize,
+    reporter: &dyn Reporter,
+) -> Result<(), WorkspacesError> {
+    restore_with_report(config, opt, jobs, reporter)?.into_result()
+}
 
-    match opt {
+/// Like `restore`, but failed projects and steps are handed back in the
+/// report, only what stops the whole restore is an error.
+#[instrument(level = "debug", skip(config, reporter), err)]
+pub fn restore_with_report(
+    config: &Config,
+    opt: RestoreOption,
+    jobs: usize,
+    reporter: &dyn Reporter,
+) -> Result<RestoreReport, WorkspacesError> {
+    block_on(restore_async(
+        config,
+        opt,
+        jobs,
+        reporter,
+        &CancellationToken::new(),
+    ))?
+}
+
+/// Creates the workspaces `opt` names and clones their missing projects as
+/// tasks on tokio's blocking pool, up to `jobs` at once. No more clones start
+/// once `cancel` is cancelled. The events of every task reach `reporter` from
+/// the awaiting task.
+#[instrument(skip(config, reporter, cancel), err)]
+pub async fn restore_async(
+    config: &Config,
+    opt: RestoreOption,
+    jobs: usize,
+    reporter: &dyn Reporter,
+    cancel: &CancellationToken,
+) -> Result<RestoreReport, WorkspacesError> {
+    let started = Instant::now();
+    let tally = RestoreTally::new(reporter);
+    let reporter = &tally;
+    let config = Arc::new(config.clone());
+    let scope = match &opt {
+        RestoreOption::Workspace { ws_path, .. } => config.resolve_path(ws_path),
+        RestoreOption::AllWorkspaces { .. } => config.root.clone(),
+        RestoreOption::Project { proj_path } => config.resolve_path(proj_path),
+    };
+    // Everything is below `scope`, save for workspaces kept in other roots
+    let scopes = match &opt {
+        RestoreOption::AllWorkspaces { .. } => config
+            .root_dirs()
+            .into_iter()
+            .map(Path::to_path_buf)
+            .collect(),
+        _ => vec![scope.clone()],
+    };
+    let (_lock, projects) = blocking(reporter, {
+        let config = config.clone();
+        let scope = scope.clone();
+        move |reporter| -> Result<_, WorkspacesError> {
+            let lock = Lock::acquire(&config.lock_dir(), reporter)?;
+            let explicit = matches!(opt, RestoreOption::Project { .. });
+            let mut projects = restore_workspaces(&config, opt, reporter)?;
+            if !explicit {
+                // Archived projects only come back when asked for by name
+                let archived = archived_projects(&config);
+                projects.retain(|p| !archived.contains_key(p));
+            }
+
+            let missing = projects.iter().filter(|p| !p.exists()).count();
+            if missing > 0 {
+                let hook = GlobalHook::PreRestore;
+                run_global_hook(&config, hook, &scope, missing, None, reporter)?;
+            }
+            Ok((lock, projects))
+        }
+    })
+    .await?;
+
+    let results = restore_projects(config.clone(), projects, jobs, reporter, cancel).await;
+    let (results, steps) = blocking(reporter, move |reporter| {
+        // Links and environments go first, bootstraps may rely on them
+        let links = scopes
+            .iter()
+            .flat_map(|scope| create_links(&config, scope, reporter))
+            .collect();
+        let envrcs = scopes
+            .iter()
+            .flat_map(|scope| write_envrcs(&config, scope, reporter))
+            .collect();
+        let devcontainers = write_devcontainers(&config, &results, reporter);
+        // Before the bootstraps too, which may need the tools
+        let toolchains = install_toolchains(&config, &results, reporter);
+        let bootstraps = bootstrap_workspaces(&config, &results, reporter);
+        let mut post_hooks = vec![];
+        let total = results.len();
+        if total > 0 {
+            let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+            let done = Some((total - failed, failed));
+            let hook = GlobalHook::PostRestore;
+            if let Err(e) = run_global_hook(&config, hook, &scope, total, done, reporter) {
+                post_hooks.push((config.root.clone(), e));
+            }
+        }
+        let steps = [
+            (RestoreStep::Link, links),
+            (RestoreStep::Envrc, envrcs),
+            (RestoreStep::Devcontainer, devcontainers),
+            (RestoreStep::Toolchains, toolchains),
+            (RestoreStep::Bootstrap, bootstraps),
+            (RestoreStep::PostRestoreHook, post_hooks),
+        ];
+        (results, steps)
+    })
+    .await;
+    let mut restored = vec![];
+    let mut failures = vec![];
+    for (path, result) in results {
+        match result {
+            Ok(()) => restored.push(path),
+            Err(error) => failures.push(RestoreFailure {
+                path,
+                step: RestoreStep::Project,
+                error,
+            }),
+        }
+    }
+    for (step, step_failures) in steps {
+        failures.extend(
+            step_failures
+                .into_iter()
+                .map(|(path, error)| RestoreFailure { path, step, error }),
+        );
+    }
+
+    let (created, received_bytes) = tally.totals();
+    Ok(RestoreReport {
+        restored,
+        created,
+        received_bytes,
+        elapsed: started.elapsed(),
+        failures,
+    })
+}
+
+/// Runs the bootstrap of each workspace projects were just restored into,
+/// nested workspaces first and after every project's hooks. A workspace with
+/// a project that failed to restore is not bootstrapped.
+#[instrument(level = "debug", skip_all)]
+pub(crate) fn bootstrap_workspaces(
+    config: &Config,
+    restored: &[(PathBuf, Result<()>)],
+    reporter: &dyn Reporter,
+) -> Vec<(PathBuf, anyhow::Error)> {
+    let mut workspaces = restored
+        .iter()
+        .flat_map(|(path, _)| config.enclosing_workspaces(path))
+        .filter(|(_, ws)| !ws.bootstrap.is_empty())
+        .collect::<Vec<_>>();
+    workspaces.sort_by(|a, b| {
+        let depth = |p: &PathBuf| p.components().count();
+        depth(&b.0).cmp(&depth(&a.0)).then(a.0.cmp(&b.0))
+    });
+    workspaces.dedup_by(|a, b| a.0 == b.0);
+
+    let mut failures = vec![];
+    for (ws_path, ws) in workspaces {
+        let mut projects = restored.iter().filter(|(p, _)| p.starts_with(&ws_path));
+        if projects.clone().next().is_none() {
+            continue;
+        }
+        if projects.any(|(_, r)| r.is_err()) {
+            debug!(path = %ws_path.to_string_lossy(), "skipping bootstrap, a project failed");
+            continue;
+        }
+        if let Err(e) = run_hooks(&ws.bootstrap, &ws_path, reporter) {
+            warn!(path = %ws_path.to_string_lossy(), "failed to bootstrap: {e:#}");
+            failures.push((ws_path, e));
+        }
+    }
+    failures
+}
+
+/// Creates the workspace directories `opt` asks for and returns the projects
+/// that should be restored into them.
+#[instrument(level = "debug", skip(config, reporter))]
+fn restore_workspaces(
+    config: &Config,
+    opt: RestoreOption,
+    reporter: &dyn Reporter,
+) -> Result<Vec<PathBuf>> {
+    let projects = match opt {
         RestoreOption::Workspace {
             ws_path,
             include_projects,
         } => {
             let ws = config.lookup_workspace(&ws_path)?;
+            let ws_path = config.resolve_path(&ws_path);
 
-            // match ws_path.parent() {
-            //     Some(parent) if parent != Path::new("") => {
-            //         restore(
-            //             config,
-            //             RestoreOption::Workspace {
-            //                 ws_path: parent.to_path_buf(),
-            //                 include_projects: false,
-            //             },
-            //         )?;
-            //     }
-            //     _ => {}
-            // }
-
-            let mut ws_path = ws_path;
-            if !ws_path.starts_with(&config.root) {
-                ws_path = PathBuf::from(&config.root).join(ws_path);
+            // Nested workspaces need their parent workspace restored first
+            if let Some(parent) = ws_path.parent() {
+                if config.lookup_workspace(parent).is_ok() {
+                    restore_workspaces(
+                        config,
+                        RestoreOption::Workspace {
+                            ws_path: parent.to_path_buf(),
+                            include_projects: false,
+                        },
+                        reporter,
+                    )?;
+                }
             }
-            let ws_path = ws_path;
 
-            if diagnosis.missing_workspaces.contains(&ws_path) {
-                fs::create_dir(&ws_path).context("Tried restoring workspace")?;
+            if !ws_path.exists() {
+                fs::create_dir_all(&ws_path).map_err(|source| WorkspacesError::Io {
+                    path: ws_path.clone(),
+                    source,
+                })?;
+                reporter.report(&Event::WorkspaceCreated {
+                    path: ws_path.clone(),
+                });
             }
 
             if !include_projects {
-                return Ok(());
+                return Ok(vec![]);
             }
 
-            for project in ws.collect_project_paths(&ws_path).iter() {
-                restore_project(&config, &project)?;
-            }
+            ws.collect_project_paths(&ws_path)
         }
         RestoreOption::AllWorkspaces { include_projects } => {
-            for ws_path in diagnosis.missing_workspaces.iter() {
-                restore(
+            let mut missing_workspaces = config
+                .collect_workspace_paths()
+                .into_iter()
+                .filter(|p| !p.exists())
+                .collect::<Vec<PathBuf>>();
+            missing_workspaces.sort();
+            for ws_path in missing_workspaces {
+                restore_workspaces(
                     config,
                     RestoreOption::Workspace {
-                        ws_path: ws_path.clone(),
-                        include_projects,
+                        ws_path,
+                        include_projects: false,
                     },
+                    reporter,
                 )?;
             }
+
+            if !include_projects {
+                return Ok(vec![]);
+            }
+
+            config.collect_project_paths()
         }
         RestoreOption::Project { proj_path } => {
-            restore(
+            restore_workspaces(
                 config,
                 RestoreOption::Workspace {
                     ws_path: proj_path.parent().unwrap().to_path_buf(),
                     include_projects: false,
                 },
+                reporter,
             )?;
 
-            let mut proj_path = proj_path;
-            if !proj_path.starts_with(&config.root) {
-                proj_path = PathBuf::from(&config.root).join(proj_path);
-            }
-            let proj_path = proj_path;
+            vec![config.resolve_path(&proj_path)]
+        }
+    };
 
-            if !diagnosis.missing_projects.contains(&proj_path) {
-                return Ok(());
+    Ok(projects)
+}
+
+/// Runs `future` to completion on a runtime of its own, for the blocking API.
+pub(crate) fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .context("Tried starting the async runtime")?;
+    Ok(runtime.block_on(future))
+}
+
+/// Runs `f` on tokio's blocking pool, passing the events it reports on to
+/// `reporter` as they come.
+async fn blocking<T, F>(reporter: &dyn Reporter, f: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce(&dyn Reporter) -> T + Send + 'static,
+{
+    let (events, mut received) = mpsc::unbounded_channel();
+    let span = Span::current();
+    let task = tokio::task::spawn_blocking(move || {
+        let _span = span.enter();
+        f(&ChannelReporter(events))
+    });
+    // The channel closes once `f` returned and dropped its sender
+    while let Some(event) = received.recv().await {
+        reporter.report(&event);
+    }
+    task.await
+        .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// Restores missing projects as tasks, up to `jobs` at once, returning the
+/// outcome for each. A failed project does not stop the others. Projects of a
+/// host with a limit in the config's `jobs.per_host` wait for one of its
+/// slots before taking one of the `jobs`. Projects not started when `cancel`
+/// is cancelled fail.
+#[instrument(level = "debug", skip_all, fields(projects = projects.len(), jobs = jobs))]
+pub(crate) async fn restore_projects(
+    config: Arc<Config>,
+    projects: Vec<PathBuf>,
+    jobs: usize,
+    reporter: &dyn Reporter,
+    cancel: &CancellationToken,
+) -> Vec<(PathBuf, Result<()>)> {
+    let mut missing = projects
+        .into_iter()
+        .filter(|p| !p.exists())
+        .collect::<Vec<PathBuf>>();
+    missing.sort();
+
+    let total = missing.len();
+    let jobs = jobs.clamp(1, total.max(1));
+    reporter.report(&Event::RestoreStarted { total, jobs });
+    let job_slots = Arc::new(Semaphore::new(jobs));
+    let mut host_slots = HashMap::<String, Arc<Semaphore>>::new();
+    let (events, mut received) = mpsc::unbounded_channel();
+    let mut tasks = JoinSet::new();
+
+    for (i, proj_path) in missing.into_iter().enumerate() {
+        let host_slot = config
+            .lookup_project(&proj_path)
+            .ok()
+            .and_then(|p| p.git.clone())
+            .map(|g| Git::new(proj_path.clone(), g).host_domain())
+            .and_then(|host| {
+                let limit = config.jobs.host_limit(&host)?;
+                let slots = host_slots
+                    .entry(host)
+                    .or_insert_with(|| Arc::new(Semaphore::new(limit)));
+                Some(slots.clone())
+            });
+        let job_slots = job_slots.clone();
+        let config = config.clone();
+        let reporter = ChannelReporter(events.clone());
+        let cancel = cancel.clone();
+        let span = Span::current();
+        tasks.spawn(async move {
+            // A project waiting on its host does not hold one of the jobs
+            let _host_slot = match host_slot {
+                Some(slots) => slots.acquire_owned().await.ok(),
+                None => None,
+            };
+            let _job_slot = job_slots.acquire_owned().await.ok();
+            let index = i + 1;
+            if cancel.is_cancelled() {
+                let error = anyhow!("Restore was cancelled before cloning");
+                reporter.report(&Event::ProjectRestoreFailed {
+                    path: proj_path.clone(),
+                    index,
+                    total,
+                    error: format!("{error:#}"),
+                });
+                return (proj_path, Err(error), None);
             }
 
-            restore_project(config, &proj_path)?;
-        }
-    };
+            tokio::task::spawn_blocking(move || {
+                let _span = span.enter();
+                reporter.report(&Event::ProjectRestoreStarted {
+                    path: proj_path.clone(),
+                    index,
+                    total,
+                });
+                let started = Instant::now();
+                let result = restore_project(&config, &proj_path, &reporter);
+                if let Err(ref e) = result {
+                    warn!(path = %proj_path.to_string_lossy(), "failed to restore: {e:#}");
+                }
+                reporter.report(&match result {
+                    Ok(()) => Event::ProjectRestored {
+                        path: proj_path.clone(),
+                        index,
+                        total,
+                        elapsed: started.elapsed(),
+                    },
+                    Err(ref e) => Event::ProjectRestoreFailed {
+                        path: proj_path.clone(),
+                        index,
+                        total,
+                        error: format!("{e:#}"),
+                    },
+                });
+                let outcome = Outcome {
+                    path: proj_path.clone(),
+                    duration: Some(started.elapsed()),
+                    error: result.as_ref().err().map(|e| format!("{e:#}")),
+                };
+                (proj_path, result, Some(outcome))
+            })
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+        });
+    }
+    // Every task holds a sender, so the channel closes once they are all done
+    drop(events);
+    while let Some(event) = received.recv().await {
+        reporter.report(&event);
+    }
 
-    Ok(())
+    let mut results = vec![];
+    let mut outcomes = vec![];
+    while let Some(joined) = tasks.join_next().await {
+        let (path, result, outcome) =
+            joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        outcomes.extend(outcome);
+        results.push((path, result));
+    }
+    state::record(&config, Operation::Restore, outcomes);
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    results
 }
 
-fn restore_project(config: &Config, proj_path: &PathBuf) -> Result<()> {
+#[instrument(level = "debug", skip(config, reporter))]
+fn restore_project(config: &Config, proj_path: &PathBuf, reporter: &dyn Reporter) -> Result<()> {
     if proj_path.exists() {
+        debug!("project exists");
         return Ok(());
     }
     let project = config.lookup_project(proj_path)?;
 
     let Some(ref proj_git) = project.git else {
-        return fs::create_dir(proj_path).context("Tried creating project directory");
+        debug!("project has no git settings, creating an empty directory");
+        fs::create_dir(proj_path).map_err(|source| WorkspacesError::Io {
+            path: proj_path.clone(),
+            source,
+        })?;
+        return Ok(());
     };
 
     let mut g = Git::new(proj_path.clone(), proj_git.clone());
 
-    g.clone()
-}
-
-pub struct DoctorDiagnosis {
-    missing_workspaces: Vec<PathBuf>,
-    missing_projects: Vec<PathBuf>,
-}
-
-impl DoctorDiagnosis {
-    pub fn print(&self) {
-        println!("Dev Workspaces Doctor Diagnosis:\n");
-
-        println!("The following workspaces are missing:\n");
-
-        for w in self.missing_workspaces.iter() {
-            println!(
-                "\t{:}",
-                w.clone()
-                    .into_os_string()
-                    .into_string()
-                    .expect("Something unexpected happened")
-            );
-        }
-        println!("");
-
-        println!("The following projects are missing:\n");
-
-        for p in self.missing_projects.iter() {
-            println!(
-                "\t{:}",
-                p.clone()
-                    .into_os_string()
-                    .into_string()
-                    .expect("Something unexpected happened")
-            );
-        }
-        println!("");
-    }
-}
-
-pub fn doctor(config: &Config) -> Result<DoctorDiagnosis> {
-    let missing_workspaces = config
-        .collect_workspace_paths()
-        .iter()
-        .filter(|p| !p.exists())
-        .map(Clone::clone)
-        .collect::<Vec<PathBuf>>();
-    let missing_projects = config
-        .collect_project_paths()
-        .iter()
-        .filter(|p| !p.exists())
-        .map(Clone::clone)
-        .collect::<Vec<PathBuf>>();
-
-    Ok(DoctorDiagnosis {
-        missing_workspaces,
-        missing_projects,
-    })
+    g.clone(reporter)?;
+    run_hooks(&project.hooks.post_clone, proj_path, reporter)
+        .context("Tried running post_clone hooks")
 }
 
 #[cfg(test)]
 mod should {
 
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     use rstest::*;
 
@@ -234,16 +680,16 @@ workspaces:
         let config = config.unwrap();
 
         let mut workspaces = config.collect_workspace_paths();
+        workspaces.sort();
 
         assert_eq!(
-            workspaces.sort(),
-            vec![
+            workspaces,
+            [
                 PathBuf::from("/some/root/w0"),
                 PathBuf::from("/some/root/w0/w1"),
                 PathBuf::from("/some/root/w0/w1/w2"),
                 PathBuf::from("/some/root/w0/w1/w2/w3"),
             ]
-            .sort()
         );
     }
 
@@ -276,15 +722,426 @@ workspaces:
         let config = config.unwrap();
 
         let mut projects = config.collect_project_paths();
+        projects.sort();
 
         assert_eq!(
-            projects.sort(),
-            vec![
+            projects,
+            [
                 PathBuf::from("/some/root/w0/p0"),
                 PathBuf::from("/some/root/w0/w1/p1"),
                 PathBuf::from("/some/root/w0/w1/w2/p2"),
             ]
-            .sort()
         );
     }
+
+    #[rstest]
+    #[case("w0/w1")]
+    #[case("w0/w1/")]
+    #[case("/some/root/w0/w1")]
+    fn lookup_nested_paths(#[case] ws_path: &str) {
+        let contents = r#"---
+root: /some/root
+workspaces:
+  w0:
+    projects:
+      p0:
+    workspaces:
+      w1:
+        projects:
+          p1:
+"#;
+
+        let config = super::Config::from_str(contents).unwrap();
+
+        let ws = config.lookup_workspace(&PathBuf::from(ws_path));
+        assert!(ws.is_ok());
+        assert!(ws.unwrap().projects.contains_key("p1"));
+
+        assert!(config
+            .lookup_project(&PathBuf::from(ws_path).join("p1"))
+            .is_ok());
+        assert!(config
+            .lookup_project(&PathBuf::from(ws_path).join("p0"))
+            .is_err());
+    }
+
+    #[rstest]
+    fn restore_nested_workspace_with_parents() {
+        let root = std::env::temp_dir().join(format!("workspaces-restore-{}", std::process::id()));
+        let contents = format!(
+            r#"---
+root: {}
+workspaces:
+  w0:
+    projects:
+      p0:
+    workspaces:
+      w1:
+        workspaces:
+          w2:
+            projects:
+              p2:
+"#,
+            root.to_string_lossy()
+        );
+        let mut config = super::Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+        let created = CreatedWorkspaces(Default::default());
+
+        let restored = super::restore(
+            &config,
+            super::RestoreOption::Workspace {
+                ws_path: PathBuf::from("w0/w1/w2"),
+                include_projects: true,
+            },
+            1,
+            &created,
+        );
+        let exists = ["w0/w1/w2/p2", "w0/p0"].map(|p| root.join(p).is_dir());
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(restored.is_ok());
+        assert_eq!(exists, [true, false]);
+        assert_eq!(
+            created.0.into_inner().unwrap(),
+            ["w0", "w0/w1", "w0/w1/w2"].map(|w| root.join(w))
+        );
+    }
+
+    // The commands are written for sh
+    #[cfg(unix)]
+    #[rstest]
+    fn restore_a_project_without_touching_the_rest() {
+        let root = std::env::temp_dir().join(format!("workspaces-single-{}", std::process::id()));
+        let contents = format!(
+            r#"---
+root: {}
+workspaces:
+  w0:
+    bootstrap: echo w0 >> ../order
+    projects:
+      p0:
+  w1:
+    bootstrap: exit 1
+    projects:
+      p1:
+"#,
+            root.to_string_lossy()
+        );
+        let mut config = super::Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+        // A file where a workspace should be would fail any step run on it
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("w1"), "").unwrap();
+
+        let report = super::restore_with_report(
+            &config,
+            super::RestoreOption::Project {
+                proj_path: PathBuf::from("w0/p0"),
+            },
+            1,
+            &super::SilentReporter,
+        );
+        let order = std::fs::read_to_string(root.join("order"));
+        let untouched = root.join("w1").is_file();
+        let _ = std::fs::remove_dir_all(&root);
+
+        let report = report.unwrap();
+        assert_eq!(report.restored, [root.join("w0/p0")]);
+        assert!(report.failures.is_empty());
+        assert_eq!(order.unwrap(), "w0\n");
+        assert!(untouched);
+    }
+
+    // The commands are written for sh
+    #[cfg(unix)]
+    #[rstest]
+    fn bootstrap_nested_workspaces_first() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-bootstrap-{}", std::process::id()));
+        let contents = format!(
+            r#"---
+root: {}
+workspaces:
+  w0:
+    bootstrap: echo w0 >> ../order
+    projects:
+      p0:
+    workspaces:
+      w1:
+        bootstrap: ["echo w1 >> ../../order"]
+        projects:
+          p1:
+  w2:
+    bootstrap: echo w2 >> ../order
+"#,
+            root.to_string_lossy()
+        );
+        let mut config = super::Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+
+        let restored = super::restore(
+            &config,
+            super::RestoreOption::AllWorkspaces {
+                include_projects: true,
+            },
+            2,
+            &super::SilentReporter,
+        );
+        let order = std::fs::read_to_string(root.join("order"));
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(restored.is_ok());
+        assert_eq!(order.unwrap(), "w1\nw0\n");
+    }
+
+    fn config_with_projects(root: &Path, count: usize) -> super::Config {
+        let projects = (0..count)
+            .map(|i| format!("      p{i}:\n"))
+            .collect::<String>();
+        let contents = format!(
+            "---\nroot: {}\nworkspaces:\n  w0:\n    projects:\n{projects}",
+            root.to_string_lossy()
+        );
+        let mut config = super::Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+        config
+    }
+
+    #[rstest]
+    #[tokio::test]
+    async fn restore_projects_when_awaited() {
+        let root = std::env::temp_dir().join(format!("workspaces-async-{}", std::process::id()));
+        let config = config_with_projects(&root, 3);
+        let events = Events(Default::default());
+
+        let restored = super::restore_async(
+            &config,
+            super::RestoreOption::AllWorkspaces {
+                include_projects: true,
+            },
+            2,
+            &events,
+            &super::CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        let exists = ["w0/p0", "w0/p1", "w0/p2"].map(|p| root.join(p).is_dir());
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(restored.restored.len(), 3);
+        assert!(restored.failures.is_empty());
+        assert_eq!(exists, [true; 3]);
+        let events = events.0.into_inner().unwrap();
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, super::Event::RestoreStarted { total: 3, jobs: 2 })));
+        let restored = events
+            .iter()
+            .filter(|e| matches!(e, super::Event::ProjectRestored { total: 3, .. }))
+            .count();
+        assert_eq!(restored, 3);
+    }
+
+    #[rstest]
+    fn fail_projects_not_started_once_cancelled() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-async-cancel-{}", std::process::id()));
+        let config = config_with_projects(&root, 3);
+        let cancel = super::CancellationToken::new();
+        cancel.cancel();
+
+        let restored = super::block_on(super::restore_async(
+            &config,
+            super::RestoreOption::AllWorkspaces {
+                include_projects: true,
+            },
+            1,
+            &crate::SilentReporter,
+            &cancel,
+        ))
+        .unwrap()
+        .unwrap();
+        let exists = root.join("w0/p0").exists();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(restored.restored.is_empty());
+        assert_eq!(restored.failures.len(), 3);
+        assert!(restored
+            .failures
+            .iter()
+            .all(|f| f.step == super::RestoreStep::Project && f.reason().contains("cancelled")));
+        assert!(!exists);
+    }
+
+    #[rstest]
+    fn clone_one_project_at_a_time_from_a_host_limited_to_one() {
+        let root = std::env::temp_dir().join(format!("workspaces-per-host-{}", std::process::id()));
+        let origin = git2::Repository::init_bare(root.join("origin.git")).unwrap();
+        let sig = git2::Signature::now("workspaces", "workspaces@example.com").unwrap();
+        let tree = origin.treebuilder(None).unwrap().write().unwrap();
+        let tree = origin.find_tree(tree).unwrap();
+        origin
+            .commit(Some("HEAD"), &sig, &sig, "base", &tree, &[])
+            .unwrap();
+        let projects = (0..3)
+            .map(|i| {
+                format!(
+                    "      p{i}: {{ git: {{ repo: team/p{i}, host: example.com, url: '{}' }} }}\n",
+                    origin.path().to_string_lossy()
+                )
+            })
+            .collect::<String>();
+        let contents = format!(
+            "---\nroot: {}\njobs: {{ per_host: {{ example.com: 1 }} }}\nworkspaces:\n  w0:\n    projects:\n{projects}",
+            root.to_string_lossy()
+        );
+        let mut config = super::Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+        let paths = ["w0/p0", "w0/p1", "w0/p2"].map(|p| root.join(p)).to_vec();
+        let events = Events(Default::default());
+
+        let restored = super::block_on(super::restore_projects(
+            std::sync::Arc::new(config),
+            paths,
+            3,
+            &events,
+            &super::CancellationToken::new(),
+        ))
+        .unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(restored.iter().all(|(_, r)| r.is_ok()));
+        let events = events.0.into_inner().unwrap();
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, super::Event::RestoreStarted { total: 3, jobs: 3 })));
+        let mut cloning = 0;
+        for event in events {
+            match event {
+                super::Event::ProjectRestoreStarted { .. } => cloning += 1,
+                super::Event::ProjectRestored { .. } => cloning -= 1,
+                _ => {}
+            }
+            assert!(cloning <= 1);
+        }
+    }
+
+    #[rstest]
+    fn fail_pending_projects_when_cancelled_midway() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-cancel-midway-{}", std::process::id()));
+        let config = config_with_projects(&root, 3);
+        std::fs::create_dir_all(root.join("w0")).unwrap();
+        let paths = ["w0/p0", "w0/p1", "w0/p2"].map(|p| root.join(p)).to_vec();
+        let reporter = CancelOnFirstProject(super::CancellationToken::new());
+
+        let restored = super::block_on(super::restore_projects(
+            std::sync::Arc::new(config),
+            paths,
+            1,
+            &reporter,
+            &reporter.0,
+        ))
+        .unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert!(restored[0].1.is_ok());
+        for (_, result) in &restored[1..] {
+            let error = result.as_ref().unwrap_err();
+            assert!(error.to_string().contains("cancelled"));
+        }
+    }
+
+    // The commands are written for sh
+    #[cfg(unix)]
+    #[rstest]
+    #[case("with spaces", "my projects", "the app")]
+    #[case("ünïcødé", "プロジェクト", "café")]
+    fn restore_paths_with_spaces_and_unicode(
+        #[case] root_dir: &str,
+        #[case] ws: &str,
+        #[case] project: &str,
+    ) {
+        let root =
+            std::env::temp_dir().join(format!("workspaces {root_dir} {}", std::process::id()));
+        let contents = format!(
+            r#"---
+root: "{}"
+hooks:
+  post_restore: ['test "$WORKSPACES_PATH" = "$PWD/{ws}"']
+workspaces:
+  "{ws}":
+    projects:
+      "{project}":
+"#,
+            root.to_string_lossy()
+        );
+        let mut config = super::Config::from_str(&contents).unwrap();
+        config.state_dir = Some(root.join(".state"));
+
+        let found = config.find_project(project);
+        let restored = super::restore(
+            &config,
+            super::RestoreOption::Workspace {
+                ws_path: PathBuf::from(ws),
+                include_projects: true,
+            },
+            1,
+            &super::SilentReporter,
+        );
+        let exists = root.join(ws).join(project).is_dir();
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(found.unwrap(), root.join(ws).join(project));
+        assert!(restored.is_ok(), "{:#}", restored.unwrap_err());
+        assert!(exists);
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    fn expand_home_in_paths_that_are_not_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let name = OsStr::from_bytes(b"caf\xe9");
+        let home = home::home_dir().unwrap();
+
+        assert_eq!(
+            super::expand_home(Path::new("~").join(name)),
+            home.join(name)
+        );
+        assert_eq!(
+            super::expand_home(Path::new("/r").join(name)),
+            Path::new("/r").join(name)
+        );
+    }
+
+    struct Events(std::sync::Mutex<Vec<super::Event>>);
+
+    impl super::Reporter for Events {
+        fn report(&self, event: &super::Event) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    /// Cancels the restore as soon as the first project starts restoring.
+    struct CancelOnFirstProject(super::CancellationToken);
+
+    impl super::Reporter for CancelOnFirstProject {
+        fn report(&self, event: &super::Event) {
+            if let super::Event::ProjectRestoreStarted { .. } = event {
+                self.0.cancel();
+            }
+        }
+    }
+
+    struct CreatedWorkspaces(std::sync::Mutex<Vec<PathBuf>>);
+
+    impl super::Reporter for CreatedWorkspaces {
+        fn report(&self, event: &super::Event) {
+            if let super::Event::WorkspaceCreated { path } = event {
+                self.0.lock().unwrap().push(path.clone());
+            }
+        }
+    }
 }
@@ -0,0 +1,181 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{expand_home, Config, Event, Reporter};
+
+/// A symlink set up by the config, at `path` and pointing at `target`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Link {
+    pub path: PathBuf,
+    pub target: PathBuf,
+    /// Workspace or project directory the link is configured on
+    #[serde(skip)]
+    pub(crate) base: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkIssue {
+    Missing,
+    /// A symlink pointing at something else
+    WrongTarget(PathBuf),
+    /// Something other than a symlink is in the way
+    NotALink,
+}
+
+#[derive(Serialize)]
+pub struct BrokenLink {
+    #[serde(flatten)]
+    pub link: Link,
+    pub issue: LinkIssue,
+}
+
+impl Link {
+    /// Resolves a `links` entry of the workspace or project at `base`. Both
+    /// sides may be relative to `base`, and the target may start with `~`.
+    pub(crate) fn new(base: &Path, path: &str, target: &str) -> Result<Self> {
+        Ok(Self {
+            path: base.join(path),
+            target: normalize(&base.join(expand_home(target))),
+            base: base.to_path_buf(),
+        })
+    }
+
+    pub(crate) fn issue(&self) -> Option<LinkIssue> {
+        match fs::read_link(&self.path) {
+            Ok(target) if target == self.target => None,
+            Ok(target) => Some(LinkIssue::WrongTarget(target)),
+            Err(_) if fs::symlink_metadata(&self.path).is_ok() => Some(LinkIssue::NotALink),
+            Err(_) => Some(LinkIssue::Missing),
+        }
+    }
+
+    /// Creates the link, replacing a symlink that points elsewhere.
+    pub(crate) fn create(&self, reporter: &dyn Reporter) -> Result<()> {
+        if fs::read_link(&self.path).is_ok() {
+            // Windows removes links to directories as directories
+            fs::remove_file(&self.path)
+                .or_else(|e| {
+                    if cfg!(windows) {
+                        fs::remove_dir(&self.path)
+                    } else {
+                        Err(e)
+                    }
+                })
+                .context("Tried removing the previous link")?;
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context("Tried creating the link's directory")?;
+        }
+        #[cfg(unix)]
+        let linked = std::os::unix::fs::symlink(&self.target, &self.path);
+        #[cfg(windows)]
+        let linked = {
+            // Windows tells links to directories from links to files
+            let parent = self.path.parent().unwrap_or(Path::new(""));
+            if parent.join(&self.target).is_dir() {
+                std::os::windows::fs::symlink_dir(&self.target, &self.path)
+            } else {
+                std::os::windows::fs::symlink_file(&self.target, &self.path)
+            }
+        };
+        linked.with_context(|| format!("Tried linking {}", self.path.to_string_lossy()))?;
+
+        reporter.report(&Event::LinkCreated {
+            path: self.path.clone(),
+            target: self.target.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// Drops `.` and folds `..` into the component before it, without looking at
+/// the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+impl LinkIssue {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Missing => "is missing".to_string(),
+            Self::WrongTarget(target) => format!("points at {}", target.to_string_lossy()),
+            Self::NotALink => "exists but is not a symlink".to_string(),
+        }
+    }
+
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            Self::Missing | Self::WrongTarget(_) => "run `workspaces doctor --fix`",
+            Self::NotALink => "move it aside and run `workspaces doctor --fix`",
+        }
+    }
+}
+
+/// Creates the missing links below `scope` whose workspace or project
+/// directory exists, returning the ones that could not be created.
+pub(crate) fn create_links(
+    config: &Config,
+    scope: &Path,
+    reporter: &dyn Reporter,
+) -> Vec<(PathBuf, anyhow::Error)> {
+    let links = match config.collect_links() {
+        Ok(links) => links,
+        Err(e) => return vec![(scope.to_path_buf(), e)],
+    };
+
+    links
+        .into_iter()
+        .filter(|l| l.path.starts_with(scope) && l.base.is_dir())
+        .filter(|l| matches!(l.issue(), Some(LinkIssue::Missing)))
+        .filter_map(|l| l.create(reporter).err().map(|e| (l.path, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+
+    use super::{Link, LinkIssue};
+    use crate::SilentReporter;
+
+    #[rstest]
+    fn create_and_check_links() {
+        let base = std::env::temp_dir().join(format!("workspaces-links-{}", std::process::id()));
+        std::fs::create_dir_all(&base).unwrap();
+        let link = Link::new(&base, "config/.env", "../shared.env").unwrap();
+        let moved = Link::new(&base, "config/.env", "/elsewhere/shared.env").unwrap();
+        let blocked = Link::new(&base, "file", "target").unwrap();
+        std::fs::write(base.join("file"), "").unwrap();
+
+        let missing = link.issue();
+        link.create(&SilentReporter).unwrap();
+        let created = link.issue();
+        let wrong = moved.issue();
+        let not_a_link = blocked.issue();
+        let target = std::fs::read_link(base.join("config/.env"));
+        std::fs::remove_dir_all(&base).unwrap();
+
+        assert!(matches!(missing, Some(LinkIssue::Missing)));
+        assert!(created.is_none());
+        let shared = std::env::temp_dir().join("shared.env");
+        assert!(matches!(wrong, Some(LinkIssue::WrongTarget(t)) if t == shared));
+        assert!(matches!(not_a_link, Some(LinkIssue::NotALink)));
+        assert_eq!(target.unwrap(), shared);
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{git::Git, Config, Render, Workspace};
+
+/// Mistakes in the config that parse fine but make restores and lookups
+/// behave in confusing ways.
+#[derive(Serialize)]
+pub struct ConfigLint {
+    #[serde(skip)]
+    root: PathBuf,
+    pub issues: Vec<LintIssue>,
+}
+
+#[derive(Serialize)]
+pub struct LintIssue {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub kind: LintKind,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LintKind {
+    /// Another project clones the same repo and branch
+    DuplicateRepo { other: PathBuf, url: String },
+    /// Another workspace or project ends up in the same directory, also when
+    /// the names only differ in case
+    DuplicatePath { other: PathBuf },
+    /// A project with the name of a workspace next to it
+    ShadowedWorkspace,
+    /// A workspace without projects or workspaces
+    EmptyWorkspace,
+    /// A name that does not lead to a directory inside its workspace
+    Unreachable { name: String },
+}
+
+impl LintKind {
+    fn describe(&self, root: &Path) -> String {
+        let rel = |p: &Path| {
+            p.strip_prefix(root)
+                .unwrap_or(p)
+                .to_string_lossy()
+                .to_string()
+        };
+        match self {
+            LintKind::DuplicateRepo { other, url } => {
+                format!("clones {url}, as {} does", rel(other))
+            }
+            LintKind::DuplicatePath { other } => {
+                format!("shares its directory with {}", rel(other))
+            }
+            LintKind::ShadowedWorkspace => {
+                "project shares its name with a workspace next to it".to_string()
+            }
+            LintKind::EmptyWorkspace => "workspace has no projects or workspaces".to_string(),
+            LintKind::Unreachable { name } => {
+                format!("{name:?} does not name a directory inside its workspace")
+            }
+        }
+    }
+}
+
+/// A workspace or project as the config file lays it out.
+struct Node<'a> {
+    path: PathBuf,
+    /// The workspace or project is listed under this name
+    name: &'a str,
+    parent: PathBuf,
+    workspace: Option<&'a Workspace>,
+}
+
+/// Looks for projects cloning the same repo, workspaces and projects sharing
+/// a directory, projects shadowing workspaces, and empty or unreachable
+/// workspaces and projects.
+pub fn lint_config(config: &Config) -> ConfigLint {
+    let mut nodes = vec![];
+    for (name, ws) in config.workspaces.iter() {
+        let parent = config.workspace_root(ws).to_path_buf();
+        collect_nodes(name, ws, &parent, &mut nodes);
+    }
+    nodes.sort_by(|a, b| a.path.cmp(&b.path).then(a.name.cmp(b.name)));
+
+    let mut issues = vec![];
+    for node in nodes.iter() {
+        if !is_reachable(node.name) {
+            issues.push(LintIssue {
+                path: node.path.clone(),
+                kind: LintKind::Unreachable {
+                    name: node.name.to_string(),
+                },
+            });
+        }
+        if node
+            .workspace
+            .is_some_and(|ws| ws.projects.is_empty() && ws.workspaces.is_empty())
+        {
+            issues.push(LintIssue {
+                path: node.path.clone(),
+                kind: LintKind::EmptyWorkspace,
+            });
+        }
+    }
+
+    // Nodes by the directory they end up in, as a case-insensitive file
+    // system sees it
+    let mut dirs: HashMap<String, &Node> = HashMap::new();
+    for node in nodes.iter() {
+        let dir = node
+            .path
+            .components()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .to_lowercase();
+        let Some(other) = dirs.get(&dir) else {
+            dirs.insert(dir, node);
+            continue;
+        };
+        let shadowed = other.parent == node.parent
+            && other.name == node.name
+            && other.workspace.is_some() != node.workspace.is_some();
+        let kind = match shadowed {
+            true => LintKind::ShadowedWorkspace,
+            false => LintKind::DuplicatePath {
+                other: other.path.clone(),
+            },
+        };
+        issues.push(LintIssue {
+            path: node.path.clone(),
+            kind,
+        });
+    }
+
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter_map(|(path, p)| p.git.clone().map(|g| (path, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut clones: HashMap<(String, Option<String>), PathBuf> = HashMap::new();
+    for (path, proj_git) in projects {
+        let branch = proj_git.branch.clone();
+        let url = Git::new(path.clone(), proj_git).url();
+        let key = (normalize_url(&url), branch);
+        match clones.get(&key) {
+            Some(other) => issues.push(LintIssue {
+                path,
+                kind: LintKind::DuplicateRepo {
+                    other: other.clone(),
+                    url,
+                },
+            }),
+            None => {
+                clones.insert(key, path);
+            }
+        }
+    }
+
+    ConfigLint {
+        root: config.root.clone(),
+        issues,
+    }
+}
+
+fn collect_nodes<'a>(name: &'a str, ws: &'a Workspace, parent: &Path, nodes: &mut Vec<Node<'a>>) {
+    let path = parent.join(name);
+    for proj_name in ws.projects.keys() {
+        nodes.push(Node {
+            path: path.join(proj_name),
+            name: proj_name,
+            parent: path.clone(),
+            workspace: None,
+        });
+    }
+    for (nested_name, nested) in ws.workspaces.iter() {
+        collect_nodes(nested_name, nested, &path, nodes);
+    }
+    nodes.push(Node {
+        path,
+        name,
+        parent: parent.to_path_buf(),
+        workspace: Some(ws),
+    });
+}
+
+/// Whether `name` leads below the workspace it is listed in, rather than to
+/// the workspace itself or somewhere else.
+fn is_reachable(name: &str) -> bool {
+    let components = Path::new(name).components().collect::<Vec<_>>();
+    !components.is_empty() && components.iter().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// `url` without the differences that do not make it another repo.
+fn normalize_url(url: &str) -> String {
+    url.trim_end_matches('/')
+        .trim_end_matches(".git")
+        .to_lowercase()
+}
+
+impl Render for ConfigLint {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        for issue in self.issues.iter() {
+            let path = issue.path.strip_prefix(&self.root).unwrap_or(&issue.path);
+            writeln!(
+                out,
+                "{}\t{}",
+                path.to_string_lossy(),
+                issue.kind.describe(&self.root)
+            )?;
+        }
+        match self.issues.len() {
+            0 => writeln!(out, "No problems found"),
+            1 => writeln!(out, "1 problem found"),
+            n => writeln!(out, "{n} problems found"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::PathBuf;
+
+    use rstest::*;
+
+    use super::{lint_config, LintKind};
+    use crate::Config;
+
+    #[rstest]
+    #[case(
+        "a:\n    projects:\n      x: { git: { repo: o/x } }\n      y: { git: { repo: o/X } }\n",
+        "a/y",
+        LintKind::DuplicateRepo { other: PathBuf::from("/dev/a/x"), url: "https://github.com/o/X.git".into() }
+    )]
+    #[case(
+        "a:\n    projects:\n      x: { git: { repo: o/x, branch: main } }\n      y: { git: { repo: o/x, branch: dev } }\n      Api:\n      api:\n",
+        "a/api",
+        LintKind::DuplicatePath { other: PathBuf::from("/dev/a/Api") }
+    )]
+    #[case(
+        "a:\n    projects:\n      b:\n    workspaces:\n      b:\n        projects:\n          c:\n",
+        "a/b",
+        LintKind::ShadowedWorkspace
+    )]
+    #[case("a:\n  b:\n    projects:\n      c:\n", "a", LintKind::EmptyWorkspace)]
+    #[case(
+        "a:\n    projects:\n      ../c:\n",
+        "a/../c",
+        LintKind::Unreachable { name: "../c".into() }
+    )]
+    fn flag_config_mistakes(#[case] workspaces: &str, #[case] path: &str, #[case] kind: LintKind) {
+        let config = Config::from_str(&format!("root: /dev\nworkspaces:\n  {workspaces}")).unwrap();
+
+        let lint = lint_config(&config);
+
+        let issues = lint
+            .issues
+            .iter()
+            .map(|i| (i.path.strip_prefix("/dev").unwrap().to_path_buf(), &i.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(issues, vec![(PathBuf::from(path), &kind)]);
+    }
+}
@@ -0,0 +1,40 @@
+use std::{
+    fs::{self, File, TryLockError},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use tracing::debug;
+
+use crate::{Event, Reporter};
+
+/// Held while the file system is changed, so that two invocations, or
+/// `watch` and a manual run, do not create and clone the same paths at once.
+/// Released when dropped.
+pub(crate) struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Takes the lock in `dir`, waiting for whichever process holds it.
+    pub(crate) fn acquire(dir: &Path, reporter: &dyn Reporter) -> Result<Self> {
+        fs::create_dir_all(dir).context("Tried creating the lock directory")?;
+        let path = dir.join(".lock");
+        let file = File::create(&path)
+            .with_context(|| format!("Tried opening lock file {}", path.to_string_lossy()))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                reporter.report(&Event::LockWaiting { path: path.clone() });
+                file.lock()
+                    .with_context(|| format!("Tried locking {}", path.to_string_lossy()))?;
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Tried locking {}", path.to_string_lossy()));
+            }
+        }
+        debug!(path = %path.to_string_lossy(), "locked");
+        Ok(Self { _file: file })
+    }
+}
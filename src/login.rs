@@ -0,0 +1,80 @@
+use anyhow::{anyhow, Context};
+use tracing::{debug, instrument};
+
+use crate::{git::GitHost, WorkspacesError};
+
+/// Service the tokens are stored under in the OS keyring, with the host's
+/// domain as the user.
+const SERVICE: &str = "dev-workspaces";
+
+/// User the token for `host` is stored under. Ports are dropped, so one
+/// token serves every port of a domain.
+fn keyring_user(host: &GitHost) -> String {
+    host.domain()
+}
+
+fn entry(host: &GitHost) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, &keyring_user(host))
+}
+
+/// Stores `token` in the OS keyring as the access token for HTTPS clones
+/// from `host`, which is given as in the config, e.g. `github` or
+/// `git.example.com`. Returns the domain it is stored for.
+#[instrument(level = "debug", skip(token))]
+pub fn login(host: &str, token: &str) -> Result<String, WorkspacesError> {
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(anyhow!("The token is empty").into());
+    }
+    let host = GitHost::named(host);
+    entry(&host)
+        .and_then(|e| e.set_password(token))
+        .context("Tried storing the token in the OS keyring")?;
+    Ok(keyring_user(&host))
+}
+
+/// Removes the token `login` stored for `host`. Returns whether there was
+/// one.
+#[instrument(level = "debug")]
+pub fn logout(host: &str) -> Result<bool, WorkspacesError> {
+    let deleted = entry(&GitHost::named(host)).and_then(|e| e.delete_credential());
+    match deleted {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(anyhow::Error::new(e)
+            .context("Tried removing the token from the OS keyring")
+            .into()),
+    }
+}
+
+/// Token `login` stored for `host`, if any. A keyring that cannot be reached,
+/// e.g. on a headless machine, is as good as an empty one.
+pub(crate) fn keyring_token(host: &GitHost) -> Option<String> {
+    match entry(host).and_then(|e| e.get_password()) {
+        Ok(token) => Some(token),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            debug!("could not read the keyring: {e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+
+    use super::keyring_user;
+    use crate::git::GitHost;
+
+    #[rstest]
+    #[case("github", "github.com")]
+    #[case("gitlab", "gitlab.com")]
+    #[case("codeberg", "codeberg.org")]
+    #[case("sourcehut", "git.sr.ht")]
+    #[case("git.example.com", "git.example.com")]
+    #[case("git.example.com:2222", "git.example.com")]
+    fn store_tokens_by_domain(#[case] host: &str, #[case] user: &str) {
+        assert_eq!(keyring_user(&GitHost::named(host)), user);
+    }
+}
@@ -0,0 +1,131 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{git::Git, Config, WorkspacesError};
+
+pub enum MirrorStatus {
+    Pushed,
+    Failed(String),
+}
+
+pub struct MirrorEntry {
+    pub path: PathBuf,
+    pub url: String,
+    pub status: MirrorStatus,
+}
+
+pub struct MirrorReport {
+    pub entries: Vec<MirrorEntry>,
+}
+
+impl MirrorReport {
+    pub fn failed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, MirrorStatus::Failed(_)))
+            .count()
+    }
+
+    pub fn print(&self) {
+        for e in self.entries.iter() {
+            let path = e.path.to_string_lossy();
+            match e.status {
+                MirrorStatus::Pushed => println!("pushed\t{path}\t{}", e.url),
+                MirrorStatus::Failed(ref reason) => {
+                    println!("FAILED\t{path}\t{}", e.url);
+                    println!("\t\t{reason}");
+                }
+            }
+        }
+        println!();
+
+        println!(
+            "Mirrored {} repositories, {} failed",
+            self.entries.len(),
+            self.failed()
+        );
+    }
+}
+
+/// Pushes every ref of each restored project with a `mirror` remote to it.
+/// Projects whose mirror another project already pushed to are not pushed,
+/// as that would replace the other project's refs.
+pub fn mirror(config: &Config) -> Result<MirrorReport, WorkspacesError> {
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(path, _)| path.exists())
+        .filter_map(|(path, p)| {
+            let git = Git::new(path.clone(), p.git.clone()?);
+            let url = git.mirror_url()?;
+            Some((path, git, url))
+        })
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut pushed_to = HashMap::<String, PathBuf>::new();
+    let entries = projects
+        .into_iter()
+        .map(|(path, git, url)| {
+            let status = if let Some(other) = pushed_to.get(&url) {
+                MirrorStatus::Failed(format!(
+                    "{} already mirrors to {url}",
+                    other.to_string_lossy()
+                ))
+            } else {
+                pushed_to.insert(url.clone(), path.clone());
+                match git.push_mirror(&url) {
+                    Ok(()) => MirrorStatus::Pushed,
+                    Err(e) => MirrorStatus::Failed(format!("{e:#}")),
+                }
+            };
+            MirrorEntry { path, url, status }
+        })
+        .collect();
+
+    Ok(MirrorReport { entries })
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::PathBuf;
+
+    use rstest::*;
+
+    use crate::{git::Git, Config};
+
+    #[rstest]
+    fn expand_mirror_placeholders() {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  src:
+    git:
+      mirror: git@backup.example:{name}.git
+    projects:
+      a:
+        git:
+          repo: czifro/dotfiles
+      b:
+        git:
+          repo: czifro/other
+          mirror: https://gitlab.com/backup/{repo}
+"#,
+        )
+        .unwrap();
+
+        let url = |p: &str| {
+            let path = PathBuf::from(p);
+            let git = config.lookup_project(&path).unwrap().git.clone().unwrap();
+            Git::new(path, git).mirror_url()
+        };
+        assert_eq!(
+            url("/some/root/src/a").as_deref(),
+            Some("git@backup.example:dotfiles.git")
+        );
+        assert_eq!(
+            url("/some/root/src/b").as_deref(),
+            Some("https://gitlab.com/backup/czifro/other")
+        );
+    }
+}
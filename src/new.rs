@@ -0,0 +1,99 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use serde_yaml::{Mapping, Value};
+
+use crate::{
+    edit::ConfigDocument,
+    git::{self, Git, GitCloneStrategy},
+    Config, ProjectGitSettings, Reporter, WorkspacesError,
+};
+
+pub struct NewProjectOptions {
+    /// Workspace path and project name, e.g. `src/tool`
+    pub path: PathBuf,
+    /// Repository whose files the project starts with
+    pub template: Option<String>,
+    /// Repository the project is registered with and pushed to
+    pub repo: Option<String>,
+    /// Create `repo` on the host and push the initial commit to it
+    pub create_remote: bool,
+    pub private: bool,
+    /// Add the project even if that drops the config file's comments
+    pub force: bool,
+}
+
+/// Starts a project in a workspace: copies the files of a template into a
+/// fresh repository, adds the project to the config and optionally creates
+/// its repository on the host.
+pub fn new_project(
+    config: &Config,
+    opts: NewProjectOptions,
+    reporter: &dyn Reporter,
+) -> Result<PathBuf, WorkspacesError> {
+    let (Some(ws_path), Some(name)) = (opts.path.parent(), opts.path.file_name()) else {
+        return Err(anyhow!("Expected a workspace path and project name, e.g. src/tool").into());
+    };
+    let name = name.to_string_lossy().to_string();
+    let keys = config.workspace_keys(ws_path)?.to_vec();
+    let proj_path = config.resolve_path(&opts.path);
+    if proj_path.exists() || config.lookup_project(&proj_path).is_ok() {
+        return Err(anyhow!("{} already exists", proj_path.to_string_lossy()).into());
+    }
+    let ws_git = config
+        .lookup_workspace(ws_path)?
+        .git
+        .clone()
+        .unwrap_or_default();
+    let settings = |repo: &str| ProjectGitSettings {
+        core_settings: ws_git.clone(),
+        ..ProjectGitSettings::new(repo.to_string())
+    };
+
+    // Before anything is created, in case the config file cannot be edited
+    let mut project = Mapping::new();
+    if let Some(repo) = &opts.repo {
+        let mut git = Mapping::new();
+        git.insert("repo".into(), repo.as_str().into());
+        project.insert("git".into(), Value::Mapping(git));
+    }
+    let mut doc = ConfigDocument::load(opts.force)?;
+    doc.insert_project(&keys, &name, Value::Mapping(project))?;
+
+    let message = match &opts.template {
+        Some(template) => {
+            let mut template_git = settings(template);
+            // The history is replaced, a plain clone is all that is needed
+            template_git.core_settings.clone_strategy = Some(GitCloneStrategy::Branch);
+            Git::new(proj_path.clone(), template_git)
+                .clone(reporter)
+                .with_context(|| format!("Tried cloning template {template}"))?;
+            format!("Initial commit from {template}")
+        }
+        None => {
+            fs::create_dir_all(&proj_path).map_err(|source| WorkspacesError::Io {
+                path: proj_path.clone(),
+                source,
+            })?;
+            "Initial commit".to_string()
+        }
+    };
+    let g = opts
+        .repo
+        .as_ref()
+        .map(|repo| Git::new(proj_path.clone(), settings(repo)));
+    git::reinit(&proj_path, &message, g.as_ref().map(Git::url).as_deref())
+        .context("Tried starting a new history")?;
+
+    doc.save()?;
+
+    if let (Some(g), true) = (g, opts.create_remote) {
+        g.create_upstream(opts.private)
+            .and_then(|_| g.push_head())
+            .with_context(|| {
+                format!("Created {name} and added it to the config, but not its repository")
+            })?;
+    }
+
+    Ok(proj_path)
+}
@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use tracing::instrument;
+
+use crate::{restore, Config, Reporter, RestoreOption, WorkspacesError};
+
+/// Opens the project `query` names, see `Config::find_project`, in the
+/// configured `editor` or else `$VISUAL` or `$EDITOR`. A missing project is
+/// restored first.
+#[instrument(level = "debug", skip(config, reporter))]
+pub fn open_project(
+    config: &Config,
+    query: &str,
+    reporter: &dyn Reporter,
+) -> Result<PathBuf, WorkspacesError> {
+    let proj_path = config.find_project(query)?;
+    if !proj_path.exists() {
+        restore(
+            config,
+            RestoreOption::Project {
+                proj_path: proj_path.clone(),
+            },
+            1,
+            reporter,
+        )?;
+    }
+
+    let editor = config
+        .editor
+        .clone()
+        .or_else(|| {
+            ["VISUAL", "EDITOR"]
+                .iter()
+                .find_map(|v| std::env::var(v).ok().filter(|e| !e.is_empty()))
+        })
+        .ok_or_else(|| anyhow!("Set `editor` in the config, or $VISUAL or $EDITOR"))?;
+    #[cfg(unix)]
+    let mut cmd = {
+        // The path is passed as an argument rather than pasted into the
+        // command, so it needs no quoting
+        let script = if editor.contains("{path}") {
+            editor.replace("{path}", "\"$1\"")
+        } else {
+            format!("{editor} \"$1\"")
+        };
+        let mut cmd = std::process::Command::new("sh");
+        cmd.arg("-c").arg(script).arg(&editor).arg(&proj_path);
+        cmd
+    };
+    #[cfg(not(unix))]
+    let mut cmd = {
+        // Windows paths cannot contain quotes
+        let path = format!("\"{}\"", proj_path.to_string_lossy());
+        let command = if editor.contains("{path}") {
+            editor.replace("{path}", &path)
+        } else {
+            format!("{editor} {path}")
+        };
+        crate::shell_command(&command)
+    };
+
+    let status = cmd
+        .current_dir(&proj_path)
+        .status()
+        .with_context(|| format!("Tried running {editor}"))?;
+    if !status.success() {
+        return Err(anyhow!("{editor} exited with {status}").into());
+    }
+
+    Ok(proj_path)
+}
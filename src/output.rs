@@ -0,0 +1,298 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use crossterm::style::Color;
+use serde::Serialize;
+
+use crate::{
+    git::shell::{self, paint, Stream},
+    DoctorDiagnosis, DoctorFix, WorkspacesError,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// When text output is colored. `Auto` colors what goes to a terminal, unless
+/// `NO_COLOR` is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Sets when output is colored for the rest of the process.
+pub fn set_color(choice: ColorChoice) {
+    shell::set_color(choice);
+}
+
+/// Prints `error` with its causes to stderr.
+pub fn print_error(error: &anyhow::Error) {
+    eprintln!("{} {error:?}", paint(Stream::Stderr, "Error:", Color::Red));
+}
+
+/// Output that can be written for people to read or as JSON for scripts and
+/// editor plugins.
+pub trait Render: Serialize {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Writes `output` to stdout in the requested format.
+pub fn emit<T: Render + ?Sized>(output: &T, format: OutputFormat) -> Result<(), WorkspacesError> {
+    let mut out = io::stdout().lock();
+    let written = match format {
+        OutputFormat::Text => output.render_text(&mut out),
+        OutputFormat::Json => serde_json::to_writer_pretty(&mut out, output)
+            .map_err(io::Error::from)
+            .and_then(|_| writeln!(out)),
+    };
+
+    match written {
+        // The reader, e.g. `head`, has seen all it wanted
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        written => Ok(written.context("Tried writing output")?),
+    }
+}
+
+impl Render for [PathBuf] {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        for p in self.iter() {
+            writeln!(out, "{}", p.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+/// Something missing or failed, in red.
+fn problem(text: impl std::fmt::Display) -> String {
+    paint(Stream::Stdout, text, Color::Red)
+}
+
+/// Something there but not right, in yellow.
+fn warning(text: impl std::fmt::Display) -> String {
+    paint(Stream::Stdout, text, Color::Yellow)
+}
+
+/// Something created or repaired, in green.
+fn success(text: impl std::fmt::Display) -> String {
+    paint(Stream::Stdout, text, Color::Green)
+}
+
+impl Render for DoctorDiagnosis {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Dev Workspaces Doctor Diagnosis:\n")?;
+
+        writeln!(out, "The following workspaces are missing:\n")?;
+        for w in self.missing_workspaces.iter() {
+            writeln!(out, "\t{:}", problem(w.to_string_lossy()))?;
+        }
+        writeln!(out)?;
+
+        writeln!(out, "The following projects are missing:\n")?;
+        for p in self.missing_projects.iter() {
+            writeln!(out, "\t{:}", problem(p.to_string_lossy()))?;
+        }
+        writeln!(out)?;
+
+        if !self.archived_projects.is_empty() {
+            writeln!(out, "The following projects are archived:\n")?;
+            for p in self.archived_projects.iter() {
+                writeln!(out, "\t{:}", p.path.to_string_lossy())?;
+                writeln!(out, "\t\tarchive: {:}", p.archive.to_string_lossy())?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.unhealthy_projects.is_empty() {
+            writeln!(out, "The following projects need attention:\n")?;
+
+            for p in self.unhealthy_projects.iter() {
+                writeln!(out, "\t{:}", warning(p.path.to_string_lossy()))?;
+                writeln!(out, "\t\t{:}", p.issue.describe())?;
+                let rel_path = p
+                    .path
+                    .strip_prefix(&self.root)
+                    .unwrap_or(&p.path)
+                    .to_string_lossy();
+                writeln!(out, "\t\thint: {:}", p.issue.remediation(&rel_path))?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.wrong_remotes.is_empty() {
+            writeln!(
+                out,
+                "The following remotes are missing or point elsewhere:\n"
+            )?;
+
+            for r in self.wrong_remotes.iter() {
+                let remote = format!("{} ({})", r.path.to_string_lossy(), r.name);
+                writeln!(out, "\t{:}", warning(remote))?;
+                match &r.found {
+                    Some(found) => writeln!(out, "\t\texpected {:}, found {:}", r.expected, found)?,
+                    None => writeln!(out, "\t\tmissing, expected {:}", r.expected)?,
+                }
+                let fix = match r.name.as_str() {
+                    "origin" => "--repair-remotes",
+                    _ => "--fix",
+                };
+                writeln!(out, "\t\thint: run `workspaces doctor {fix}`")?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.broken_links.is_empty() {
+            writeln!(out, "The following links need attention:\n")?;
+
+            for l in self.broken_links.iter() {
+                writeln!(out, "\t{:}", warning(l.link.path.to_string_lossy()))?;
+                writeln!(out, "\t\t{:}", l.issue.describe())?;
+                writeln!(out, "\t\thint: {:}", l.issue.remediation())?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.renamed_repos.is_empty() {
+            writeln!(
+                out,
+                "The following repositories were renamed or moved upstream:\n"
+            )?;
+
+            for r in self.renamed_repos.iter() {
+                writeln!(out, "\t{:}", warning(r.path.to_string_lossy()))?;
+                writeln!(out, "\t\t{:} is now {:}", r.configured, r.canonical)?;
+                writeln!(
+                    out,
+                    "\t\thint: set `repo: {:}` in the config, or run `git -C {:} remote set-url origin {:}`",
+                    r.canonical,
+                    r.path.to_string_lossy(),
+                    r.url
+                )?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.unchecked_repos.is_empty() {
+            writeln!(
+                out,
+                "The following repositories could not be checked upstream:\n"
+            )?;
+
+            for r in self.unchecked_repos.iter() {
+                writeln!(out, "\t{:}", r.path.to_string_lossy())?;
+                writeln!(out, "\t\t{:}", r.error)?;
+            }
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Render for DoctorFix {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "Dev Workspaces Doctor Fix:\n")?;
+
+        if !self.created_workspaces.is_empty() {
+            writeln!(out, "Created the following workspaces:\n")?;
+            for w in self.created_workspaces.iter() {
+                writeln!(out, "\t{:}", success(w.to_string_lossy()))?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.restored_projects.is_empty() {
+            writeln!(out, "Restored the following projects:\n")?;
+            for p in self.restored_projects.iter() {
+                writeln!(out, "\t{:}", success(p.to_string_lossy()))?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.updated_remotes.is_empty() {
+            writeln!(out, "Set up the remotes of the following projects:\n")?;
+            for p in self.updated_remotes.iter() {
+                writeln!(out, "\t{:}", success(p.to_string_lossy()))?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.created_links.is_empty() {
+            writeln!(out, "Created the following links:\n")?;
+            for l in self.created_links.iter() {
+                writeln!(out, "\t{:}", success(l.to_string_lossy()))?;
+            }
+            writeln!(out)?;
+        }
+
+        if !self.failures.is_empty() {
+            writeln!(out, "The following could not be fixed:\n")?;
+            for f in self.failures.iter() {
+                writeln!(out, "\t{:}", problem(f.path.to_string_lossy()))?;
+                writeln!(out, "\t\t{:}", f.error)?;
+            }
+            writeln!(out)?;
+        }
+
+        writeln!(
+            out,
+            "Created {} workspaces and restored {} projects, {} failed",
+            self.created_workspaces.len(),
+            self.restored_projects.len(),
+            self.failures.len()
+        )?;
+        if self.needs_attention > 0 {
+            writeln!(
+                out,
+                "{} existing projects need attention, run `workspaces doctor` for hints",
+                self.needs_attention
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::PathBuf;
+
+    use rstest::*;
+
+    use crate::{DoctorDiagnosis, ProjectIssue, UnhealthyProject};
+
+    #[rstest]
+    fn serialize_doctor_issues() {
+        let diagnosis = DoctorDiagnosis {
+            root: PathBuf::from("/some/root"),
+            missing_workspaces: vec![],
+            missing_projects: vec![PathBuf::from("/some/root/w0/p0")],
+            archived_projects: vec![],
+            unhealthy_projects: vec![UnhealthyProject {
+                path: PathBuf::from("/some/root/w0/p1"),
+                issue: ProjectIssue::NotARepo,
+            }],
+            wrong_remotes: vec![],
+            broken_links: vec![],
+            renamed_repos: vec![],
+            unchecked_repos: vec![],
+        };
+
+        let json = serde_json::to_value(&diagnosis).unwrap();
+
+        assert_eq!(json["missing_projects"][0], "/some/root/w0/p0");
+        assert_eq!(json["unhealthy_projects"][0]["issue"], "not_a_repo");
+        assert_eq!(
+            json["unhealthy_projects"][0]["description"],
+            "exists but is not a git repository"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{lock::Lock, scan::scan, Config, Reporter, WorkspacesError};
+
+/// How deep below an unmanaged directory clones are looked for.
+const CLONE_SCAN_DEPTH: usize = 4;
+
+/// A directory below the root that the config does not declare.
+pub struct UnmanagedDir {
+    pub path: PathBuf,
+    /// Git clones in it, which pruning would delete along with their work
+    pub clones: usize,
+}
+
+pub struct PruneReport {
+    pub removed: Vec<PathBuf>,
+    pub failures: Vec<(PathBuf, String)>,
+}
+
+/// Directories below the roots that are neither a workspace, a project nor on
+/// the way to a link. Projects are not looked into, and hidden directories
+/// and symlinks are left alone. `config` should not be narrowed down by tags
+/// or a profile, or everything left out would be reported.
+pub fn find_unmanaged(config: &Config) -> Result<Vec<UnmanagedDir>, WorkspacesError> {
+    let projects = config
+        .collect_project_paths()
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let mut containers = config
+        .collect_workspace_paths()
+        .into_iter()
+        .collect::<HashSet<_>>();
+    // A root kept inside another is not unmanaged either
+    let roots = config.root_dirs();
+    containers.extend(roots.iter().map(|r| r.to_path_buf()));
+    for link in config.collect_links()? {
+        containers.extend(
+            link.path
+                .ancestors()
+                .skip(1)
+                .take_while(|a| a.starts_with(&link.base) && *a != link.base)
+                .map(Path::to_path_buf),
+        );
+    }
+
+    let mut unmanaged = vec![];
+    for root in roots.into_iter().filter(|r| r.is_dir()) {
+        collect_unmanaged(root, &projects, &containers, &mut unmanaged)
+            .with_context(|| format!("Tried scanning {}", root.to_string_lossy()))?;
+    }
+    unmanaged.sort();
+    unmanaged.dedup();
+
+    Ok(unmanaged
+        .into_iter()
+        .map(|path| {
+            let clones = if path.join(".git").exists() || path.join(".bare").is_dir() {
+                1
+            } else {
+                scan(&path, CLONE_SCAN_DEPTH).map_or(0, |found| found.len())
+            };
+            UnmanagedDir { path, clones }
+        })
+        .collect())
+}
+
+fn collect_unmanaged(
+    dir: &Path,
+    projects: &HashSet<PathBuf>,
+    containers: &HashSet<PathBuf>,
+    unmanaged: &mut Vec<PathBuf>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() || entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if projects.contains(&path) {
+            continue;
+        }
+        if containers.contains(&path) {
+            collect_unmanaged(&path, projects, containers, unmanaged)?;
+        } else {
+            unmanaged.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Deletes the directories `find_unmanaged` reported.
+pub fn prune(
+    config: &Config,
+    dirs: &[PathBuf],
+    reporter: &dyn Reporter,
+) -> Result<PruneReport, WorkspacesError> {
+    let _lock = Lock::acquire(&config.lock_dir(), reporter)?;
+    let mut report = PruneReport {
+        removed: vec![],
+        failures: vec![],
+    };
+    for dir in dirs {
+        match fs::remove_dir_all(dir) {
+            Ok(()) => report.removed.push(dir.clone()),
+            Err(e) => report.failures.push((dir.clone(), e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod should {
+    use std::{fs, path::PathBuf};
+
+    use rstest::*;
+
+    use super::find_unmanaged;
+    use crate::Config;
+
+    #[rstest]
+    fn find_undeclared_directories() {
+        let root = std::env::temp_dir().join(format!("workspaces-prune-{}", std::process::id()));
+        for dir in [
+            "w/p/src",
+            "w/extra",
+            "w/inner/junk",
+            "w/linked/dir",
+            "stray",
+            ".hidden",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let config = Config::from_str(&format!(
+            r#"---
+root: {}
+workspaces:
+  w:
+    links:
+      linked/dir/file: ../target
+    projects:
+      p:
+    workspaces:
+      inner:
+"#,
+            root.to_string_lossy()
+        ))
+        .unwrap();
+
+        let unmanaged = find_unmanaged(&config).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            unmanaged.into_iter().map(|d| d.path).collect::<Vec<_>>(),
+            ["stray", "w/extra", "w/inner/junk"]
+                .iter()
+                .map(|d| root.join(d))
+                .collect::<Vec<PathBuf>>()
+        );
+    }
+}
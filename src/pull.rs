@@ -0,0 +1,202 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+    time::Instant,
+};
+
+use serde::Serialize;
+
+use crate::{
+    git::{Git, Pulled},
+    hooks::{run_global_hook, GlobalHook},
+    lock::Lock,
+    state::{self, Operation, Outcome},
+    Config, Render, Reporter, WorkspacesError,
+};
+
+pub struct PullOptions {
+    /// Rebase local commits onto origin instead of skipping diverged branches
+    pub rebase: bool,
+    /// Stash uncommitted changes around the update instead of skipping
+    pub autostash: bool,
+    /// Projects to pull at once
+    pub jobs: usize,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PullStatus {
+    UpToDate,
+    Updated,
+    /// Updated, but the stashed changes conflicted, so they are applied with
+    /// conflict markers and also left in the stash
+    Stashed,
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Serialize)]
+pub struct PullEntry {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: PullStatus,
+}
+
+#[derive(Serialize)]
+pub struct PullReport {
+    pub entries: Vec<PullEntry>,
+}
+
+impl PullReport {
+    pub fn failed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, PullStatus::Failed { .. }))
+            .count()
+    }
+}
+
+impl Render for PullReport {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        let (mut updated, mut up_to_date, mut skipped) = (0, 0, 0);
+
+        for e in self.entries.iter() {
+            let path = e.path.to_string_lossy();
+            match e.status {
+                PullStatus::UpToDate => up_to_date += 1,
+                PullStatus::Updated => {
+                    updated += 1;
+                    writeln!(out, "updated\t{path}")?;
+                }
+                PullStatus::Stashed => {
+                    updated += 1;
+                    writeln!(out, "updated\t{path}")?;
+                    writeln!(
+                        out,
+                        "\t\tlocal changes conflict, resolve them, a copy is kept in the stash"
+                    )?;
+                }
+                PullStatus::Skipped { ref reason } => {
+                    skipped += 1;
+                    writeln!(out, "skipped\t{path}")?;
+                    writeln!(out, "\t\t{reason}")?;
+                }
+                PullStatus::Failed { ref reason } => {
+                    writeln!(out, "FAILED\t{path}")?;
+                    writeln!(out, "\t\t{reason}")?;
+                }
+            }
+        }
+        if !self.entries.is_empty() {
+            writeln!(out)?;
+        }
+
+        writeln!(
+            out,
+            "Pulled {} checkouts: {updated} updated, {up_to_date} up to date, {skipped} skipped, {} failed",
+            self.entries.len(),
+            self.failed()
+        )
+    }
+}
+
+/// Fetches every cloned project and brings its checkouts up to date with
+/// origin, `opts.jobs` projects at a time. Checkouts that cannot be updated
+/// without risking local work are skipped.
+pub fn pull(
+    config: &Config,
+    opts: &PullOptions,
+    reporter: &dyn Reporter,
+) -> Result<PullReport, WorkspacesError> {
+    let _lock = Lock::acquire(&config.lock_dir(), reporter)?;
+
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(path, _)| path.exists())
+        .filter_map(|(path, p)| p.git.clone().map(|g| (path, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let root = config.root.clone();
+    let total = projects.len();
+    if total > 0 {
+        run_global_hook(config, GlobalHook::PreSync, &root, total, None, reporter)?;
+    }
+
+    let pending = Mutex::new(projects.into_iter().collect::<VecDeque<_>>());
+    let entries = Mutex::new(vec![]);
+    let outcomes = Mutex::new(vec![]);
+    thread::scope(|s| {
+        for _ in 0..opts.jobs.max(1) {
+            s.spawn(|| loop {
+                let next = pending.lock().unwrap().pop_front();
+                let Some((path, proj_git)) = next else {
+                    break;
+                };
+                let git = Git::new(path.clone(), proj_git);
+                let started = Instant::now();
+                let checkouts = match git.inspect_layout() {
+                    Some(issue) => Err(issue.describe()),
+                    None => git
+                        .pull(opts.rebase, opts.autostash, reporter)
+                        .map_err(|e| format!("{e:#}")),
+                };
+                let error = checkouts.as_ref().err().cloned();
+                let pulled = match checkouts {
+                    Ok(checkouts) => checkouts
+                        .into_iter()
+                        .map(|(path, result)| PullEntry {
+                            path,
+                            status: match result {
+                                Ok(Pulled::UpToDate) => PullStatus::UpToDate,
+                                Ok(Pulled::Updated { stash_kept: false }) => PullStatus::Updated,
+                                Ok(Pulled::Updated { stash_kept: true }) => PullStatus::Stashed,
+                                Ok(Pulled::Skipped(reason)) => PullStatus::Skipped { reason },
+                                Err(e) => PullStatus::Failed {
+                                    reason: format!("{e:#}"),
+                                },
+                            },
+                        })
+                        .collect(),
+                    Err(e) => vec![PullEntry {
+                        path: path.clone(),
+                        status: PullStatus::Failed { reason: e },
+                    }],
+                };
+                outcomes.lock().unwrap().push(Outcome {
+                    path,
+                    duration: Some(started.elapsed()),
+                    error,
+                });
+                entries.lock().unwrap().extend(pulled);
+            });
+        }
+    });
+    let outcomes = outcomes.into_inner().unwrap();
+    let failed = outcomes.iter().filter(|o| o.error.is_some()).count();
+    state::record(config, Operation::Sync, outcomes);
+
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    if total > 0 {
+        let done = Some((total - failed, failed));
+        if let Err(e) = run_global_hook(config, GlobalHook::PostSync, &root, total, done, reporter)
+        {
+            entries.push(PullEntry {
+                path: root,
+                status: PullStatus::Failed {
+                    reason: format!("{e:#}"),
+                },
+            });
+        }
+    }
+    Ok(PullReport { entries })
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+};
+
+use serde::Serialize;
+
+use crate::{
+    git::{Git, Pushed},
+    Config, Render, Reporter, WorkspacesError,
+};
+
+pub struct PushOptions {
+    /// Report what would be pushed without pushing
+    pub dry_run: bool,
+    /// Projects to push at once
+    pub jobs: usize,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PushStatus {
+    UpToDate,
+    /// Commits pushed, or that would be on a dry run
+    Pushed {
+        commits: usize,
+    },
+    Skipped {
+        reason: String,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Serialize)]
+pub struct PushEntry {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: PushStatus,
+}
+
+#[derive(Serialize)]
+pub struct PushReport {
+    pub dry_run: bool,
+    pub entries: Vec<PushEntry>,
+}
+
+impl PushReport {
+    pub fn failed(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.status, PushStatus::Failed { .. }))
+            .count()
+    }
+}
+
+impl Render for PushReport {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        let (mut pushed, mut up_to_date, mut skipped) = (0, 0, 0);
+        let verb = if self.dry_run { "would push" } else { "pushed" };
+
+        for e in self.entries.iter() {
+            let path = e.path.to_string_lossy();
+            match e.status {
+                PushStatus::UpToDate => up_to_date += 1,
+                PushStatus::Pushed { commits } => {
+                    pushed += 1;
+                    writeln!(out, "{verb}\t{path}\t{commits} commits")?;
+                }
+                PushStatus::Skipped { ref reason } => {
+                    skipped += 1;
+                    writeln!(out, "skipped\t{path}")?;
+                    writeln!(out, "\t\t{reason}")?;
+                }
+                PushStatus::Failed { ref reason } => {
+                    writeln!(out, "FAILED\t{path}")?;
+                    writeln!(out, "\t\t{reason}")?;
+                }
+            }
+        }
+        if !self.entries.is_empty() {
+            writeln!(out)?;
+        }
+
+        let verb = if self.dry_run { "Would push" } else { "Pushed" };
+        writeln!(
+            out,
+            "{verb} {pushed} of {} checkouts: {up_to_date} up to date, {skipped} skipped, {} failed",
+            self.entries.len(),
+            self.failed()
+        )
+    }
+}
+
+/// Fetches every cloned project and pushes the branch of every checkout that
+/// has commits its upstream on origin lacks, `opts.jobs` projects at a time. Never forces a push, branches
+/// that are behind origin are skipped.
+pub fn push(
+    config: &Config,
+    opts: &PushOptions,
+    reporter: &dyn Reporter,
+) -> Result<PushReport, WorkspacesError> {
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(path, _)| path.exists())
+        .filter_map(|(path, p)| p.git.clone().map(|g| (path, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let pending = Mutex::new(projects.into_iter().collect::<VecDeque<_>>());
+    let entries = Mutex::new(vec![]);
+    thread::scope(|s| {
+        for _ in 0..opts.jobs.max(1) {
+            s.spawn(|| loop {
+                let next = pending.lock().unwrap().pop_front();
+                let Some((path, proj_git)) = next else {
+                    break;
+                };
+                let git = Git::new(path.clone(), proj_git);
+                let checkouts = match git.inspect_layout() {
+                    Some(issue) => Err(issue.describe()),
+                    None => git
+                        .push(opts.dry_run, reporter)
+                        .map_err(|e| format!("{e:#}")),
+                };
+                let pushed = match checkouts {
+                    Ok(checkouts) => checkouts
+                        .into_iter()
+                        .map(|(path, result)| PushEntry {
+                            path,
+                            status: match result {
+                                Ok(Pushed::UpToDate) => PushStatus::UpToDate,
+                                Ok(Pushed::Commits(commits)) => PushStatus::Pushed { commits },
+                                Ok(Pushed::Skipped(reason)) => PushStatus::Skipped { reason },
+                                Err(e) => PushStatus::Failed {
+                                    reason: format!("{e:#}"),
+                                },
+                            },
+                        })
+                        .collect(),
+                    Err(e) => vec![PushEntry {
+                        path,
+                        status: PushStatus::Failed { reason: e },
+                    }],
+                };
+                entries.lock().unwrap().extend(pushed);
+            });
+        }
+    });
+
+    let mut entries = entries.into_inner().unwrap();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(PushReport {
+        dry_run: opts.dry_run,
+        entries,
+    })
+}
@@ -0,0 +1,65 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+use crate::{git::Git, Config, Reporter, WorkspacesError};
+
+pub struct RepairReport {
+    pub path: PathBuf,
+    pub backup: PathBuf,
+    pub restored_refs: Vec<String>,
+    pub lost_refs: Vec<String>,
+    /// Worktrees whose checkout and gitdir no longer point at each other
+    pub broken_worktrees: Vec<String>,
+}
+
+impl RepairReport {
+    pub fn print(&self) {
+        println!("Repaired {}\n", self.path.to_string_lossy());
+
+        println!("The following local refs were restored:\n");
+        for r in self.restored_refs.iter() {
+            println!("\t{r}");
+        }
+        println!();
+
+        if !self.lost_refs.is_empty() {
+            println!("The following local refs could not be recovered:\n");
+            for r in self.lost_refs.iter() {
+                println!("\t{r}");
+            }
+            println!();
+        }
+
+        if !self.broken_worktrees.is_empty() {
+            println!("The following worktrees no longer resolve:\n");
+            for name in self.broken_worktrees.iter() {
+                println!("\t{name}");
+            }
+            println!("\n`git worktree repair` may link them up again.\n");
+        }
+
+        println!(
+            "The corrupt repository was kept at {}",
+            self.backup.to_string_lossy()
+        );
+    }
+}
+
+pub fn repair(
+    config: &Config,
+    proj_path: PathBuf,
+    reporter: &dyn Reporter,
+) -> Result<RepairReport, WorkspacesError> {
+    let proj_path = config.resolve_path(&proj_path);
+
+    let project = config.lookup_project(&proj_path)?;
+    let Some(ref proj_git) = project.git else {
+        return Err(anyhow!("Project has no git settings to repair from").into());
+    };
+    if !proj_path.exists() {
+        return Err(anyhow!("Project is missing, restore it instead").into());
+    }
+
+    Ok(Git::new(proj_path, proj_git.clone()).repair(reporter)?)
+}
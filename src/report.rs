@@ -0,0 +1,326 @@
+use std::{
+    io::{self, IsTerminal},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crossterm::style::Color;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::git::{
+    progress::{MetricsCounter, Progress},
+    shell::{paint, Stream},
+};
+
+/// Something the library did that a front end may want to show. Events are
+/// reported as they happen, from the thread doing the work or, for a restore,
+/// from the task awaiting it.
+#[derive(Debug, Clone)]
+pub enum Event {
+    WorkspaceCreated {
+        path: PathBuf,
+    },
+    /// `total` missing projects are about to be restored on `jobs` threads
+    RestoreStarted {
+        total: usize,
+        jobs: usize,
+    },
+    /// `index` counts from 1 up to `total`
+    ProjectRestoreStarted {
+        path: PathBuf,
+        index: usize,
+        total: usize,
+    },
+    ProjectRestored {
+        path: PathBuf,
+        index: usize,
+        total: usize,
+        elapsed: Duration,
+    },
+    ProjectRestoreFailed {
+        path: PathBuf,
+        index: usize,
+        total: usize,
+        error: String,
+    },
+    CloneStarted {
+        path: PathBuf,
+        url: String,
+    },
+    CloneProgress {
+        path: PathBuf,
+        progress: TransferProgress,
+    },
+    CloneFinished {
+        path: PathBuf,
+    },
+    /// Clone `attempt` of `1 + retries` failed, the next starts after `delay`
+    CloneRetrying {
+        path: PathBuf,
+        attempt: u32,
+        retries: u32,
+        delay: Duration,
+        error: String,
+    },
+    /// Credentials offered to the host while cloning, e.g. `ssh agent as git`
+    CredentialsOffered {
+        path: PathBuf,
+        credentials: String,
+    },
+    /// A hook `command` is run in `path`
+    HookStarted {
+        path: PathBuf,
+        command: String,
+    },
+    /// `output` holds what the hook printed to stdout, then to stderr
+    HookFinished {
+        path: PathBuf,
+        command: String,
+        output: String,
+    },
+    LinkCreated {
+        path: PathBuf,
+        target: PathBuf,
+    },
+    /// A generated `.envrc` was written and allowed
+    EnvrcWritten {
+        path: PathBuf,
+    },
+    /// A devcontainer.json was written from a template
+    DevcontainerWritten {
+        path: PathBuf,
+    },
+    /// `manager` installs the toolchains pinned in `path`
+    ToolchainsInstalling {
+        path: PathBuf,
+        manager: String,
+    },
+    /// Another process holds the lock at `path`, it is waited for
+    LockWaiting {
+        path: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TransferProgress {
+    pub received_objects: usize,
+    pub indexed_objects: usize,
+    pub total_objects: usize,
+    pub indexed_deltas: usize,
+    pub total_deltas: usize,
+    pub received_bytes: usize,
+}
+
+/// Receives the events of long running operations like `restore`.
+pub trait Reporter: Sync {
+    fn report(&self, event: &Event);
+}
+
+/// Drops every event.
+pub struct SilentReporter;
+
+impl Reporter for SilentReporter {
+    fn report(&self, _event: &Event) {}
+}
+
+/// Sends the events of work running on tokio's blocking pool to the task that
+/// passes them on to the caller's reporter.
+pub(crate) struct ChannelReporter(pub(crate) UnboundedSender<Event>);
+
+impl Reporter for ChannelReporter {
+    fn report(&self, event: &Event) {
+        // The receiver is only dropped once the restore is over
+        let _ = self.0.send(event.clone());
+    }
+}
+
+/// How much a [`ConsoleReporter`] tells about what is being done.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    /// Also shows the URLs cloned from and the credentials offered
+    Verbose,
+}
+
+/// Reports on stderr: a progress bar while a single project is cloned, and a
+/// line per project when several are restored at once since their progress
+/// would share the terminal.
+#[derive(Default)]
+pub struct ConsoleReporter {
+    verbosity: Verbosity,
+    parallel: AtomicBool,
+    transfer: Mutex<Option<Transfer>>,
+}
+
+struct Transfer {
+    bar: Progress,
+    counter: MetricsCounter<10>,
+    last_update: Instant,
+}
+
+impl ConsoleReporter {
+    pub fn new(verbosity: Verbosity) -> Self {
+        Self {
+            verbosity,
+            ..Default::default()
+        }
+    }
+}
+
+impl Reporter for ConsoleReporter {
+    fn report(&self, event: &Event) {
+        if self.verbosity == Verbosity::Quiet {
+            return;
+        }
+        let parallel = self.parallel.load(Ordering::Relaxed);
+        let verbose = self.verbosity == Verbosity::Verbose;
+        match event {
+            Event::RestoreStarted { jobs, .. } => self.parallel.store(*jobs > 1, Ordering::Relaxed),
+            Event::ProjectRestoreStarted { path, index, total } if parallel => {
+                eprintln!("[{index}/{total}] Restoring {}", path.to_string_lossy())
+            }
+            Event::ProjectRestored {
+                path,
+                index,
+                total,
+                elapsed,
+            } if parallel => eprintln!(
+                "[{index}/{total}] {} Restored {} in {:.1}s",
+                paint(Stream::Stderr, "✓", Color::Green),
+                path.to_string_lossy(),
+                elapsed.as_secs_f32()
+            ),
+            Event::ProjectRestoreFailed {
+                path, index, total, ..
+            } if parallel => {
+                eprintln!(
+                    "[{index}/{total}] {} Failed to restore {}",
+                    paint(Stream::Stderr, "✗", Color::Red),
+                    path.to_string_lossy()
+                )
+            }
+            Event::CloneStarted { url, .. } if !parallel => {
+                if io::stderr().is_terminal() {
+                    eprintln!("Cloning {url}...\r");
+                } else {
+                    eprintln!("Cloning {url}...");
+                }
+                let now = Instant::now();
+                *self.transfer.lock().unwrap() = Some(Transfer {
+                    bar: Progress::new("Fetch"),
+                    counter: MetricsCounter::new(0, now),
+                    last_update: now,
+                });
+            }
+            Event::CloneProgress { progress, .. } => {
+                if let Some(transfer) = self.transfer.lock().unwrap().as_mut() {
+                    transfer.tick(progress);
+                }
+            }
+            Event::CloneFinished { .. } => *self.transfer.lock().unwrap() = None,
+            Event::CloneRetrying {
+                path,
+                attempt,
+                retries,
+                delay,
+                error,
+            } => eprintln!(
+                "Clone {attempt}/{} of {} failed, retrying in {}s: {}",
+                retries + 1,
+                path.to_string_lossy(),
+                delay.as_secs(),
+                error.replace('\n', " ")
+            ),
+            Event::WorkspaceCreated { path } if verbose => {
+                eprintln!("Created workspace {}", path.to_string_lossy())
+            }
+            Event::ProjectRestored { path, elapsed, .. } if verbose => eprintln!(
+                "{} Restored {} in {:.1}s",
+                paint(Stream::Stderr, "✓", Color::Green),
+                path.to_string_lossy(),
+                elapsed.as_secs_f32()
+            ),
+            Event::CloneStarted { path, url } if verbose => {
+                eprintln!("Cloning {url} into {}", path.to_string_lossy())
+            }
+            Event::CredentialsOffered { path, credentials } if verbose => {
+                eprintln!("Offering {credentials} for {}", path.to_string_lossy())
+            }
+            Event::HookStarted { path, command } if verbose => {
+                eprintln!("Running `{command}` in {}", path.to_string_lossy())
+            }
+            Event::HookFinished { output, .. } if verbose && !output.is_empty() => {
+                eprint!("{output}")
+            }
+            Event::LinkCreated { path, target } if verbose => eprintln!(
+                "Linked {} to {}",
+                path.to_string_lossy(),
+                target.to_string_lossy()
+            ),
+            Event::EnvrcWritten { path } if verbose => {
+                eprintln!("Wrote {}", path.to_string_lossy())
+            }
+            Event::DevcontainerWritten { path } if verbose => {
+                eprintln!("Wrote {}", path.to_string_lossy())
+            }
+            Event::ToolchainsInstalling { path, manager } => eprintln!(
+                "Installing toolchains in {} with {manager}...",
+                path.to_string_lossy()
+            ),
+            Event::LockWaiting { path } => eprintln!(
+                "Waiting for another workspaces process to finish ({})",
+                path.to_string_lossy()
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl Transfer {
+    fn tick(&mut self, stats: &TransferProgress) {
+        let msg = if stats.indexed_deltas > 0 {
+            format!(
+                ", ({}/{}) resolvings deltas",
+                stats.indexed_deltas, stats.total_deltas,
+            )
+        } else {
+            // Receiving objects.
+            //
+            // # Caveat
+            //
+            // Progress bar relies on git2 calling `transfer_progress`
+            // to update its transfer rate, but we cannot guarantee a
+            // periodic call of that callback. Thus if we don't receive
+            // any data for, say, 10 seconds, the rate will get stuck
+            // and never go down to 0B/s.
+            // In the future, we need to find away to update the rate
+            // even when the callback is not called.
+            let now = Instant::now();
+            // Scrape a `received_bytes` to the counter every 300ms.
+            if now - self.last_update > Duration::from_millis(300) {
+                self.counter.add(stats.received_bytes, now);
+                self.last_update = now;
+            }
+            let (rate, unit) = human_readable_bytes(self.counter.rate() as u64);
+            format!(", {:.2}{}/s", rate, unit)
+        };
+        let _ = self
+            .bar
+            .tick(stats.indexed_objects, stats.total_objects, &msg);
+    }
+}
+
+/// Formats a number of bytes into a human readable SI-prefixed size.
+/// Returns a tuple of `(quantity, units)`.
+pub(crate) fn human_readable_bytes(bytes: u64) -> (f32, &'static str) {
+    static UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let bytes = bytes as f32;
+    let i = ((bytes.log2() / 10.0) as usize).min(UNITS.len() - 1);
+    (bytes / 1024_f32.powi(i as i32), UNITS[i])
+}
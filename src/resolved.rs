@@ -0,0 +1,205 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use serde::Serialize;
+
+use crate::{git::Git, Config, Render};
+
+/// The git settings every project ends up with once the workspace and global
+/// settings are applied and the defaults filled in.
+#[derive(Serialize)]
+pub struct ResolvedConfig {
+    #[serde(skip)]
+    root: PathBuf,
+    pub projects: Vec<ResolvedProject>,
+}
+
+#[derive(Serialize)]
+pub struct ResolvedProject {
+    pub path: PathBuf,
+    /// `None` for projects without git settings, which are never cloned
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub git: Option<ResolvedGit>,
+}
+
+#[derive(Serialize)]
+pub struct ResolvedGit {
+    pub repo: String,
+    /// What the project is cloned from
+    pub url: String,
+    pub host: String,
+    /// `~/.ssh/config` alias the host was given as
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssh_alias: Option<String>,
+    pub protocol: String,
+    pub strategy: String,
+    pub backend: String,
+}
+
+pub fn resolved_config(config: &Config) -> ResolvedConfig {
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .map(|(path, p)| {
+            let git = p.git.clone().map(|proj_git| {
+                let repo = proj_git.repo.clone();
+                let git = Git::new(path.clone(), proj_git);
+                ResolvedGit {
+                    repo,
+                    url: git.url(),
+                    host: git.host(),
+                    ssh_alias: git.ssh_alias().map(String::from),
+                    protocol: git.protocol().to_string(),
+                    strategy: git.strategy().to_string(),
+                    backend: git.backend().to_string(),
+                }
+            });
+            ResolvedProject { path, git }
+        })
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.path.cmp(&b.path));
+
+    ResolvedConfig {
+        root: config.root.clone(),
+        projects,
+    }
+}
+
+impl Render for ResolvedConfig {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        let dash = || "-".to_string();
+        let rows = self
+            .projects
+            .iter()
+            .map(|p| {
+                let path = p.path.strip_prefix(&self.root).unwrap_or(&p.path);
+                let path = path.to_string_lossy().to_string();
+                match &p.git {
+                    Some(git) => [
+                        path,
+                        match &git.ssh_alias {
+                            Some(alias) => format!("{} ({alias})", git.host),
+                            None => git.host.clone(),
+                        },
+                        git.protocol.clone(),
+                        git.strategy.clone(),
+                        git.backend.clone(),
+                        git.url.clone(),
+                    ],
+                    None => [path, dash(), dash(), dash(), dash(), dash()],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let header =
+            ["PROJECT", "HOST", "PROTOCOL", "STRATEGY", "BACKEND", "URL"].map(String::from);
+        let mut widths = header.clone().map(|h| h.chars().count());
+        for row in rows.iter() {
+            for (w, cell) in widths.iter_mut().zip(row.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, w)| format!("{cell:w$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+
+    use super::resolved_config;
+    use crate::Config;
+
+    #[rstest]
+    fn apply_overlays_and_defaults() {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+git:
+  protocol: ssh
+workspaces:
+  work:
+    git:
+      host: gitlab
+      clone_strategy: worktree
+    projects:
+      api:
+        git:
+          repo: team/api
+      web:
+        git:
+          repo: team/web
+          protocol: https
+  oss:
+    projects:
+      dotfiles:
+        git:
+          repo: czifro/dotfiles
+      notes:
+"#,
+        )
+        .unwrap();
+
+        let resolved = resolved_config(&config);
+        let settings = resolved
+            .projects
+            .iter()
+            .map(|p| {
+                let git = p.git.as_ref().map(|g| {
+                    (
+                        g.host.as_str(),
+                        g.protocol.as_str(),
+                        g.strategy.as_str(),
+                        g.url.as_str(),
+                    )
+                });
+                (p.path.to_string_lossy().to_string(), git)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            settings,
+            vec![
+                (
+                    "/some/root/oss/dotfiles".to_string(),
+                    Some((
+                        "github.com",
+                        "ssh",
+                        "branch",
+                        "git@github.com:czifro/dotfiles.git"
+                    ))
+                ),
+                ("/some/root/oss/notes".to_string(), None),
+                (
+                    "/some/root/work/api".to_string(),
+                    Some((
+                        "gitlab.com",
+                        "ssh",
+                        "worktree",
+                        "git@gitlab.com:team/api.git"
+                    ))
+                ),
+                (
+                    "/some/root/work/web".to_string(),
+                    Some((
+                        "gitlab.com",
+                        "https",
+                        "worktree",
+                        "https://gitlab.com/team/web.git"
+                    ))
+                ),
+            ]
+        );
+    }
+}
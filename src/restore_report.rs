@@ -0,0 +1,249 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::anyhow;
+
+use crate::{human_readable_bytes, Event, Reporter, WorkspacesError};
+
+/// What a restore step that failed was doing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestoreStep {
+    /// Cloning the project or running its `post_clone` hooks
+    Project,
+    Link,
+    Envrc,
+    Devcontainer,
+    Toolchains,
+    Bootstrap,
+    PostRestoreHook,
+}
+
+impl RestoreStep {
+    fn label(&self) -> &'static str {
+        match self {
+            RestoreStep::Project => "restore",
+            RestoreStep::Link => "link",
+            RestoreStep::Envrc => ".envrc",
+            RestoreStep::Devcontainer => "devcontainer",
+            RestoreStep::Toolchains => "toolchains",
+            RestoreStep::Bootstrap => "bootstrap",
+            RestoreStep::PostRestoreHook => "post_restore",
+        }
+    }
+
+    /// How `into_result` introduces the failures of the step
+    fn failed(&self) -> &'static str {
+        match self {
+            RestoreStep::Project => "projects failed to restore",
+            RestoreStep::Link => "links could not be created",
+            RestoreStep::Envrc => ".envrc files could not be written",
+            RestoreStep::Devcontainer => "devcontainer.json files could not be written",
+            RestoreStep::Toolchains => "projects failed to install their toolchains",
+            RestoreStep::Bootstrap => "workspaces failed to bootstrap",
+            RestoreStep::PostRestoreHook => "post_restore hooks failed",
+        }
+    }
+}
+
+pub struct RestoreFailure {
+    pub path: PathBuf,
+    pub step: RestoreStep,
+    pub error: anyhow::Error,
+}
+
+impl RestoreFailure {
+    pub fn reason(&self) -> String {
+        format!("{:#}", self.error)
+    }
+}
+
+/// How a restore went, once it is over.
+pub struct RestoreReport {
+    /// Projects cloned, or created as an empty directory
+    pub restored: Vec<PathBuf>,
+    /// Workspace directories that did not exist yet
+    pub created: Vec<PathBuf>,
+    /// Bytes the clones received, only counted by the libgit2 backend
+    pub received_bytes: usize,
+    pub elapsed: Duration,
+    /// Failed projects in path order, then the other failed steps
+    pub failures: Vec<RestoreFailure>,
+}
+
+impl RestoreReport {
+    /// Projects that were to be restored
+    fn total(&self) -> usize {
+        self.restored.len() + self.failures_of(RestoreStep::Project).count()
+    }
+
+    fn failures_of(&self, step: RestoreStep) -> impl Iterator<Item = &RestoreFailure> {
+        self.failures.iter().filter(move |f| f.step == step)
+    }
+
+    /// The failures as a single error, the lone failure's own error when the
+    /// only project to restore failed and nothing else did.
+    pub fn into_result(mut self) -> Result<(), WorkspacesError> {
+        let total = self.total();
+        if total == 1 && self.failures.len() == 1 && self.failures[0].step == RestoreStep::Project {
+            return Err(self.failures.remove(0).error.into());
+        }
+
+        let details = |failures: Vec<&RestoreFailure>| {
+            failures
+                .iter()
+                .map(|f| format!("\t{}: {}", f.path.to_string_lossy(), f.reason()))
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+        let mut errors = vec![];
+        let steps = [
+            RestoreStep::Project,
+            RestoreStep::Link,
+            RestoreStep::Envrc,
+            RestoreStep::Devcontainer,
+            RestoreStep::Toolchains,
+            RestoreStep::Bootstrap,
+            RestoreStep::PostRestoreHook,
+        ];
+        for step in steps {
+            let failures = self.failures_of(step).collect::<Vec<_>>();
+            if failures.is_empty() {
+                continue;
+            }
+            let count = match step {
+                RestoreStep::Project => format!("{} of {total}", failures.len()),
+                _ => failures.len().to_string(),
+            };
+            errors.push(format!("{count} {}:\n{}", step.failed(), details(failures)));
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        Err(anyhow!("{}", errors.join("\n")).into())
+    }
+
+    pub fn print(&self) {
+        let received = match self.received_bytes {
+            0 => "-".to_string(),
+            bytes => {
+                let (size, unit) = human_readable_bytes(bytes as u64);
+                format!("{size:.2}{unit}")
+            }
+        };
+        let rows = [
+            ("Restored", format!("{} projects", self.restored.len())),
+            ("Created", format!("{} workspaces", self.created.len())),
+            ("Received", received),
+            ("Elapsed", format!("{:.1}s", self.elapsed.as_secs_f32())),
+            ("Failed", self.failures.len().to_string()),
+        ];
+        for (name, value) in rows {
+            println!("{name:<10}{value}");
+        }
+        for f in self.failures.iter() {
+            println!(
+                "  {}\t{}: {}",
+                f.path.to_string_lossy(),
+                f.step.label(),
+                f.reason()
+            );
+        }
+    }
+}
+
+/// Passes every event on while keeping what the report needs from them.
+pub(crate) struct RestoreTally<'a> {
+    reporter: &'a dyn Reporter,
+    created: Mutex<Vec<PathBuf>>,
+    /// Bytes received by the latest clone attempt of each project
+    received: Mutex<HashMap<PathBuf, usize>>,
+}
+
+impl<'a> RestoreTally<'a> {
+    pub(crate) fn new(reporter: &'a dyn Reporter) -> Self {
+        RestoreTally {
+            reporter,
+            created: Mutex::new(vec![]),
+            received: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Workspaces created and bytes received so far
+    pub(crate) fn totals(&self) -> (Vec<PathBuf>, usize) {
+        let created = self.created.lock().unwrap().clone();
+        let received = self.received.lock().unwrap().values().sum();
+        (created, received)
+    }
+
+    fn record(&self, path: &Path, bytes: usize) {
+        self.received
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), bytes);
+    }
+}
+
+impl Reporter for RestoreTally<'_> {
+    fn report(&self, event: &Event) {
+        match event {
+            Event::WorkspaceCreated { path } => self.created.lock().unwrap().push(path.clone()),
+            Event::CloneProgress { path, progress } => self.record(path, progress.received_bytes),
+            _ => {}
+        }
+        self.reporter.report(event);
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::{path::PathBuf, time::Duration};
+
+    use anyhow::anyhow;
+    use rstest::*;
+
+    use super::{RestoreFailure, RestoreReport, RestoreStep};
+
+    fn report(restored: &[&str], failures: &[(&str, RestoreStep)]) -> RestoreReport {
+        RestoreReport {
+            restored: restored.iter().map(PathBuf::from).collect(),
+            created: vec![],
+            received_bytes: 0,
+            elapsed: Duration::ZERO,
+            failures: failures
+                .iter()
+                .map(|(path, step)| RestoreFailure {
+                    path: PathBuf::from(path),
+                    step: *step,
+                    error: anyhow!("broken"),
+                })
+                .collect(),
+        }
+    }
+
+    #[rstest]
+    #[case(&[], &[], None)]
+    #[case(&[], &[("/r/p", RestoreStep::Project)], Some("broken"))]
+    #[case(
+        &["/r/ok"],
+        &[("/r/p", RestoreStep::Project)],
+        Some("1 of 2 projects failed to restore:\n\t/r/p: broken")
+    )]
+    #[case(
+        &[],
+        &[("/r/p", RestoreStep::Project), ("/r", RestoreStep::Bootstrap)],
+        Some("1 of 1 projects failed to restore:\n\t/r/p: broken\n1 workspaces failed to bootstrap:\n\t/r: broken")
+    )]
+    fn sum_up_failures_as_one_error(
+        #[case] restored: &[&str],
+        #[case] failures: &[(&str, RestoreStep)],
+        #[case] expected: Option<&str>,
+    ) {
+        let result = report(restored, failures).into_result();
+
+        assert_eq!(result.err().map(|e| e.to_string()).as_deref(), expected);
+    }
+}
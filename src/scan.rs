@@ -0,0 +1,67 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+pub(crate) struct FoundRepo {
+    pub(crate) path: PathBuf,
+    pub(crate) origin: Option<String>,
+    /// Cloned with the worktree strategy, a bare repo in `.bare`
+    pub(crate) worktree: bool,
+}
+
+/// Finds git clones up to `depth` levels below `dir`. Clones are not searched
+/// for nested clones and hidden directories are skipped.
+pub(crate) fn scan(dir: &Path, depth: usize) -> Result<Vec<FoundRepo>> {
+    let mut found = vec![];
+    scan_into(dir, depth, &mut found)
+        .with_context(|| format!("Tried scanning {}", dir.to_string_lossy()))?;
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(found)
+}
+
+fn scan_into(dir: &Path, depth: usize, found: &mut Vec<FoundRepo>) -> Result<()> {
+    if depth == 0 {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // Symlinks are not followed, they could lead out of the tree or
+        // back into it
+        if !entry.file_type()?.is_dir() || entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+
+        let bare = path.join(".bare");
+        let repo = if bare.is_dir() {
+            Some((git2::Repository::open_bare(&bare), true))
+        } else if path.join(".git").exists() {
+            Some((git2::Repository::open(&path), false))
+        } else {
+            None
+        };
+
+        match repo {
+            Some((repo, worktree)) => {
+                // Read as configured, `Remote::url` applies `insteadOf` rewrites
+                let origin = repo
+                    .and_then(|r| r.config())
+                    .and_then(|c| c.get_string("remote.origin.url"))
+                    .ok();
+                found.push(FoundRepo {
+                    path,
+                    origin,
+                    worktree,
+                });
+            }
+            None => scan_into(&path, depth - 1, found)?,
+        }
+    }
+
+    Ok(())
+}
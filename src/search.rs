@@ -0,0 +1,157 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crossterm::style::Color;
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use serde::Serialize;
+
+use crate::{
+    git::shell::{paint, Stream},
+    Config, NodeKind, Render,
+};
+
+#[derive(Serialize)]
+pub struct SearchResults {
+    #[serde(skip)]
+    root: PathBuf,
+    /// Best match first
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Serialize)]
+pub struct SearchMatch {
+    pub path: PathBuf,
+    pub kind: NodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    pub exists: bool,
+}
+
+/// Workspaces and projects whose path relative to the root, or alias,
+/// fuzzily matches `pattern`, best match first.
+pub fn search(config: &Config, pattern: &str) -> SearchResults {
+    let root = config.root.clone();
+    let mut candidates = config
+        .collect_workspace_paths()
+        .into_iter()
+        .map(|p| (p, NodeKind::Workspace, None))
+        .chain(
+            config
+                .collect_projects()
+                .into_iter()
+                .map(|(p, project)| (p, NodeKind::Project, project.alias.clone())),
+        )
+        .collect::<Vec<_>>();
+    candidates.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let matcher = SkimMatcherV2::default();
+    let mut ranked = candidates
+        .into_iter()
+        .filter_map(|(path, kind, alias)| {
+            let rel = config.relative_path(&path);
+            let score = [Some(rel.to_string_lossy().to_string()), alias.clone()]
+                .iter()
+                .flatten()
+                .filter_map(|text| matcher.fuzzy_match(text, pattern))
+                .max()?;
+            Some((score, path, kind, alias))
+        })
+        .collect::<Vec<_>>();
+    // Stable, so equal scores stay in path order
+    ranked.sort_by_key(|(score, ..)| std::cmp::Reverse(*score));
+
+    SearchResults {
+        matches: ranked
+            .into_iter()
+            .map(|(_, path, kind, alias)| SearchMatch {
+                exists: path.exists(),
+                path,
+                kind,
+                alias,
+            })
+            .collect(),
+        root,
+    }
+}
+
+impl Render for SearchResults {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        let names = self
+            .matches
+            .iter()
+            .map(|m| {
+                let rel = m.path.strip_prefix(&self.root).unwrap_or(&m.path);
+                match (m.kind, &m.alias) {
+                    (NodeKind::Workspace, _) => format!("{}/", rel.to_string_lossy()),
+                    (NodeKind::Project, Some(alias)) => {
+                        format!("{} ({alias})", rel.to_string_lossy())
+                    }
+                    (NodeKind::Project, None) => rel.to_string_lossy().to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+        for (name, m) in names.iter().zip(self.matches.iter()) {
+            let marker = if m.exists {
+                paint(Stream::Stdout, "✓", Color::Green)
+            } else {
+                paint(Stream::Stdout, "✗ missing", Color::Red)
+            };
+            writeln!(out, "{name:width$}  {marker}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::Path;
+
+    use rstest::*;
+
+    use super::search;
+    use crate::Config;
+
+    #[rstest]
+    fn match_workspaces_and_projects() {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  clients/acme:
+    projects:
+      backend-platform-api-service:
+      web:
+  oss:
+    projects:
+      api-docs:
+"#,
+        )
+        .unwrap();
+
+        let found = |pattern: &str| {
+            search(&config, pattern)
+                .matches
+                .into_iter()
+                .map(|m| m.path.strip_prefix("/some/root").unwrap().to_path_buf())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            found("acme"),
+            [
+                "clients/acme",
+                "clients/acme/backend-platform-api-service",
+                "clients/acme/web"
+            ]
+            .map(|p| Path::new(p).to_path_buf())
+        );
+        assert_eq!(
+            found("apisvc").first().map(|p| p.as_path()),
+            Some(Path::new("clients/acme/backend-platform-api-service"))
+        );
+        assert!(found("zzz").is_empty());
+    }
+}
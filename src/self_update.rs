@@ -0,0 +1,160 @@
+use std::{env, fs};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{upstream, WorkspacesError};
+
+const RELEASES: &str = "https://api.github.com/repos/czifro/dev-workspaces/releases/latest";
+
+#[derive(Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+pub enum SelfUpdate {
+    UpToDate(String),
+    Available(String),
+    Updated(String),
+}
+
+impl SelfUpdate {
+    pub fn print(&self) {
+        let current = env!("CARGO_PKG_VERSION");
+        match self {
+            Self::UpToDate(latest) => {
+                println!("workspaces {current} is up to date (latest release is {latest})")
+            }
+            Self::Available(latest) => {
+                println!("workspaces {latest} is available, currently running {current}")
+            }
+            Self::Updated(latest) => println!("Updated workspaces from {current} to {latest}"),
+        }
+    }
+}
+
+/// Target triple release binaries are published under, e.g.
+/// `workspaces-x86_64-unknown-linux-gnu`.
+fn target_triple() -> Result<String> {
+    let arch = env::consts::ARCH;
+    let vendor_os = match env::consts::OS {
+        "linux" => "unknown-linux-gnu",
+        "macos" => "apple-darwin",
+        "windows" => "pc-windows-msvc",
+        os => return Err(anyhow!("No release binaries are published for {os}")),
+    };
+    Ok(format!("{arch}-{vendor_os}"))
+}
+
+fn parse_version(v: &str) -> Vec<u64> {
+    v.trim_start_matches('v')
+        .split('.')
+        .map(|part| {
+            part.chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>()
+                .parse()
+                .unwrap_or(0)
+        })
+        .collect()
+}
+
+fn is_newer(latest: &str, current: &str) -> bool {
+    parse_version(latest) > parse_version(current)
+}
+
+pub fn self_update(check_only: bool, force: bool) -> Result<SelfUpdate, WorkspacesError> {
+    let release: Release = upstream::get_json(RELEASES, "GITHUB_TOKEN")?;
+    let latest = release.tag_name.trim_start_matches('v').to_string();
+
+    if !force && !is_newer(&latest, env!("CARGO_PKG_VERSION")) {
+        return Ok(SelfUpdate::UpToDate(latest));
+    }
+    if check_only {
+        return Ok(SelfUpdate::Available(latest));
+    }
+
+    let binary_name = format!("workspaces-{}{}", target_triple()?, env::consts::EXE_SUFFIX);
+    let checksum_name = format!("{binary_name}.sha256");
+    let find_asset = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|a| a.name == name)
+            .ok_or_else(|| anyhow!("Release {latest} has no {name} asset"))
+    };
+    let binary_asset = find_asset(&binary_name)?;
+    let checksum_asset = find_asset(&checksum_name)?;
+
+    let checksum = upstream::get_bytes(&checksum_asset.browser_download_url, 1024)?;
+    let checksum = String::from_utf8_lossy(&checksum);
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("{checksum_name} is empty"))?
+        .to_lowercase();
+
+    let binary = upstream::get_bytes(&binary_asset.browser_download_url, 256 * 1024 * 1024)?;
+    let actual = Sha256::digest(&binary)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+    if actual != expected {
+        return Err(anyhow!(
+            "Checksum mismatch for {binary_name}: expected {expected}, got {actual}"
+        )
+        .into());
+    }
+
+    replace_executable(&binary)?;
+
+    Ok(SelfUpdate::Updated(latest))
+}
+
+fn replace_executable(binary: &[u8]) -> Result<()> {
+    let exe = env::current_exe().context("Tried locating the running executable")?;
+    let exe = fs::canonicalize(&exe).unwrap_or(exe);
+    let staged = exe.with_extension("new");
+
+    fs::write(&staged, binary).context("Tried writing the new executable")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))
+            .context("Tried making the new executable runnable")?;
+    }
+
+    // Windows refuses to overwrite a running executable but allows renaming it
+    #[cfg(windows)]
+    {
+        let old = exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(&exe, &old).context("Tried moving the running executable aside")?;
+    }
+
+    fs::rename(&staged, &exe).context("Tried replacing the running executable")
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+
+    use super::is_newer;
+
+    #[rstest]
+    #[case("v0.4.0", "0.3.0", true)]
+    #[case("0.3.10", "0.3.9", true)]
+    #[case("v0.3.0", "0.3.0", false)]
+    #[case("0.2.9", "0.3.0", false)]
+    fn compare_release_versions(#[case] latest: &str, #[case] current: &str, #[case] newer: bool) {
+        assert_eq!(is_newer(latest, current), newer);
+    }
+}
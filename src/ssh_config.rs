@@ -0,0 +1,139 @@
+use std::{fs, sync::OnceLock};
+
+/// `Host` aliases in `~/.ssh/config` with a `HostName`.
+static ALIASES: OnceLock<Vec<SshAlias>> = OnceLock::new();
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SshAlias {
+    pub(crate) alias: String,
+    pub(crate) host_name: String,
+    pub(crate) identity_file: Option<String>,
+}
+
+/// The `~/.ssh/config` block of `alias`, if it defines one with a `HostName`.
+pub(crate) fn find_alias(alias: &str) -> Option<SshAlias> {
+    ALIASES
+        .get_or_init(|| {
+            home::home_dir()
+                .and_then(|home| fs::read_to_string(home.join(".ssh/config")).ok())
+                .map(|contents| parse_aliases(&contents))
+                .unwrap_or_default()
+        })
+        .iter()
+        .find(|a| a.alias == alias)
+        .cloned()
+}
+
+/// Every plain `Host` name of a block with a `HostName`. Patterns are
+/// skipped, they do not name a single alias.
+fn parse_aliases(contents: &str) -> Vec<SshAlias> {
+    let mut aliases = vec![];
+    let mut block: Vec<SshAlias> = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        let Some((keyword, value)) = line.split_once(|c: char| c.is_whitespace() || c == '=')
+        else {
+            continue;
+        };
+        let value = value.trim_start_matches(|c: char| c.is_whitespace() || c == '=');
+        let value = value.trim_matches('"').to_string();
+        match keyword.to_lowercase().as_str() {
+            "host" | "match" => {
+                aliases.extend(block.drain(..).filter(|a| !a.host_name.is_empty()));
+                if keyword.eq_ignore_ascii_case("host") {
+                    block = value
+                        .split_whitespace()
+                        .filter(|h| !h.contains(['*', '?', '!']))
+                        .map(|h| SshAlias {
+                            alias: h.to_string(),
+                            host_name: String::new(),
+                            identity_file: None,
+                        })
+                        .collect();
+                }
+            }
+            // The first value given for a keyword wins
+            "hostname" => block
+                .iter_mut()
+                .filter(|a| a.host_name.is_empty())
+                .for_each(|a| a.host_name = value.clone()),
+            "identityfile" => block
+                .iter_mut()
+                .filter(|a| a.identity_file.is_none())
+                .for_each(|a| a.identity_file = Some(value.clone())),
+            _ => {}
+        }
+    }
+    aliases.extend(block.into_iter().filter(|a| !a.host_name.is_empty()));
+    aliases
+}
+
+/// Puts `alias` in place of the host, and port, of the SSH URL `url`.
+pub(crate) fn with_alias(url: &str, alias: &str) -> String {
+    let (scheme, rest, separator) = match url.strip_prefix("ssh://") {
+        Some(rest) => ("ssh://", rest, '/'),
+        None => ("", url, ':'),
+    };
+    let Some((user, host_and_path)) = rest.split_once('@') else {
+        return url.to_string();
+    };
+    match host_and_path.find(separator) {
+        Some(i) => format!("{scheme}{user}@{alias}{}", &host_and_path[i..]),
+        None => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+
+    use super::{parse_aliases, with_alias, SshAlias};
+
+    #[rstest]
+    fn parse_host_aliases() {
+        let config = r#"
+Host work-github personal-*
+    HostName github.com
+    IdentityFile ~/.ssh/id_work
+
+Host=corp
+  User git
+  HostName="git.corp.com"
+
+Host no-hostname
+    User me
+"#;
+        assert_eq!(
+            parse_aliases(config),
+            vec![
+                SshAlias {
+                    alias: "work-github".to_string(),
+                    host_name: "github.com".to_string(),
+                    identity_file: Some("~/.ssh/id_work".to_string()),
+                },
+                SshAlias {
+                    alias: "corp".to_string(),
+                    host_name: "git.corp.com".to_string(),
+                    identity_file: None,
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case(
+        "git@github.com:czifro/dotfiles.git",
+        "git@work-github:czifro/dotfiles.git"
+    )]
+    #[case(
+        "ssh://git@git.corp.com:2222/team/repo.git",
+        "ssh://git@work-github/team/repo.git"
+    )]
+    #[case(
+        "https://github.com/czifro/dotfiles.git",
+        "https://github.com/czifro/dotfiles.git"
+    )]
+    fn swap_in_alias(#[case] url: &str, #[case] aliased: &str) {
+        assert_eq!(with_alias(url, "work-github"), aliased);
+    }
+}
@@ -0,0 +1,359 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{git::Git, Config, Render, WorkspacesError};
+
+/// Operations kept in the history, older ones are dropped.
+const MAX_HISTORY: usize = 1000;
+
+/// What was last done to each project, kept in
+/// `$XDG_STATE_HOME/workspaces/state.json` unless the config keeps it
+/// elsewhere.
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct State {
+    #[serde(default)]
+    projects: BTreeMap<PathBuf, ProjectRecord>,
+    /// Oldest first
+    #[serde(default)]
+    history: Vec<HistoryEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectRecord {
+    /// Unix time of the last successful restore
+    pub last_restored: Option<u64>,
+    /// Unix time of the last successful sync
+    pub last_synced: Option<u64>,
+    /// Commit checked out by the last restore or sync
+    pub commit: Option<String>,
+    /// Seconds the last restore took
+    pub clone_duration: Option<f32>,
+    /// Archive the project was packed into when it was removed, until it is
+    /// restored again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Restore,
+    Sync,
+    Archive,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix time the operation finished
+    pub at: u64,
+    pub operation: Operation,
+    pub path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The outcome of an operation on a project, `duration` is how long it took.
+pub(crate) struct Outcome {
+    pub(crate) path: PathBuf,
+    pub(crate) duration: Option<Duration>,
+    pub(crate) error: Option<String>,
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl State {
+    fn path(config: &Config) -> PathBuf {
+        config.state_dir().join("state.json")
+    }
+
+    fn load(config: &Config) -> Result<Self> {
+        let path = Self::path(config);
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("Tried parsing {}", path.to_string_lossy())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Tried reading {}", path.to_string_lossy())),
+        }
+    }
+
+    /// Replaces the state file in one step so readers never see half of it.
+    fn save(&self, config: &Config) -> Result<()> {
+        let path = Self::path(config);
+        fs::create_dir_all(config.state_dir()).context("Tried creating the state directory")?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?).context("Tried writing the state file")?;
+        fs::rename(&tmp, &path).context("Tried replacing the state file")
+    }
+
+    fn record(&mut self, config: &Config, operation: Operation, outcome: Outcome) {
+        let at = now();
+        if outcome.error.is_none() {
+            let record = self.projects.entry(outcome.path.clone()).or_default();
+            match operation {
+                Operation::Restore => {
+                    record.last_restored = Some(at);
+                    record.clone_duration = outcome.duration.map(|d| d.as_secs_f32());
+                    record.archive = None;
+                }
+                Operation::Sync => record.last_synced = Some(at),
+                Operation::Archive => {}
+            }
+            record.commit = config
+                .lookup_project(&outcome.path)
+                .ok()
+                .and_then(|p| p.git.clone())
+                .and_then(|g| Git::new(outcome.path.clone(), g).head_commit().ok());
+        }
+
+        self.history.push(HistoryEntry {
+            at,
+            operation,
+            path: outcome.path,
+            error: outcome.error,
+        });
+        let excess = self.history.len().saturating_sub(MAX_HISTORY);
+        self.history.drain(..excess);
+    }
+}
+
+/// Adds the outcomes of `operation` to the state file. The state only informs
+/// `history`, so failing to keep it is logged rather than failing the
+/// operation.
+pub(crate) fn record(config: &Config, operation: Operation, outcomes: Vec<Outcome>) {
+    if outcomes.is_empty() {
+        return;
+    }
+    let result = State::load(config).and_then(|mut state| {
+        for outcome in outcomes {
+            state.record(config, operation, outcome);
+        }
+        state.save(config)
+    });
+    match result {
+        Ok(()) => debug!("recorded {operation:?} in the state file"),
+        Err(e) => warn!("failed to record {operation:?} in the state file: {e:#}"),
+    }
+}
+
+/// Records that the project at `path` was packed into `archive` and removed.
+pub(crate) fn record_archive(config: &Config, path: &Path, archive: &Path) -> Result<()> {
+    let mut state = State::load(config)?;
+    state
+        .projects
+        .entry(path.to_path_buf())
+        .or_default()
+        .archive = Some(archive.to_path_buf());
+    state.history.push(HistoryEntry {
+        at: now(),
+        operation: Operation::Archive,
+        path: path.to_path_buf(),
+        error: None,
+    });
+    state.save(config)
+}
+
+/// Archived projects and their archives.
+pub(crate) fn archived_projects(config: &Config) -> BTreeMap<PathBuf, PathBuf> {
+    let state = State::load(config).unwrap_or_else(|e| {
+        warn!("failed to read the state file: {e:#}");
+        State::default()
+    });
+    state
+        .projects
+        .into_iter()
+        .filter_map(|(path, record)| Some((path, record.archive?)))
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct History {
+    #[serde(skip)]
+    root: PathBuf,
+    /// What was last done to the project asked about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<ProjectHistory>,
+    /// Newest first
+    entries: Vec<HistoryEntry>,
+}
+
+#[derive(Serialize)]
+pub struct ProjectHistory {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub record: ProjectRecord,
+}
+
+/// The last `limit` operations, on the project `query` names if given, see
+/// `Config::find_project`.
+pub fn history(
+    config: &Config,
+    query: Option<&str>,
+    limit: usize,
+) -> Result<History, WorkspacesError> {
+    let state = State::load(config)?;
+    let project = query.map(|q| config.find_project(q)).transpose()?;
+    let entries = state
+        .history
+        .iter()
+        .rev()
+        .filter(|e| project.as_ref().is_none_or(|p| e.path == *p))
+        .take(limit)
+        .cloned()
+        .collect();
+    let project = project.map(|path| ProjectHistory {
+        record: state.projects.get(&path).cloned().unwrap_or_default(),
+        path,
+    });
+
+    Ok(History {
+        root: config.root.clone(),
+        project,
+        entries,
+    })
+}
+
+/// How long ago the unix time `at` was, e.g. `5m ago`.
+fn ago(at: u64) -> String {
+    let secs = now().saturating_sub(at);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+impl Render for History {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        let relative = |p: &Path| p.strip_prefix(&self.root).unwrap_or(p).to_path_buf();
+        if let Some(ProjectHistory { path, record }) = &self.project {
+            let when = |at: Option<u64>| at.map_or("never".to_string(), ago);
+            writeln!(out, "{}", relative(path).to_string_lossy())?;
+            write!(out, "  Last restored  {}", when(record.last_restored))?;
+            match record.clone_duration {
+                Some(secs) => writeln!(out, ", took {secs:.1}s")?,
+                None => writeln!(out)?,
+            }
+            writeln!(out, "  Last synced    {}", when(record.last_synced))?;
+            if let Some(commit) = &record.commit {
+                writeln!(out, "  Commit         {commit}")?;
+            }
+            if let Some(archive) = &record.archive {
+                writeln!(out, "  Archived to    {}", archive.to_string_lossy())?;
+            }
+            writeln!(out)?;
+        }
+
+        if self.entries.is_empty() {
+            return writeln!(out, "No operations recorded yet");
+        }
+        let rows = self
+            .entries
+            .iter()
+            .map(|e| {
+                let operation = match e.operation {
+                    Operation::Restore => "restore",
+                    Operation::Sync => "sync",
+                    Operation::Archive => "archive",
+                };
+                let result = match &e.error {
+                    Some(error) => format!("failed: {}", error.replace('\n', " ")),
+                    None => "ok".to_string(),
+                };
+                [
+                    ago(e.at),
+                    operation.to_string(),
+                    relative(&e.path).to_string_lossy().to_string(),
+                    result,
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let header = ["WHEN", "OPERATION", "PROJECT", "RESULT"].map(String::from);
+        let mut widths = header.clone().map(|h| h.chars().count());
+        for row in rows.iter() {
+            for (w, cell) in widths.iter_mut().zip(row.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, w)| format!("{cell:w$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::{path::PathBuf, time::Duration};
+
+    use rstest::*;
+
+    use super::{HistoryEntry, Operation, Outcome, State, MAX_HISTORY};
+    use crate::Config;
+
+    #[rstest]
+    fn record_outcomes() {
+        let config = Config::from_str("root: /some/root\nworkspaces: {}\n").unwrap();
+        let mut state = State {
+            history: vec![
+                HistoryEntry {
+                    at: 0,
+                    operation: Operation::Sync,
+                    path: PathBuf::from("/old"),
+                    error: None,
+                };
+                MAX_HISTORY
+            ],
+            ..Default::default()
+        };
+
+        state.record(
+            &config,
+            Operation::Restore,
+            Outcome {
+                path: PathBuf::from("/some/root/a"),
+                duration: Some(Duration::from_secs(2)),
+                error: None,
+            },
+        );
+        state.record(
+            &config,
+            Operation::Sync,
+            Outcome {
+                path: PathBuf::from("/some/root/a"),
+                duration: None,
+                error: Some("diverged".to_string()),
+            },
+        );
+
+        let record = &state.projects[&PathBuf::from("/some/root/a")];
+        assert!(record.last_restored.is_some());
+        assert_eq!(record.clone_duration, Some(2.0));
+        assert!(record.last_synced.is_none());
+        assert_eq!(state.history.len(), MAX_HISTORY);
+        assert_eq!(
+            state.history[MAX_HISTORY - 1].error.as_deref(),
+            Some("diverged")
+        );
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::{
+    git::Git, Config, ProjectGitSettings, ProjectIssue, Render, Reporter, WorkspacesError,
+};
+
+#[derive(Serialize)]
+pub struct RepoStatus {
+    /// Checked out branch, or a description of a detached or unborn HEAD
+    pub branch: String,
+    /// Short id of the commit HEAD points at, none before the first commit
+    pub head: Option<String>,
+    /// Tracked files differ from HEAD, staged or not
+    pub dirty: bool,
+    pub untracked: usize,
+    /// Commits on the branch that its upstream lacks, none without an
+    /// upstream
+    pub ahead: Option<usize>,
+    /// Commits on the upstream that the branch lacks
+    pub behind: Option<usize>,
+}
+
+impl RepoStatus {
+    /// How the branch compares to its upstream, as of the last fetch.
+    pub fn describe_upstream(&self) -> String {
+        match (self.ahead, self.behind) {
+            (Some(0), Some(0)) => "up to date".into(),
+            (Some(ahead), Some(behind)) => [(ahead, "↑"), (behind, "↓")]
+                .iter()
+                .filter(|(n, _)| *n > 0)
+                .map(|(n, arrow)| format!("{arrow}{n}"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => "-".into(),
+        }
+    }
+}
+
+pub enum ProjectState {
+    Missing,
+    Unhealthy(ProjectIssue),
+    /// A worktree-strategy clone with no worktrees checked out
+    NoWorktrees,
+    Repo(RepoStatus),
+    Error(String),
+}
+
+impl ProjectState {
+    pub fn describe(&self) -> String {
+        match self {
+            ProjectState::Repo(s) => if s.dirty { "dirty" } else { "clean" }.to_string(),
+            ProjectState::Missing => "missing".into(),
+            ProjectState::Unhealthy(issue) => issue.describe(),
+            ProjectState::NoWorktrees => "no worktrees".into(),
+            ProjectState::Error(e) => format!("error: {e}"),
+        }
+    }
+}
+
+pub struct ProjectStatus {
+    pub path: PathBuf,
+    pub state: ProjectState,
+}
+
+pub struct StatusReport {
+    root: PathBuf,
+    pub projects: Vec<ProjectStatus>,
+    /// Projects that could not be fetched, with why
+    pub fetch_failures: Vec<(PathBuf, String)>,
+}
+
+pub struct StatusOptions {
+    /// Fetch from origin first, so ahead and behind are up to date
+    pub fetch: bool,
+    /// Projects to fetch at once
+    pub jobs: usize,
+}
+
+impl StatusReport {
+    pub fn print(&self) {
+        let rows = self
+            .projects
+            .iter()
+            .map(|p| {
+                let path = p
+                    .path
+                    .strip_prefix(&self.root)
+                    .unwrap_or(&p.path)
+                    .to_string_lossy()
+                    .to_string();
+                let (branch, upstream, untracked) = match &p.state {
+                    ProjectState::Repo(s) => (
+                        s.branch.clone(),
+                        s.describe_upstream(),
+                        s.untracked.to_string(),
+                    ),
+                    _ => ("-".into(), "-".into(), "-".into()),
+                };
+                let state = p.state.describe();
+                [path, branch, upstream, untracked, state]
+            })
+            .collect::<Vec<_>>();
+
+        let header = ["PROJECT", "BRANCH", "UPSTREAM", "UNTRACKED", "STATE"].map(String::from);
+        let mut widths = header.clone().map(|h| h.chars().count());
+        for row in rows.iter() {
+            for (w, cell) in widths.iter_mut().zip(row.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, w)| format!("{cell:w$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            println!("{}", line.trim_end());
+        }
+
+        for (path, e) in self.fetch_failures.iter() {
+            eprintln!("Failed to fetch {}: {e}", path.to_string_lossy());
+        }
+    }
+}
+
+pub fn status(
+    config: &Config,
+    opts: &StatusOptions,
+    reporter: &dyn Reporter,
+) -> Result<StatusReport, WorkspacesError> {
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter_map(|(path, p)| p.git.clone().map(|g| (path, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let fetch_failures = if opts.fetch {
+        fetch_all(&projects, opts.jobs, reporter)
+    } else {
+        vec![]
+    };
+
+    let statuses = projects
+        .into_iter()
+        .flat_map(|(path, proj_git)| project_status(path, proj_git))
+        .collect();
+
+    Ok(StatusReport {
+        root: config.root.clone(),
+        projects: statuses,
+        fetch_failures,
+    })
+}
+
+/// Fetches origin into every clone of `projects` on disk, `jobs` at a time,
+/// returning the ones that failed in path order.
+fn fetch_all(
+    projects: &[(PathBuf, ProjectGitSettings)],
+    jobs: usize,
+    reporter: &dyn Reporter,
+) -> Vec<(PathBuf, String)> {
+    let pending = Mutex::new(
+        projects
+            .iter()
+            .filter(|(path, _)| path.exists())
+            .collect::<VecDeque<_>>(),
+    );
+    let failures = Mutex::new(vec![]);
+    thread::scope(|s| {
+        for _ in 0..jobs.max(1) {
+            s.spawn(|| loop {
+                let next = pending.lock().unwrap().pop_front();
+                let Some((path, proj_git)) = next else {
+                    break;
+                };
+                let git = Git::new(path.clone(), proj_git.clone());
+                // Reported by the status itself
+                if git.inspect_layout().is_some() {
+                    continue;
+                }
+                if let Err(e) = git.fetch(reporter) {
+                    failures
+                        .lock()
+                        .unwrap()
+                        .push((path.clone(), format!("{e:#}")));
+                }
+            });
+        }
+    });
+
+    let mut failures = failures.into_inner().unwrap();
+    failures.sort_by(|a, b| a.0.cmp(&b.0));
+    failures
+}
+
+/// Status of the project at `path`, one per worktree for worktree-strategy
+/// clones.
+pub(crate) fn project_status(path: PathBuf, proj_git: ProjectGitSettings) -> Vec<ProjectStatus> {
+    if !path.exists() {
+        return vec![ProjectStatus {
+            path,
+            state: ProjectState::Missing,
+        }];
+    }
+
+    let git = Git::new(path.clone(), proj_git);
+    if let Some(issue) = git.inspect_layout() {
+        return vec![ProjectStatus {
+            path,
+            state: ProjectState::Unhealthy(issue),
+        }];
+    }
+
+    match git.status() {
+        Ok(repos) if repos.is_empty() => vec![ProjectStatus {
+            path,
+            state: ProjectState::NoWorktrees,
+        }],
+        Ok(repos) => repos
+            .into_iter()
+            .map(|(path, s)| ProjectStatus {
+                path,
+                state: ProjectState::Repo(s),
+            })
+            .collect(),
+        Err(e) => vec![ProjectStatus {
+            path,
+            state: ProjectState::Error(format!("{e:#}")),
+        }],
+    }
+}
+
+/// Projects with their git state and effective git settings, for
+/// `list projects --long`.
+#[derive(Serialize)]
+pub struct ProjectListing {
+    #[serde(skip)]
+    root: PathBuf,
+    /// A row per worktree for worktree-strategy clones
+    pub projects: Vec<ProjectDetails>,
+}
+
+#[derive(Serialize)]
+pub struct ProjectDetails {
+    pub path: PathBuf,
+    /// `clean`, `dirty`, `missing` or what is wrong with the clone
+    pub state: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dirty: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<String>,
+}
+
+/// Details of the projects at `paths`, in order. Opens every clone, so it
+/// takes a while on large configs.
+pub fn project_details(
+    config: &Config,
+    paths: &[PathBuf],
+) -> Result<ProjectListing, WorkspacesError> {
+    let mut projects = vec![];
+    for path in paths {
+        let Some(proj_git) = config.lookup_project(path)?.git.clone() else {
+            projects.push(ProjectDetails {
+                path: path.clone(),
+                state: if path.exists() { "-" } else { "missing" }.to_string(),
+                branch: None,
+                head: None,
+                dirty: None,
+                host: None,
+                strategy: None,
+            });
+            continue;
+        };
+
+        let git = Git::new(path.clone(), proj_git.clone());
+        let (host, strategy) = (git.host_domain(), git.strategy());
+        for s in project_status(path.clone(), proj_git) {
+            let repo = match &s.state {
+                ProjectState::Repo(repo) => Some(repo),
+                _ => None,
+            };
+            projects.push(ProjectDetails {
+                state: s.state.describe(),
+                branch: repo.map(|r| r.branch.clone()),
+                head: repo.and_then(|r| r.head.clone()),
+                dirty: repo.map(|r| r.dirty),
+                host: Some(host.clone()),
+                strategy: Some(strategy.to_string()),
+                path: s.path,
+            });
+        }
+    }
+
+    Ok(ProjectListing {
+        root: config.root.clone(),
+        projects,
+    })
+}
+
+impl Render for ProjectListing {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        let cell = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+        let rows = self
+            .projects
+            .iter()
+            .map(|p| {
+                [
+                    p.path
+                        .strip_prefix(&self.root)
+                        .unwrap_or(&p.path)
+                        .to_string_lossy()
+                        .to_string(),
+                    cell(&p.branch),
+                    cell(&p.head),
+                    p.state.clone(),
+                    cell(&p.host),
+                    cell(&p.strategy),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let header = ["PROJECT", "BRANCH", "HEAD", "STATE", "HOST", "STRATEGY"].map(String::from);
+        let mut widths = header.clone().map(|h| h.chars().count());
+        for row in rows.iter() {
+            for (w, cell) in widths.iter_mut().zip(row.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, w)| format!("{cell:w$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
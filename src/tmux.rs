@@ -0,0 +1,162 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{Config, WorkspacesError};
+
+/// How `workspaces tmux` lays out the window of each project.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TmuxSettings {
+    /// tmux layout of the panes, e.g. `main-vertical` or `tiled`
+    pub(crate) layout: Option<String>,
+    /// Command of each pane, an empty one leaves the pane at a shell
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) panes: Vec<String>,
+}
+
+/// Name of the session for the workspace at `ws_path` below `root`. tmux does
+/// not allow `.` and `:` in session names.
+fn session_name(root: &Path, ws_path: &Path) -> String {
+    ws_path
+        .strip_prefix(root)
+        .unwrap_or(ws_path)
+        .to_string_lossy()
+        .replace(['/', '.', ':'], "-")
+}
+
+/// Creates a tmux session for the workspace at `ws_path` with a window per
+/// project, unless one already exists, and returns its name.
+#[instrument(level = "debug", skip(config))]
+pub fn tmux_session(config: &Config, ws_path: &Path) -> Result<String, WorkspacesError> {
+    let ws = config.lookup_workspace(ws_path)?;
+    let ws_dir = config.resolve_path(ws_path);
+    let name = session_name(config.root_of(&ws_dir), &ws_dir);
+    if tmux(["has-session", "-t", &format!("={name}")]).is_ok() {
+        debug!(session = name, "session exists");
+        return Ok(name);
+    }
+
+    let settings = ws.tmux.as_ref().or(config.tmux.as_ref());
+    let mut windows = ws
+        .projects
+        .keys()
+        .map(|p| (p.clone(), ws_dir.join(p)))
+        .filter(|(_, dir)| dir.is_dir())
+        .collect::<Vec<(String, PathBuf)>>();
+    windows.sort();
+    if windows.is_empty() {
+        windows.push((name.clone(), ws_dir.clone()));
+    }
+
+    let target = format!("={name}:");
+    for (i, (window, dir)) in windows.iter().enumerate() {
+        let dir = dir.to_string_lossy();
+        let mut args = match i {
+            0 => vec!["new-session", "-s", &name],
+            _ => vec!["new-window", "-t", &target],
+        };
+        args.extend(["-d", "-P", "-F", "#{pane_id}", "-n", window, "-c", &dir]);
+        let pane = tmux(args).with_context(|| format!("Tried opening a window for {window}"))?;
+
+        if let Some(settings) = settings {
+            lay_out(&pane, &dir, settings)
+                .with_context(|| format!("Tried laying out the window for {window}"))?;
+        }
+    }
+
+    Ok(name)
+}
+
+/// Splits the window of `pane` into the configured panes and starts their
+/// commands.
+fn lay_out(pane: &str, dir: &str, settings: &TmuxSettings) -> Result<()> {
+    let mut panes = vec![pane.to_string()];
+    for _ in 1..settings.panes.len() {
+        let split = [
+            "split-window",
+            "-d",
+            "-P",
+            "-F",
+            "#{pane_id}",
+            "-t",
+            pane,
+            "-c",
+            dir,
+        ];
+        panes.push(tmux(split)?);
+        // Keep splitting the space evenly before the layout is applied
+        tmux(["select-layout", "-t", pane, "tiled"])?;
+    }
+    for (pane, command) in panes.iter().zip(settings.panes.iter()) {
+        if !command.is_empty() {
+            tmux(["send-keys", "-t", pane, command, "Enter"])?;
+        }
+    }
+    if let Some(layout) = &settings.layout {
+        tmux(["select-layout", "-t", pane, layout])?;
+    }
+    Ok(())
+}
+
+/// Attaches to the session `name`, or switches to it from inside tmux.
+pub fn tmux_attach(name: &str) -> Result<(), WorkspacesError> {
+    let command = match std::env::var_os("TMUX") {
+        Some(_) => "switch-client",
+        None => "attach-session",
+    };
+    let status = Command::new("tmux")
+        .args([command, "-t", &format!("={name}")])
+        .status()
+        .context("Tried running tmux")?;
+    if !status.success() {
+        return Err(anyhow!("tmux {command} exited with {status}").into());
+    }
+    Ok(())
+}
+
+fn tmux<I, S>(args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut cmd = Command::new("tmux");
+    cmd.args(args);
+    debug!(args = ?cmd.get_args().collect::<Vec<_>>(), "running tmux");
+    let output = cmd
+        .output()
+        .context("Tried running tmux, is it installed?")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tmux exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::Path;
+
+    use rstest::*;
+
+    use super::session_name;
+
+    #[rstest]
+    #[case("/some/root/src", "src")]
+    #[case("/some/root/src/nested", "src-nested")]
+    #[case("/some/root/dotted.name", "dotted-name")]
+    fn name_sessions_after_workspaces(#[case] ws_path: &str, #[case] name: &str) {
+        assert_eq!(
+            session_name(Path::new("/some/root"), Path::new(ws_path)),
+            name
+        );
+    }
+}
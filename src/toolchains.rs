@@ -0,0 +1,163 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, instrument};
+
+use crate::{Config, Event, Reporter};
+
+/// Version manager that installs the toolchains a project pins.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolchainManager {
+    Mise,
+    Asdf,
+}
+
+impl ToolchainManager {
+    fn program(&self) -> &'static str {
+        match self {
+            ToolchainManager::Mise => "mise",
+            ToolchainManager::Asdf => "asdf",
+        }
+    }
+
+    /// Files pinning tool versions that the manager reads
+    fn version_files(&self) -> &'static [&'static str] {
+        match self {
+            ToolchainManager::Mise => &[
+                ".tool-versions",
+                ".mise.toml",
+                "mise.toml",
+                ".config/mise.toml",
+                ".config/mise/config.toml",
+            ],
+            ToolchainManager::Asdf => &[".tool-versions"],
+        }
+    }
+
+    /// Directories of the project at `path` that pin tool versions. In a
+    /// worktree clone those are the worktrees rather than the project
+    /// directory.
+    fn pinned_dirs(&self, path: &Path) -> Vec<PathBuf> {
+        let mut dirs = vec![path.to_path_buf()];
+        if path.join(".bare").is_dir() {
+            let mut worktrees = fs::read_dir(path)
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|p| p.join(".git").is_file())
+                .collect::<Vec<_>>();
+            worktrees.sort();
+            dirs.extend(worktrees);
+        }
+        dirs.into_iter()
+            .filter(|dir| self.version_files().iter().any(|f| dir.join(f).is_file()))
+            .collect()
+    }
+}
+
+/// Installs the toolchains every project that was restored pins, with the
+/// manager the config's `toolchains` names. Returns the projects whose
+/// toolchains could not be installed, the others are not held up by them.
+#[instrument(level = "debug", skip_all)]
+pub(crate) fn install_toolchains(
+    config: &Config,
+    restored: &[(PathBuf, Result<()>)],
+    reporter: &dyn Reporter,
+) -> Vec<(PathBuf, anyhow::Error)> {
+    let Some(manager) = config.toolchains else {
+        return vec![];
+    };
+
+    restored
+        .iter()
+        .filter(|(_, r)| r.is_ok())
+        .flat_map(|(path, _)| {
+            manager
+                .pinned_dirs(path)
+                .into_iter()
+                .map(move |dir| (path, dir))
+        })
+        .filter_map(|(path, dir)| {
+            let installed = install(manager, &dir, reporter);
+            installed.err().map(|e| (path.clone(), e))
+        })
+        .collect()
+}
+
+#[instrument(level = "debug", skip(reporter))]
+fn install(manager: ToolchainManager, dir: &Path, reporter: &dyn Reporter) -> Result<()> {
+    let program = manager.program();
+    reporter.report(&Event::ToolchainsInstalling {
+        path: dir.to_path_buf(),
+        manager: program.to_string(),
+    });
+    let output = Command::new(program)
+        .arg("install")
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Tried running `{program} install`, is {program} installed?"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} install exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    debug!("installed toolchains");
+    Ok(())
+}
+
+#[cfg(test)]
+mod should {
+    use std::fs;
+
+    use rstest::*;
+
+    use super::ToolchainManager;
+
+    #[rstest]
+    fn find_pinned_versions_in_worktrees() {
+        let root =
+            std::env::temp_dir().join(format!("workspaces-toolchains-{}", std::process::id()));
+        for dir in ["plain", "mise", "worktree/.bare", "worktree/main"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("plain/.tool-versions"), "nodejs 20.11.0\n").unwrap();
+        fs::write(root.join("mise/mise.toml"), "[tools]\n").unwrap();
+        fs::write(root.join("worktree/.git"), "gitdir: ./.bare\n").unwrap();
+        fs::write(
+            root.join("worktree/main/.git"),
+            "gitdir: ../.bare/worktrees/main\n",
+        )
+        .unwrap();
+        fs::write(root.join("worktree/main/.tool-versions"), "rust 1.80.0\n").unwrap();
+
+        let pinned = |manager: ToolchainManager, dir: &str| {
+            manager
+                .pinned_dirs(&root.join(dir))
+                .into_iter()
+                .map(|p| p.strip_prefix(&root).unwrap().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+        let found = [
+            pinned(ToolchainManager::Mise, "plain"),
+            pinned(ToolchainManager::Mise, "mise"),
+            pinned(ToolchainManager::Asdf, "mise"),
+            pinned(ToolchainManager::Asdf, "worktree"),
+        ];
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            found,
+            [vec!["plain"], vec!["mise"], vec![], vec!["worktree/main"]]
+        );
+    }
+}
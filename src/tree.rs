@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crossterm::style::Color;
+use serde::Serialize;
+
+use crate::{
+    git::shell::{paint, Stream},
+    Config, Project, Render, Workspace,
+};
+
+/// The root with its workspaces and projects, nested as in the config.
+#[derive(Serialize)]
+pub struct ConfigTree {
+    pub root: PathBuf,
+    pub children: Vec<TreeNode>,
+    /// The other `roots` with the workspaces kept in them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub other_roots: Vec<ConfigTree>,
+}
+
+#[derive(Serialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub kind: NodeKind,
+    pub path: PathBuf,
+    pub exists: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TreeNode>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Workspace,
+    Project,
+}
+
+pub fn config_tree(config: &Config) -> ConfigTree {
+    let tree = |root: &Path| {
+        let workspaces = config
+            .workspaces
+            .iter()
+            .filter(|(_, ws)| config.workspace_root(ws) == root)
+            .map(|(name, ws)| (name.clone(), ws.clone()))
+            .collect();
+        ConfigTree {
+            root: root.to_path_buf(),
+            children: children(root, &workspaces, &HashMap::new()),
+            other_roots: vec![],
+        }
+    };
+    let mut roots = config.root_dirs().into_iter();
+    let mut main = tree(roots.next().unwrap());
+    main.other_roots = roots.map(tree).collect();
+    main
+}
+
+/// Nodes for `workspaces` and `projects` in `dir`, sorted by name.
+fn children(
+    dir: &Path,
+    workspaces: &HashMap<String, Workspace>,
+    projects: &HashMap<String, Project>,
+) -> Vec<TreeNode> {
+    let node = |name: &String, kind, children| {
+        let path = dir.join(name);
+        TreeNode {
+            name: name.clone(),
+            kind,
+            exists: path.exists(),
+            path,
+            children,
+        }
+    };
+    let mut nodes = workspaces
+        .iter()
+        .map(|(name, ws)| {
+            let nested = children(&dir.join(name), &ws.workspaces, &ws.projects);
+            node(name, NodeKind::Workspace, nested)
+        })
+        .chain(
+            projects
+                .keys()
+                .map(|name| node(name, NodeKind::Project, vec![])),
+        )
+        .collect::<Vec<_>>();
+    nodes.sort_by(|a, b| a.name.cmp(&b.name));
+    nodes
+}
+
+fn render_nodes(nodes: &[TreeNode], prefix: &str, out: &mut dyn Write) -> io::Result<()> {
+    for (i, n) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let name = match n.kind {
+            NodeKind::Workspace => format!("{}/", n.name),
+            NodeKind::Project => n.name.clone(),
+        };
+        let marker = if n.exists {
+            paint(Stream::Stdout, "✓", Color::Green)
+        } else {
+            paint(Stream::Stdout, "✗ missing", Color::Red)
+        };
+        writeln!(out, "{prefix}{branch}{name} {marker}")?;
+        render_nodes(&n.children, &format!("{prefix}{indent}"), out)?;
+    }
+    Ok(())
+}
+
+impl Render for ConfigTree {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", self.root.to_string_lossy())?;
+        render_nodes(&self.children, "", out)?;
+        for tree in self.other_roots.iter() {
+            writeln!(out)?;
+            tree.render_text(out)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use rstest::*;
+
+    use super::config_tree;
+    use crate::{Config, Render};
+
+    #[rstest]
+    fn render_nested_workspaces() {
+        let config = Config::from_str(
+            r#"---
+root: /nonexistent/root
+workspaces:
+  work:
+    projects:
+      api:
+      web:
+    workspaces:
+      infra:
+        projects:
+          terraform:
+  oss:
+"#,
+        )
+        .unwrap();
+
+        let mut out = vec![];
+        config_tree(&config).render_text(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "/nonexistent/root
+├── oss/ ✗ missing
+└── work/ ✗ missing
+    ├── api ✗ missing
+    ├── infra/ ✗ missing
+    │   └── terraform ✗ missing
+    └── web ✗ missing
+"
+        );
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{self, EnterAlternateScreen},
+};
+use ratatui::{
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{List, ListItem, ListState},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    git::Git,
+    hooks::{run_global_hook, GlobalHook},
+    lock::Lock,
+    open_project, restore,
+    state::{self, Operation, Outcome},
+    status::project_status,
+    Config, ProjectState, RestoreOption, SilentReporter, WorkspacesError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NodeKind {
+    Workspace,
+    Project,
+}
+
+struct Node {
+    path: PathBuf,
+    depth: usize,
+    kind: NodeKind,
+    /// Existence of a workspace, or branch and state of a project
+    status: Vec<Span<'static>>,
+}
+
+/// Workspaces and projects in tree order, each with its depth below the root.
+fn tree(config: &Config) -> Vec<(PathBuf, usize, NodeKind)> {
+    let workspaces = config
+        .collect_workspace_paths()
+        .into_iter()
+        .map(|p| (p, NodeKind::Workspace));
+    let projects = config
+        .collect_project_paths()
+        .into_iter()
+        .map(|p| (p, NodeKind::Project));
+    let mut nodes = workspaces
+        .chain(projects)
+        .map(|(path, kind)| {
+            let depth = config.relative_path(&path).components().count();
+            (path, depth, kind)
+        })
+        .collect::<Vec<_>>();
+    // Paths sort component by component, which puts children right after
+    // their parent
+    nodes.sort_by(|a, b| a.0.cmp(&b.0));
+    nodes
+}
+
+fn describe(config: &Config, path: &Path, kind: NodeKind) -> Vec<Span<'static>> {
+    if !path.exists() {
+        return vec!["missing".red()];
+    }
+    let proj_git = match kind {
+        NodeKind::Workspace => return vec![],
+        NodeKind::Project => config.lookup_project(path).ok().and_then(|p| p.git.clone()),
+    };
+    let Some(proj_git) = proj_git else {
+        return vec![];
+    };
+
+    let statuses = project_status(path.to_path_buf(), proj_git);
+    let mut spans = vec![];
+    for s in statuses {
+        if !spans.is_empty() {
+            spans.push(Span::raw(", "));
+        }
+        if s.path != path {
+            let worktree = s.path.strip_prefix(path).unwrap_or(&s.path);
+            spans.push(Span::raw(format!("{}: ", worktree.to_string_lossy())));
+        }
+        let state = s.state.describe();
+        match &s.state {
+            ProjectState::Repo(repo) => {
+                spans.push(format!("{} ", repo.branch).cyan());
+                spans.push(if repo.dirty {
+                    state.yellow()
+                } else {
+                    state.green()
+                });
+            }
+            _ => spans.push(state.red()),
+        }
+    }
+    spans
+}
+
+struct App<'a> {
+    config: &'a Config,
+    nodes: Vec<Node>,
+    collapsed: HashSet<PathBuf>,
+    list: ListState,
+    message: Option<Line<'static>>,
+    quit: bool,
+}
+
+impl<'a> App<'a> {
+    fn new(config: &'a Config) -> Self {
+        let nodes = tree(config)
+            .into_iter()
+            .map(|(path, depth, kind)| Node {
+                status: describe(config, &path, kind),
+                path,
+                depth,
+                kind,
+            })
+            .collect();
+        Self {
+            config,
+            nodes,
+            collapsed: HashSet::new(),
+            list: ListState::default().with_selected(Some(0)),
+            message: None,
+            quit: false,
+        }
+    }
+
+    /// Nodes that are not below a collapsed workspace.
+    fn visible(&self) -> Vec<&Node> {
+        self.nodes
+            .iter()
+            .filter(|n| {
+                !self
+                    .collapsed
+                    .iter()
+                    .any(|c| n.path != *c && n.path.starts_with(c))
+            })
+            .collect()
+    }
+
+    fn selected(&self) -> Option<(PathBuf, NodeKind)> {
+        let i = self.list.selected()?;
+        self.visible().get(i).map(|n| (n.path.clone(), n.kind))
+    }
+
+    /// Describes the nodes at or below `scope` again.
+    fn refresh(&mut self, scope: &Path) {
+        for n in self.nodes.iter_mut().filter(|n| n.path.starts_with(scope)) {
+            n.status = describe(self.config, &n.path, n.kind);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let items = self
+            .visible()
+            .into_iter()
+            .map(|n| {
+                let name = n.path.file_name().unwrap_or_default().to_string_lossy();
+                let marker = match n.kind {
+                    NodeKind::Workspace if self.collapsed.contains(&n.path) => "▸ ",
+                    NodeKind::Workspace => "▾ ",
+                    NodeKind::Project => "  ",
+                };
+                let mut spans = vec![
+                    Span::raw("  ".repeat(n.depth.saturating_sub(1))),
+                    Span::raw(marker),
+                    match n.kind {
+                        NodeKind::Workspace => Span::raw(name.to_string()).bold(),
+                        NodeKind::Project => Span::raw(name.to_string()),
+                    },
+                    Span::raw("  "),
+                ];
+                spans.extend(n.status.iter().cloned());
+                ListItem::new(Line::from(spans))
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items).highlight_style(Style::new().bg(Color::DarkGray));
+        frame.render_stateful_widget(list, main, &mut self.list);
+
+        let help =
+            Line::from("↑↓ move  enter fold  r restore  s sync  o open  F5 refresh  q quit".dim());
+        frame.render_widget(self.message.clone().unwrap_or(help), footer);
+    }
+
+    fn handle_key(&mut self, code: KeyCode, terminal: &mut DefaultTerminal) -> Result<()> {
+        let visible = self.visible().len();
+        self.message = None;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+            KeyCode::Down | KeyCode::Char('j')
+                if self.list.selected().is_some_and(|i| i + 1 < visible) =>
+            {
+                self.list.select_next()
+            }
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                if let Some((path, NodeKind::Workspace)) = self.selected() {
+                    if !self.collapsed.remove(&path) {
+                        self.collapsed.insert(path);
+                    }
+                }
+            }
+            KeyCode::Char('r') => {
+                if let Some((path, kind)) = self.selected() {
+                    self.busy(terminal, "Restoring", &path)?;
+                    self.restore(&path, kind);
+                }
+            }
+            KeyCode::Char('s') => {
+                if let Some((path, _)) = self.selected() {
+                    self.busy(terminal, "Syncing", &path)?;
+                    self.sync(&path);
+                }
+            }
+            KeyCode::Char('o') => {
+                if let Some((path, NodeKind::Project)) = self.selected() {
+                    self.open(terminal, &path)?;
+                }
+            }
+            KeyCode::F(5) => {
+                let root = self.config.root.clone();
+                self.busy(terminal, "Refreshing", &root)?;
+                self.refresh(&root);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Shows what is being done before blocking on it.
+    fn busy(&mut self, terminal: &mut DefaultTerminal, action: &str, path: &Path) -> Result<()> {
+        self.message = Some(Line::from(format!(
+            "{action} {}...",
+            self.relative(path).to_string_lossy()
+        )));
+        terminal.draw(|f| self.draw(f))?;
+        Ok(())
+    }
+
+    fn relative<'p>(&self, path: &'p Path) -> &'p Path {
+        self.config.relative_path(path)
+    }
+
+    fn report(&mut self, done: &str, path: &Path, result: Result<(), WorkspacesError>) {
+        let path = self.relative(path).to_string_lossy().to_string();
+        self.message = Some(match result {
+            Ok(_) => Line::from(format!("{done} {path}")).green(),
+            Err(e) => Line::from(format!("{e:#}").replace('\n', " ")).red(),
+        });
+    }
+
+    fn restore(&mut self, path: &Path, kind: NodeKind) {
+        let opt = match kind {
+            NodeKind::Workspace => RestoreOption::Workspace {
+                ws_path: path.to_path_buf(),
+                include_projects: true,
+            },
+            NodeKind::Project => RestoreOption::Project {
+                proj_path: path.to_path_buf(),
+            },
+        };
+        let result = restore(self.config, opt, 1, &SilentReporter);
+        self.refresh(path);
+        self.report("Restored", path, result);
+    }
+
+    /// Fast-forwards every cloned project at or below `path` to origin.
+    fn sync(&mut self, path: &Path) {
+        let _lock = match Lock::acquire(&self.config.lock_dir(), &SilentReporter) {
+            Ok(lock) => lock,
+            Err(e) => return self.report("Synced", path, Err(e.into())),
+        };
+        let projects = self
+            .config
+            .collect_projects()
+            .into_iter()
+            .filter(|(proj_path, _)| proj_path.starts_with(path) && proj_path.exists())
+            .filter_map(|(proj_path, project)| project.git.clone().map(|g| (proj_path, g)))
+            .collect::<Vec<_>>();
+        let total = projects.len();
+        if total > 0 {
+            let pre = run_global_hook(
+                self.config,
+                GlobalHook::PreSync,
+                path,
+                total,
+                None,
+                &SilentReporter,
+            );
+            if let Err(e) = pre {
+                return self.report("Synced", path, Err(e.into()));
+            }
+        }
+
+        let mut synced = 0;
+        let mut failures = vec![];
+        let mut outcomes = vec![];
+        for (proj_path, proj_git) in projects {
+            let started = Instant::now();
+            let result = Git::new(proj_path.clone(), proj_git).fast_forward(&SilentReporter);
+            outcomes.push(Outcome {
+                path: proj_path.clone(),
+                duration: Some(started.elapsed()),
+                error: result.as_ref().err().map(|e| format!("{e:#}")),
+            });
+            match result {
+                Ok(_) => synced += 1,
+                Err(e) => failures.push((proj_path, e)),
+            }
+        }
+        state::record(self.config, Operation::Sync, outcomes);
+        if total > 0 {
+            let done = Some((synced, failures.len()));
+            let post = run_global_hook(
+                self.config,
+                GlobalHook::PostSync,
+                path,
+                total,
+                done,
+                &SilentReporter,
+            );
+            if let Err(e) = post {
+                failures.push((self.config.root.clone(), e));
+            }
+        }
+        self.refresh(path);
+
+        let mut message = format!("Synced {synced} projects");
+        if let Some((first, e)) = failures.first() {
+            message.push_str(&format!(
+                ", {} failed: {}: {e:#}",
+                failures.len(),
+                self.relative(first).to_string_lossy()
+            ));
+        }
+        self.message = Some(if failures.is_empty() {
+            Line::from(message).green()
+        } else {
+            Line::from(message.replace('\n', " ")).red()
+        });
+    }
+
+    /// Leaves the terminal to the editor while it runs.
+    fn open(&mut self, terminal: &mut DefaultTerminal, path: &Path) -> Result<()> {
+        ratatui::restore();
+        let result = open_project(self.config, &path.to_string_lossy(), &SilentReporter);
+        terminal::enable_raw_mode().context("Tried switching the terminal to raw mode")?;
+        execute!(io::stdout(), EnterAlternateScreen)
+            .context("Tried switching to the alternate screen")?;
+        terminal.clear().context("Tried clearing the terminal")?;
+
+        self.refresh(path);
+        self.report("Opened", path, result.map(|_| ()));
+        Ok(())
+    }
+}
+
+/// Browses the workspace tree with the state of each project, restoring,
+/// syncing and opening them from the keyboard.
+pub fn ui(config: &Config) -> Result<(), WorkspacesError> {
+    let mut app = App::new(config);
+    let mut terminal = ratatui::try_init().context("Tried setting up the terminal")?;
+    let result = run(&mut app, &mut terminal);
+    ratatui::restore();
+    Ok(result?)
+}
+
+fn run(app: &mut App, terminal: &mut DefaultTerminal) -> Result<()> {
+    while !app.quit {
+        terminal
+            .draw(|f| app.draw(f))
+            .context("Tried drawing the terminal")?;
+        if let Event::Key(key) = event::read().context("Tried reading input")? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key.code, terminal)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::PathBuf;
+
+    use rstest::*;
+
+    use super::{tree, NodeKind};
+    use crate::Config;
+
+    #[rstest]
+    fn list_nodes_in_tree_order() {
+        let config = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  src:
+    projects:
+      zed:
+    workspaces:
+      nested:
+        projects:
+          api:
+  work:
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            tree(&config),
+            vec![
+                (PathBuf::from("/some/root/src"), 1, NodeKind::Workspace),
+                (
+                    PathBuf::from("/some/root/src/nested"),
+                    2,
+                    NodeKind::Workspace
+                ),
+                (
+                    PathBuf::from("/some/root/src/nested/api"),
+                    3,
+                    NodeKind::Project
+                ),
+                (PathBuf::from("/some/root/src/zed"), 2, NodeKind::Project),
+                (PathBuf::from("/some/root/work"), 1, NodeKind::Workspace),
+            ]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize};
+use ureq::config::RedirectAuthHeaders;
+
+use crate::git::GitHost;
+
+const USER_AGENT: &str = concat!("dev-workspaces/", env!("CARGO_PKG_VERSION"));
+
+/// Entries requested per page from listing APIs, the most they allow
+const PAGE_SIZE: usize = 100;
+
+/// Repository as returned by the GitHub and Gitea APIs
+#[derive(Deserialize)]
+struct ApiRepo {
+    full_name: String,
+}
+
+/// Asks the host where `repo` lives now. The returned slug differs from
+/// `repo` when the repository was renamed or transferred upstream.
+pub(crate) fn canonical_repo(host: &GitHost, repo: &str) -> Result<String> {
+    match host {
+        GitHost::GitHub => {
+            let url = format!("https://api.github.com/repos/{repo}");
+            get_json::<ApiRepo>(&url, "GITHUB_TOKEN").map(|r| r.full_name)
+        }
+        GitHost::GitLab => {
+            // The API does not follow renames, but the web UI redirects old
+            // paths to the new location
+            let base = format!("https://{host}/");
+            let response = ureq::Agent::config_builder()
+                .max_redirects(0)
+                .http_status_as_error(false)
+                .build()
+                .new_agent()
+                .get(&format!("{base}{repo}"))
+                .header("User-Agent", USER_AGENT)
+                .call()
+                .with_context(|| format!("Tried requesting {base}{repo}"))?;
+
+            let location = response
+                .headers()
+                .get("location")
+                .and_then(|l| l.to_str().ok())
+                .and_then(|l| l.strip_prefix(&base))
+                .map(|l| l.trim_end_matches('/'))
+                .filter(|l| !l.starts_with("users/"));
+            Ok(match location {
+                Some(l) if response.status().is_redirection() => l.to_string(),
+                _ => repo.to_string(),
+            })
+        }
+        GitHost::Codeberg | GitHost::Gitea(_) => {
+            // Gitea redirects the old path of a renamed repo to the new one
+            let url = format!("https://{host}/api/v1/repos/{repo}");
+            get_json::<ApiRepo>(&url, "GITEA_TOKEN").map(|r| r.full_name)
+        }
+        GitHost::Sourcehut | GitHost::AzureDevOps | GitHost::Custom(_) => {
+            Err(anyhow!("Checking for renames is not supported on {host}"))
+        }
+    }
+}
+
+/// Account as returned by the GitHub and Gitea APIs
+#[derive(Deserialize)]
+struct ApiUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct ApiNamespace {
+    id: u64,
+}
+
+/// Creates the empty repository `repo` on the host, in the namespace of the
+/// user `token` belongs to or of the organization or group named in `repo`.
+pub(crate) fn create_repo(host: &GitHost, repo: &str, private: bool, token: &str) -> Result<()> {
+    let (owner, name) = repo
+        .rsplit_once('/')
+        .ok_or_else(|| anyhow!("Expected {repo} to be owner/name"))?;
+    match host {
+        GitHost::GitHub | GitHost::Codeberg | GitHost::Gitea(_) => {
+            let api = match host {
+                GitHost::GitHub => "https://api.github.com".to_string(),
+                _ => format!("https://{host}/api/v1"),
+            };
+            let user = api_request(&format!("{api}/user"), token, None)?
+                .read_json::<ApiUser>()
+                .context("Tried parsing API response")?;
+            let url = if user.login == owner {
+                format!("{api}/user/repos")
+            } else {
+                format!("{api}/orgs/{owner}/repos")
+            };
+            let body = serde_json::json!({ "name": name, "private": private });
+            api_request(&url, token, Some(body))?;
+        }
+        GitHost::GitLab => {
+            let api = format!("https://{host}/api/v4");
+            let namespace = owner.replace('/', "%2F");
+            let namespace = api_request(&format!("{api}/namespaces/{namespace}"), token, None)?
+                .read_json::<ApiNamespace>()
+                .context("Tried parsing API response")?;
+            let visibility = if private { "private" } else { "public" };
+            let body = serde_json::json!({
+                "name": name,
+                "path": name,
+                "namespace_id": namespace.id,
+                "visibility": visibility,
+            });
+            api_request(&format!("{api}/projects"), token, Some(body))?;
+        }
+        GitHost::Sourcehut | GitHost::AzureDevOps | GitHost::Custom(_) => {
+            return Err(anyhow!("Creating repositories is not supported on {host}"));
+        }
+    }
+    Ok(())
+}
+
+/// Sends an authenticated request to a host API, a POST of `body` if given
+/// and a GET otherwise.
+fn api_request(url: &str, token: &str, body: Option<serde_json::Value>) -> Result<ureq::Body> {
+    let agent = ureq::Agent::config_builder()
+        .redirect_auth_headers(RedirectAuthHeaders::SameHost)
+        .build()
+        .new_agent();
+    let auth = format!("Bearer {token}");
+    let response = match body {
+        Some(body) => agent
+            .post(url)
+            .header("User-Agent", USER_AGENT)
+            .header("Accept", "application/json")
+            .header("Authorization", &auth)
+            .send_json(body),
+        None => agent
+            .get(url)
+            .header("User-Agent", USER_AGENT)
+            .header("Accept", "application/json")
+            .header("Authorization", &auth)
+            .call(),
+    };
+    Ok(response
+        .with_context(|| format!("Tried requesting {url}"))?
+        .into_body())
+}
+
+/// A repository listed by a host API, see `list_github_repos` and
+/// `list_gitlab_repos`.
+pub(crate) struct HostedRepo {
+    /// Slug to clone it by, e.g. `owner/name`
+    pub(crate) repo: String,
+    pub(crate) name: String,
+    /// Subgroups leading to it below the imported group, e.g. `infra/aws`
+    pub(crate) dir: String,
+    pub(crate) archived: bool,
+    pub(crate) fork: bool,
+    pub(crate) topics: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GitHubRepo {
+    full_name: String,
+    name: String,
+    archived: bool,
+    fork: bool,
+    #[serde(default)]
+    topics: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GitHubAccount {
+    login: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+/// Every repository of the GitHub organization or user `owner`. Private
+/// repositories are included as far as `$GITHUB_TOKEN` can see them.
+pub(crate) fn list_github_repos(owner: &str) -> Result<Vec<HostedRepo>> {
+    let api = "https://api.github.com";
+    let account = get_json::<GitHubAccount>(&format!("{api}/users/{owner}"), "GITHUB_TOKEN")?;
+    let authenticated = std::env::var("GITHUB_TOKEN")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .map(|_| get_json::<ApiUser>(&format!("{api}/user"), "GITHUB_TOKEN"))
+        .transpose()?;
+    // The listing of a user only has their public repositories
+    let url = if authenticated.is_some_and(|u| u.login.eq_ignore_ascii_case(&account.login)) {
+        format!("{api}/user/repos?affiliation=owner")
+    } else if account.kind == "Organization" {
+        format!("{api}/orgs/{}/repos?type=all", account.login)
+    } else {
+        format!("{api}/users/{}/repos?type=owner", account.login)
+    };
+
+    let mut repos = vec![];
+    for page in 1.. {
+        let batch = get_json::<Vec<GitHubRepo>>(
+            &format!("{url}&per_page={PAGE_SIZE}&page={page}"),
+            "GITHUB_TOKEN",
+        )?;
+        let last = batch.len() < PAGE_SIZE;
+        repos.extend(batch.into_iter().map(|r| HostedRepo {
+            repo: r.full_name,
+            name: r.name,
+            dir: String::new(),
+            archived: r.archived,
+            fork: r.fork,
+            topics: r.topics,
+        }));
+        if last {
+            break;
+        }
+    }
+    Ok(repos)
+}
+
+#[derive(Deserialize)]
+struct GitLabGroup {
+    full_path: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabProject {
+    path: String,
+    path_with_namespace: String,
+    namespace: GitLabGroup,
+    archived: bool,
+    forked_from_project: Option<serde_json::Value>,
+    #[serde(default)]
+    topics: Vec<String>,
+}
+
+/// Every project of the GitLab group `group` on `host`, and of its subgroups
+/// if `recursive`. Private projects are included as far as `$GITLAB_TOKEN`
+/// can see them.
+pub(crate) fn list_gitlab_repos(
+    host: &GitHost,
+    group: &str,
+    recursive: bool,
+) -> Result<Vec<HostedRepo>> {
+    let api = format!("https://{host}/api/v4");
+    let id = group.trim_matches('/').replace('/', "%2F");
+    let group = get_json::<GitLabGroup>(&format!("{api}/groups/{id}"), "GITLAB_TOKEN")?;
+    let url = format!("{api}/groups/{id}/projects?include_subgroups={recursive}&order_by=path");
+
+    let mut repos = vec![];
+    for page in 1.. {
+        let batch = get_json::<Vec<GitLabProject>>(
+            &format!("{url}&per_page={PAGE_SIZE}&page={page}"),
+            "GITLAB_TOKEN",
+        )?;
+        let last = batch.len() < PAGE_SIZE;
+        repos.extend(batch.into_iter().map(|p| {
+            let dir = p
+                .namespace
+                .full_path
+                .strip_prefix(&group.full_path)
+                .unwrap_or_default()
+                .trim_start_matches('/')
+                .to_string();
+            HostedRepo {
+                repo: p.path_with_namespace,
+                name: p.path,
+                dir,
+                archived: p.archived,
+                fork: p.forked_from_project.is_some(),
+                topics: p.topics,
+            }
+        }));
+        if last {
+            break;
+        }
+    }
+    Ok(repos)
+}
+
+pub(crate) fn get_json<T: DeserializeOwned>(url: &str, token_env: &str) -> Result<T> {
+    let mut request = ureq::Agent::config_builder()
+        .redirect_auth_headers(RedirectAuthHeaders::SameHost)
+        .build()
+        .new_agent()
+        .get(url)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/json");
+    if let Ok(token) = std::env::var(token_env) {
+        request = request.header("Authorization", format!("Bearer {token}"));
+    }
+
+    request
+        .call()
+        .with_context(|| format!("Tried requesting {url}"))?
+        .body_mut()
+        .read_json()
+        .context("Tried parsing API response")
+}
+
+pub(crate) fn get_bytes(url: &str, limit: u64) -> Result<Vec<u8>> {
+    ureq::get(url)
+        .header("User-Agent", USER_AGENT)
+        .call()
+        .with_context(|| format!("Tried downloading {url}"))?
+        .body_mut()
+        .with_config()
+        .limit(limit)
+        .read_to_vec()
+        .with_context(|| format!("Tried downloading {url}"))
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::{git::Git, Config, WorkspacesError};
+
+pub struct RepoVerification {
+    pub path: PathBuf,
+    pub problems: Vec<String>,
+}
+
+pub struct VerifyReport {
+    pub repos: Vec<RepoVerification>,
+}
+
+impl VerifyReport {
+    pub fn failed(&self) -> Vec<&RepoVerification> {
+        self.repos
+            .iter()
+            .filter(|r| !r.problems.is_empty())
+            .collect()
+    }
+
+    pub fn print(&self) {
+        for r in self.repos.iter() {
+            let path = r.path.to_string_lossy();
+            if r.problems.is_empty() {
+                println!("ok\t{path}");
+                continue;
+            }
+            println!("FAILED\t{path}");
+            for problem in r.problems.iter() {
+                println!("\t\t{problem}");
+            }
+        }
+        println!();
+
+        println!(
+            "Verified {} repositories, {} with problems",
+            self.repos.len(),
+            self.failed().len()
+        );
+    }
+}
+
+pub fn verify(config: &Config, fsck: bool) -> Result<VerifyReport, WorkspacesError> {
+    let mut projects = config
+        .collect_projects()
+        .into_iter()
+        .filter(|(path, _)| path.exists())
+        .filter_map(|(path, p)| p.git.clone().map(|g| (path, g)))
+        .collect::<Vec<_>>();
+    projects.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let repos = projects
+        .into_iter()
+        .map(|(path, proj_git)| {
+            let problems = Git::new(path.clone(), proj_git)
+                .verify(fsck)
+                .unwrap_or_else(|e| vec![format!("{e:#}")]);
+            RepoVerification { path, problems }
+        })
+        .collect();
+
+    Ok(VerifyReport { repos })
+}
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use tracing::{debug, instrument};
+
+use crate::{doctor, restore, Config, DoctorDiagnosis, Reporter, RestoreOption, WorkspacesError};
+
+/// How often the config files are checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct WatchOptions {
+    /// Time between doctor runs
+    pub interval: Duration,
+    /// Restore the workspaces and projects added to the config
+    pub restore: bool,
+    /// Number of projects to clone concurrently, the config's `jobs` if not set
+    pub jobs: Option<usize>,
+}
+
+/// What `watch` noticed, for the front end to show.
+pub enum WatchEvent {
+    /// The config changed, `added` holds the new workspaces and projects
+    ConfigReloaded {
+        added: Vec<PathBuf>,
+    },
+    /// The config changed but could not be loaded, the previous one stays in
+    /// use
+    ConfigInvalid(WorkspacesError),
+    /// Doctor found something different from its previous run
+    Diagnosis(DoctorDiagnosis),
+    DoctorFailed(WorkspacesError),
+    Restored(PathBuf),
+    RestoreFailed(PathBuf, WorkspacesError),
+}
+
+/// Modification times of the config file and the fragments it includes.
+fn config_mtimes() -> Vec<(PathBuf, Option<SystemTime>)> {
+    let Ok(path) = Config::source_path() else {
+        return vec![];
+    };
+    let mut files = vec![path.clone()];
+    // Only the `include` globs are needed, a broken config is still watched
+    // for the fix
+    if let Some(c) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_yaml::from_str::<Config>(&contents).ok())
+    {
+        files.extend(c.included_files(&path).unwrap_or_default());
+    }
+    files
+        .into_iter()
+        .map(|f| {
+            let mtime = fs::metadata(&f).and_then(|m| m.modified()).ok();
+            (f, mtime)
+        })
+        .collect()
+}
+
+/// Workspaces and projects in `new` that are not in `old`, leaving out those
+/// below an added workspace since restoring it covers them.
+fn added_entries(old: &Config, new: &Config) -> Vec<PathBuf> {
+    let known = old
+        .collect_workspace_paths()
+        .into_iter()
+        .chain(old.collect_project_paths())
+        .collect::<HashSet<PathBuf>>();
+    let mut added = new
+        .collect_workspace_paths()
+        .into_iter()
+        .chain(new.collect_project_paths())
+        .filter(|p| !known.contains(p))
+        .collect::<Vec<PathBuf>>();
+    added.sort();
+
+    let mut top = Vec::<PathBuf>::new();
+    for path in added {
+        if !top.iter().any(|t| path.starts_with(t)) {
+            top.push(path);
+        }
+    }
+    top
+}
+
+/// Keeps running doctor every `interval` and reloads the config whenever it
+/// changes, restoring what was added to it with `restore`. Only returns when
+/// the config cannot be loaded to begin with.
+#[instrument(level = "debug", skip_all)]
+pub fn watch(
+    opts: &WatchOptions,
+    reporter: &dyn Reporter,
+    mut on_event: impl FnMut(WatchEvent),
+) -> Result<(), WorkspacesError> {
+    let mut config = Config::from_config_file()?;
+    let mut mtimes = config_mtimes();
+    let mut last_diagnosis = None;
+    let mut next_doctor = Instant::now();
+
+    loop {
+        let current = config_mtimes();
+        if current != mtimes {
+            debug!("config changed");
+            mtimes = current;
+            match Config::from_config_file() {
+                Ok(new) => {
+                    let added = added_entries(&config, &new);
+                    config = new;
+                    on_event(WatchEvent::ConfigReloaded {
+                        added: added.clone(),
+                    });
+                    if opts.restore {
+                        let jobs = opts.jobs.unwrap_or_else(|| config.jobs());
+                        restore_added(&config, added, jobs, reporter, &mut on_event);
+                    }
+                    next_doctor = Instant::now();
+                }
+                Err(e) => on_event(WatchEvent::ConfigInvalid(e)),
+            }
+        }
+
+        if Instant::now() >= next_doctor {
+            match doctor(&config, &Default::default()) {
+                Ok(diagnosis) => {
+                    let summary = serde_json::to_value(&diagnosis).ok();
+                    if last_diagnosis.is_none() || summary != last_diagnosis {
+                        last_diagnosis = summary;
+                        on_event(WatchEvent::Diagnosis(diagnosis));
+                    }
+                }
+                Err(e) => on_event(WatchEvent::DoctorFailed(e)),
+            }
+            next_doctor = Instant::now() + opts.interval;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn restore_added(
+    config: &Config,
+    added: Vec<PathBuf>,
+    jobs: usize,
+    reporter: &dyn Reporter,
+    on_event: &mut impl FnMut(WatchEvent),
+) {
+    for path in added {
+        let opt = match config.lookup_project(&path) {
+            Ok(_) => RestoreOption::Project {
+                proj_path: path.clone(),
+            },
+            Err(_) => RestoreOption::Workspace {
+                ws_path: path.clone(),
+                include_projects: true,
+            },
+        };
+        match restore(config, opt, jobs, reporter) {
+            Ok(_) => on_event(WatchEvent::Restored(path)),
+            Err(e) => on_event(WatchEvent::RestoreFailed(path, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use std::path::PathBuf;
+
+    use rstest::*;
+
+    use super::added_entries;
+    use crate::Config;
+
+    #[rstest]
+    fn find_added_entries() {
+        let old = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  src:
+    projects:
+      a:
+"#,
+        )
+        .unwrap();
+        let new = Config::from_str(
+            r#"---
+root: /some/root
+workspaces:
+  src:
+    projects:
+      a:
+      b:
+  work:
+    projects:
+      c:
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            added_entries(&old, &new),
+            vec![
+                PathBuf::from("/some/root/src/b"),
+                PathBuf::from("/some/root/work")
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::anyhow;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::{
+    git::Git, lock::Lock, Config, ProjectGitSettings, Render, RepoStatus, Reporter, WorkspacesError,
+};
+
+#[derive(Serialize)]
+pub struct WorktreeList {
+    pub project: PathBuf,
+    pub worktrees: Vec<WorktreeEntry>,
+}
+
+#[derive(Serialize)]
+pub struct WorktreeEntry {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub status: RepoStatus,
+}
+
+/// The path and git settings of the worktree-strategy project `query` names,
+/// see `Config::find_project`.
+fn worktree_project(
+    config: &Config,
+    query: &str,
+) -> Result<(PathBuf, ProjectGitSettings), WorkspacesError> {
+    let proj_path = config.find_project(query)?;
+    let proj_git = config
+        .lookup_project(&proj_path)?
+        .git
+        .clone()
+        .ok_or_else(|| anyhow!("{} has no git settings", proj_path.to_string_lossy()))?;
+    if !Git::new(proj_path.clone(), proj_git.clone()).is_worktree() {
+        return Err(anyhow!(
+            "{} does not use the worktree clone strategy",
+            proj_path.to_string_lossy()
+        )
+        .into());
+    }
+    if !proj_path.exists() {
+        return Err(anyhow!("{} does not exist", proj_path.to_string_lossy()).into());
+    }
+    Ok((proj_path, proj_git))
+}
+
+/// Checks out `branch` in a new worktree at `<project>/<branch>`, returning its
+/// path.
+#[instrument(level = "debug", skip(config, reporter))]
+pub fn add_worktree(
+    config: &Config,
+    query: &str,
+    branch: &str,
+    reporter: &dyn Reporter,
+) -> Result<PathBuf, WorkspacesError> {
+    let (proj_path, proj_git) = worktree_project(config, query)?;
+    let _lock = Lock::acquire(&config.lock_dir(), reporter)?;
+    Ok(Git::new(proj_path, proj_git).add_branch_worktree(branch)?)
+}
+
+pub fn list_worktrees(config: &Config, query: &str) -> Result<WorktreeList, WorkspacesError> {
+    let (proj_path, proj_git) = worktree_project(config, query)?;
+    let worktrees = Git::new(proj_path.clone(), proj_git)
+        .status()?
+        .into_iter()
+        .map(|(path, status)| WorktreeEntry { path, status })
+        .collect();
+    Ok(WorktreeList {
+        project: proj_path,
+        worktrees,
+    })
+}
+
+/// Removes the worktree of `branch`, keeping the branch itself. Worktrees with
+/// changes are only removed with `force`.
+#[instrument(level = "debug", skip(config, reporter))]
+pub fn remove_worktree(
+    config: &Config,
+    query: &str,
+    branch: &str,
+    force: bool,
+    reporter: &dyn Reporter,
+) -> Result<PathBuf, WorkspacesError> {
+    let (proj_path, proj_git) = worktree_project(config, query)?;
+    let _lock = Lock::acquire(&config.lock_dir(), reporter)?;
+    Ok(Git::new(proj_path, proj_git).remove_branch_worktree(branch, force)?)
+}
+
+impl Render for WorktreeList {
+    fn render_text(&self, out: &mut dyn Write) -> io::Result<()> {
+        if self.worktrees.is_empty() {
+            return writeln!(out, "No worktrees checked out");
+        }
+        let rows = self
+            .worktrees
+            .iter()
+            .map(|w| {
+                let path = w.path.strip_prefix(&self.project).unwrap_or(&w.path);
+                let state = if w.status.dirty { "dirty" } else { "clean" };
+                [
+                    path.to_string_lossy().to_string(),
+                    w.status.branch.clone(),
+                    w.status.untracked.to_string(),
+                    state.to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        let header = ["WORKTREE", "BRANCH", "UNTRACKED", "STATE"].map(String::from);
+        let mut widths = header.clone().map(|h| h.chars().count());
+        for row in rows.iter() {
+            for (w, cell) in widths.iter_mut().zip(row.iter()) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, w)| format!("{cell:w$}"))
+                .collect::<Vec<String>>()
+                .join("  ");
+            writeln!(out, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}